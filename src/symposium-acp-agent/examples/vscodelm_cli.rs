@@ -38,9 +38,9 @@ struct Args {
 // Import vscodelm types - we need to make these pub or use a different approach
 use symposium_acp_agent::vscodelm::session_actor::AgentDefinition;
 use symposium_acp_agent::vscodelm::{
-    ChatRequestOptions, ContentPart, LmBackend, Message, ProvideResponseRequest, ROLE_ASSISTANT,
-    ROLE_USER, ResponseCompleteNotification, ResponsePartNotification, ToolDefinition, ToolMode,
-    VsCodeToLmBackend,
+    ChatRequestOptions, ClientCapabilities, ContentPart, LmBackend, Message,
+    ProvideResponseRequest, ROLE_ASSISTANT, ROLE_USER, ResponseCompleteNotification,
+    ResponsePartNotification, ToolDefinition, ToolMode, VsCodeToLmBackend,
 };
 
 /// The "average" tool that we provide to Claude Code.
@@ -233,6 +233,8 @@ async fn main() -> Result<()> {
                         tools: tools.clone(),
                         tool_mode: Some(ToolMode::Auto),
                     },
+                    capabilities: ClientCapabilities::default(),
+                    workspace: None,
                 })
                 .block_task()
                 .await?;
@@ -299,6 +301,8 @@ async fn main() -> Result<()> {
                             tools: tools.clone(),
                             tool_mode: Some(ToolMode::Auto),
                         },
+                        capabilities: ClientCapabilities::default(),
+                        workspace: None,
                     })
                     .block_task()
                     .await?;
@@ -6,20 +6,28 @@
 //! - Forwards messages to the conductor
 //! - Forwards notifications from the conductor back to the client
 
-use super::ConfigAgentMessage;
-use crate::registry::ComponentSourceExt;
-use crate::user_config::ModConfig;
+use super::proxy_supervisor::{CrashReporter, SupervisedProxy};
+use super::session_traces::{SessionTraces, SharedSessionTraces};
+use super::{ConfigAgentMessage, get_session_id};
+use crate::mods_lock::{self, ModsLock};
+use crate::registry::{self, ComponentSourceExt, ProgressSink};
+use crate::user_config::{FailurePolicy, GlobalAgentConfig, ModConfig};
+use crate::versions;
+use futures::StreamExt;
 use futures::channel::mpsc::UnboundedSender;
+use fxhash::FxHashSet;
 use sacp::link::{AgentToClient, ClientToAgent, ProxyToConductor};
 use sacp::schema::{
-    InitializeRequest, McpServer, NewSessionRequest, NewSessionResponse, PromptRequest,
-    PromptResponse,
+    InitializeRequest, LoadSessionRequest, LoadSessionResponse, McpServer, NewSessionRequest,
+    NewSessionResponse, PromptRequest, PromptResponse, SessionId,
 };
 use sacp::{DynComponent, JrConnectionCx, JrRequestCx, MessageCx};
 use sacp_conductor::{Conductor, McpBridgeMode};
 use sacp_tokio::AcpAgent;
-use std::path::PathBuf;
-use symposium_recommendations::{ComponentSource, ModKind};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use symposium_recommendations::ModKind;
 use tokio::sync::{mpsc, oneshot};
 
 /// Messages that can be sent to the ConductorActor.
@@ -28,8 +36,24 @@ pub enum ConductorMessage {
     NewSession {
         request: NewSessionRequest,
         request_cx: JrRequestCx<NewSessionResponse>,
+        starting: StartingSession,
     },
 
+    /// A request to load a session created by an earlier conductor.
+    /// The conductor will send SessionLoaded to ConfigAgent.
+    LoadSession {
+        request: LoadSessionRequest,
+        request_cx: JrRequestCx<LoadSessionResponse>,
+        starting: StartingSession,
+    },
+
+    /// Take over a session from a conductor with an older configuration.
+    /// The conductor loads the session and sends HandoverFinished to ConfigAgent.
+    Handover { session_id: SessionId },
+
+    /// A session moved to another conductor; close its trace.
+    ReleaseSession { session_id: SessionId },
+
     /// A prompt request for a session.
     Prompt {
         request: PromptRequest,
@@ -47,21 +71,59 @@ pub enum ConductorMessage {
         /// Channel to send the resume signal sender back to the caller.
         resume_tx_sender: oneshot::Sender<oneshot::Sender<()>>,
     },
+
+    /// Stop the conductor, along with its agent and mods.
+    Shutdown,
 }
 
 /// Handle for communicating with a ConductorActor.
 #[derive(Clone, Debug)]
 pub struct ConductorHandle {
     tx: mpsc::Sender<ConductorMessage>,
+    /// The agent and mods the conductor runs
+    config: Arc<Mutex<ConductorConfig>>,
+    /// How many [`StartingSession`]s there are for this conductor
+    starting: Arc<AtomicUsize>,
+}
+
+#[derive(Debug)]
+struct ConductorConfig {
+    agent: GlobalAgentConfig,
+    mods: Vec<ModConfig>,
+}
+
+/// A session request on its way through a conductor, from
+/// [`ConductorHandle::send_new_session`] (or `send_load_session`) until
+/// ConfigAgent has stored the session or the request failed.
+///
+/// While one exists, the conductor is not retired
+/// (see [`ConductorHandle::is_starting_sessions`]).
+#[derive(Debug)]
+pub struct StartingSession(Arc<AtomicUsize>);
+
+impl StartingSession {
+    fn new(starting: &Arc<AtomicUsize>) -> Self {
+        starting.fetch_add(1, Ordering::SeqCst);
+        Self(starting.clone())
+    }
+}
+
+impl Drop for StartingSession {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 impl ConductorHandle {
     /// Spawn a new conductor actor for the given agent and mods.
     ///
+    /// If the workspace has a lockfile, mods it contains run at their locked
+    /// versions (see [`crate::mods_lock`]).
+    ///
     /// Returns a handle for sending messages to the actor.
     pub async fn spawn(
         workspace_path: PathBuf,
-        agent: ComponentSource,
+        agent: GlobalAgentConfig,
         mods: Vec<ModConfig>,
         trace_dir: Option<&PathBuf>,
         config_agent_tx: UnboundedSender<ConfigAgentMessage>,
@@ -72,21 +134,90 @@ impl ConductorHandle {
         // Create the channel for receiving messages
         let (tx, rx) = mpsc::channel(32);
 
-        let handle = Self { tx: tx.clone() };
-
-        client_cx.spawn(run_actor(
-            workspace_path,
+        let handle = Self {
+            tx: tx.clone(),
+            config: Arc::new(Mutex::new(ConductorConfig {
+                agent: agent.clone(),
+                mods: mods.clone(),
+            })),
+            starting: Default::default(),
+        };
+
+        let actor = run_actor(
+            workspace_path.clone(),
             agent,
             mods,
             trace_dir.cloned(),
-            config_agent_tx,
+            config_agent_tx.clone(),
             handle.clone(),
             rx,
-        ))?;
+        );
+        client_cx.spawn(async move {
+            // A crashed conductor must not take down the other workspaces;
+            // the next session in this workspace starts a new one.
+            if let Err(error) = actor.await {
+                tracing::error!(?workspace_path, %error, "Conductor stopped");
+                config_agent_tx
+                    .unbounded_send(ConfigAgentMessage::ModWarning {
+                        workspace_path,
+                        message: format!(
+                            "Mods stopped ({}). Start a new session to restart them.",
+                            error
+                        ),
+                    })
+                    .ok();
+            }
+            Ok(())
+        })?;
 
         Ok(handle)
     }
 
+    /// Whether the actor has stopped, e.g. because the conductor failed.
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+
+    /// The agent the conductor runs.
+    pub fn agent(&self) -> GlobalAgentConfig {
+        self.config.lock().unwrap().agent.clone()
+    }
+
+    /// The mods the conductor was configured with (enabled or not).
+    pub fn mods(&self) -> Vec<ModConfig> {
+        self.config.lock().unwrap().mods.clone()
+    }
+
+    /// Whether the conductor runs with this agent and these mods.
+    pub fn has_config(&self, agent: &GlobalAgentConfig, mods: &[ModConfig]) -> bool {
+        let config = self.config.lock().unwrap();
+        config.agent == *agent && config.mods == mods
+    }
+
+    /// Take over the pins recorded for the configuration the conductor started
+    /// with (see [`versions::record_mod_versions`]), so that it still counts as
+    /// running the configuration once the pins are saved.
+    ///
+    /// The conductor resolved its unpinned agent and mods to their newest
+    /// versions when it started, which are the versions that were pinned.
+    pub fn adopt_pins(&self, agent: Option<&GlobalAgentConfig>, mods: &[ModConfig]) {
+        let mut config = self.config.lock().unwrap();
+        if let Some(agent) = agent {
+            versions::copy_agent_pin(agent, &mut config.agent);
+        }
+        versions::copy_mod_pins(mods, &mut config.mods);
+    }
+
+    /// Whether a session is being created or loaded on the conductor.
+    pub fn is_starting_sessions(&self) -> bool {
+        self.starting.load(Ordering::SeqCst) > 0
+    }
+
+    /// Whether both handles talk to the same conductor.
+    pub fn same_conductor(&self, other: &ConductorHandle) -> bool {
+        self.tx.same_channel(&other.tx)
+    }
+
     /// Send a new session request to the conductor.
     /// The conductor will send NewSessionCreated to ConfigAgent when done.
     pub async fn send_new_session(
@@ -100,11 +231,48 @@ impl ConductorHandle {
             .send(ConductorMessage::NewSession {
                 request,
                 request_cx,
+                starting: StartingSession::new(&self.starting),
+            })
+            .await
+            .map_err(|_| sacp::util::internal_error("Conductor actor closed"))
+    }
+
+    /// Send a request to load a session to the conductor.
+    /// The conductor will send SessionLoaded to ConfigAgent when done.
+    pub async fn send_load_session(
+        &self,
+        request: LoadSessionRequest,
+        request_cx: JrRequestCx<LoadSessionResponse>,
+    ) -> Result<(), sacp::Error> {
+        tracing::debug!(?request, "ConductorHandle::send_load_session");
+
+        self.tx
+            .send(ConductorMessage::LoadSession {
+                request,
+                request_cx,
+                starting: StartingSession::new(&self.starting),
             })
             .await
             .map_err(|_| sacp::util::internal_error("Conductor actor closed"))
     }
 
+    /// Ask the conductor to take over a session from another conductor.
+    /// The conductor will send HandoverFinished to ConfigAgent when done.
+    pub async fn send_handover(&self, session_id: SessionId) -> Result<(), sacp::Error> {
+        self.tx
+            .send(ConductorMessage::Handover { session_id })
+            .await
+            .map_err(|_| sacp::util::internal_error("Conductor actor closed"))
+    }
+
+    /// Tell the conductor that a session moved to another conductor.
+    pub async fn release_session(&self, session_id: SessionId) -> Result<(), sacp::Error> {
+        self.tx
+            .send(ConductorMessage::ReleaseSession { session_id })
+            .await
+            .map_err(|_| sacp::util::internal_error("Conductor actor closed"))
+    }
+
     /// Send a prompt request to the conductor.
     pub async fn send_prompt(
         &self,
@@ -149,82 +317,205 @@ impl ConductorHandle {
             .await
             .map_err(|_| sacp::util::internal_error("Conductor actor closed"))
     }
+
+    /// Stop the conductor once it has handled the messages sent before.
+    pub async fn shutdown(&self) -> Result<(), sacp::Error> {
+        self.tx
+            .send(ConductorMessage::Shutdown)
+            .await
+            .map_err(|_| sacp::util::internal_error("Conductor actor closed"))
+    }
 }
 
-/// Build proxy components from ComponentSources.
-async fn build_proxies(
-    mod_sources: Vec<ComponentSource>,
-) -> Result<Vec<DynComponent<ProxyToConductor>>, sacp::Error> {
-    let mut proxies = vec![];
-    for source in &mod_sources {
-        tracing::debug!(mod_name = %source.display_name(), "Resolving mod");
-        let server = source.resolve().await.map_err(|e| {
-            tracing::error!(
-                mod_name = %source.display_name(),
-                error = %e,
-                "Failed to resolve mod"
-            );
-            sacp::util::internal_error(format!(
-                "Failed to resolve {}: {}",
-                source.display_name(),
-                e
-            ))
-        })?;
-        proxies.push(DynComponent::new(AcpAgent::new(server)));
+/// How many mods are resolved (downloaded, installed, ...) at the same time
+const MAX_CONCURRENT_RESOLUTIONS: usize = 4;
+
+/// The agent and mods of a conductor, ready to be spawned.
+struct ResolvedComponents {
+    agent: McpServer,
+    /// Proxies in chain order, with the mod they came from
+    proxies: Vec<(ModConfig, McpServer)>,
+    mcp_servers: Vec<McpServer>,
+    /// Optional mods that were skipped because they failed to resolve
+    warnings: Vec<String>,
+}
+
+/// Resolve the agent and the enabled mods concurrently.
+///
+/// Optional mods that fail to resolve are left out (see [`FailurePolicy`]);
+/// proxies keep the order of `proxies`.
+async fn resolve_components(
+    workspace_path: &Path,
+    agent: GlobalAgentConfig,
+    proxies: &[ModConfig],
+    mcp_mods: &[ModConfig],
+) -> Result<ResolvedComponents, sacp::Error> {
+    // Locked mods run at their locked versions
+    let lock = ModsLock::load(workspace_path)
+        .map_err(|e| sacp::util::internal_error(format!("{:#}", e)))?
+        .map(Arc::new);
+
+    let agent_server = async move {
+        agent
+            .agent
+            .resolve_version(agent.version.as_deref())
+            .await
+            .map_err(|e| sacp::util::internal_error(format!("Failed to resolve agent: {}", e)))
+    };
+
+    let mods: Vec<ModConfig> = proxies.iter().chain(mcp_mods).cloned().collect();
+    let (agent, results) = futures::join!(agent_server, resolve_mods(mods.clone(), lock));
+    let agent = agent?;
+
+    let mut resolved = ResolvedComponents {
+        agent,
+        proxies: vec![],
+        mcp_servers: vec![],
+        warnings: vec![],
+    };
+    for ((m, result), index) in mods.into_iter().zip(results).zip(0..) {
+        let server = match result {
+            Ok(server) => server,
+            Err(e) => {
+                let mod_name = m.source.display_name();
+                tracing::error!(%mod_name, error = %e, "Failed to resolve mod");
+                match m.policy {
+                    FailurePolicy::Required => {
+                        return Err(sacp::util::internal_error(format!(
+                            "Failed to resolve required mod {}: {}",
+                            mod_name, e
+                        )));
+                    }
+                    FailurePolicy::Optional => {
+                        resolved
+                            .warnings
+                            .push(format!("Skipped mod `{}`: {}", mod_name, e));
+                        continue;
+                    }
+                }
+            }
+        };
+        if index < proxies.len() {
+            resolved.proxies.push((m, server));
+        } else {
+            resolved.mcp_servers.push(server);
+        }
     }
 
-    Ok(proxies)
+    Ok(resolved)
 }
 
-/// Build proxy components from ComponentSources.
-async fn build_mcp_servers(
-    mod_sources: Vec<ComponentSource>,
-) -> Result<Vec<McpServer>, sacp::Error> {
-    let mut servers = vec![];
-    for source in &mod_sources {
-        tracing::debug!(mod_name = %source.display_name(), "Resolving mod");
-        let server = source.resolve().await.map_err(|e| {
-            tracing::error!(
-                mod_name = %source.display_name(),
-                error = %e,
-                "Failed to resolve mod"
-            );
-            sacp::util::internal_error(format!(
-                "Failed to resolve {}: {}",
-                source.display_name(),
-                e
-            ))
-        })?;
-        servers.push(server);
+/// Resolve mods at their locked or pinned versions, at most
+/// [`MAX_CONCURRENT_RESOLUTIONS`] at a time. Results are returned in the order of `mods`.
+async fn resolve_mods(
+    mods: Vec<ModConfig>,
+    lock: Option<Arc<ModsLock>>,
+) -> Vec<anyhow::Result<McpServer>> {
+    futures::stream::iter(mods)
+        .map(move |m| {
+            let lock = lock.clone();
+            async move {
+                tracing::debug!(mod_name = %m.source.display_name(), version = ?m.version, "Resolving mod");
+                let server = mods_lock::resolve_mod(lock.as_deref(), &m).await?;
+                m.overrides.apply(server)
+            }
+        })
+        .buffered(MAX_CONCURRENT_RESOLUTIONS)
+        .collect()
+        .await
+}
+
+/// Answer the requests queued for a conductor that failed to start or stopped.
+async fn reject_pending(
+    rx: &mut mpsc::Receiver<ConductorMessage>,
+    error: &sacp::Error,
+    config_agent_tx: &UnboundedSender<ConfigAgentMessage>,
+    self_handle: &ConductorHandle,
+    workspace_path: &Path,
+) {
+    rx.close();
+    while let Some(message) = rx.recv().await {
+        let result = match message {
+            ConductorMessage::NewSession { request_cx, .. } => {
+                request_cx.respond_with_error(error.clone())
+            }
+            ConductorMessage::LoadSession { request_cx, .. } => {
+                request_cx.respond_with_error(error.clone())
+            }
+            ConductorMessage::Prompt { request_cx, .. } => {
+                request_cx.respond_with_error(error.clone())
+            }
+            ConductorMessage::ForwardMessage {
+                message: MessageCx::Request(_, request_cx),
+            } => request_cx.respond_with_error(error.clone()),
+            ConductorMessage::Handover { session_id } => {
+                send_handover_finished(
+                    config_agent_tx,
+                    session_id,
+                    self_handle.clone(),
+                    workspace_path.to_path_buf(),
+                    vec![],
+                    Err(error.to_string()),
+                );
+                Ok(())
+            }
+            ConductorMessage::ForwardMessage { .. }
+            | ConductorMessage::ReleaseSession { .. }
+            | ConductorMessage::Pause { .. }
+            | ConductorMessage::Shutdown => Ok(()),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to reject request: {}", e);
+        }
     }
+}
 
-    Ok(servers)
+/// Tell ConfigAgent how taking over a session went.
+fn send_handover_finished(
+    config_agent_tx: &UnboundedSender<ConfigAgentMessage>,
+    session_id: SessionId,
+    conductor: ConductorHandle,
+    workspace_path: PathBuf,
+    warnings: Vec<String>,
+    result: Result<(), String>,
+) {
+    config_agent_tx
+        .unbounded_send(ConfigAgentMessage::HandoverFinished {
+            session_id,
+            conductor,
+            workspace_path,
+            warnings,
+            result,
+        })
+        .ok();
 }
 
-/// Get enabled proxies from the list
-fn enabled_proxies(mods: &[ModConfig]) -> Vec<ComponentSource> {
-    mods.iter()
+/// Get enabled proxies from the list, in chain order (see `ModConfig::order`)
+fn enabled_proxies(mods: &[ModConfig]) -> Vec<ModConfig> {
+    let mut proxies: Vec<&ModConfig> = mods
+        .iter()
         .filter(|m| m.enabled)
         .filter(|m| matches!(m.kind, ModKind::Proxy))
-        .map(|m| m.source.clone())
-        .collect()
+        .collect();
+    proxies.sort_by_key(|m| m.order);
+    proxies.into_iter().cloned().collect()
 }
 
 /// Get enabled mcp servers from the list
-fn enabled_mcp_servers(mods: &[ModConfig]) -> Vec<ComponentSource> {
+fn enabled_mcp_servers(mods: &[ModConfig]) -> Vec<ModConfig> {
     mods.iter()
         .filter(|m| m.enabled)
         .filter(|m| matches!(m.kind, ModKind::MCP))
-        .map(|m| m.source.clone())
+        .cloned()
         .collect()
 }
 
 /// The main actor loop.
 async fn run_actor(
     workspace_path: PathBuf,
-    agent: ComponentSource,
+    agent: GlobalAgentConfig,
     mods: Vec<ModConfig>,
-    _trace_dir: Option<PathBuf>,
+    trace_dir: Option<PathBuf>,
     config_agent_tx: UnboundedSender<ConfigAgentMessage>,
     self_handle: ConductorHandle,
     mut rx: mpsc::Receiver<ConductorMessage>,
@@ -232,41 +523,137 @@ async fn run_actor(
     // Get enabled proxies
     let proxies = enabled_proxies(&mods);
 
-    // Resolve the agent
-    let agent_server = agent
-        .resolve()
-        .await
-        .map_err(|e| sacp::util::internal_error(format!("Failed to resolve agent: {}", e)))?;
-
     // MCP servers are represented as mods with `ModKind::MCP` in `mods`.
-    // Build MCP servers from enabled MCP-type mods so they can be attached to sessions.
-    let mcp_sources = enabled_mcp_servers(&mods);
-    let mcp_servers = build_mcp_servers(mcp_sources).await?;
-
-    // TODO: Apply trace_dir to conductor when needed
+    // They are resolved along with the proxies so they can be attached to sessions.
+    let mcp_mods = enabled_mcp_servers(&mods);
+
+    // Downloads and installs can take a while, so tell the workspace's sessions about them.
+    // Sessions requested while resolving are shown them once they are created.
+    let startup_progress: Arc<Mutex<Vec<String>>> = Default::default();
+    let progress: ProgressSink = {
+        let config_agent_tx = config_agent_tx.clone();
+        let workspace_path = workspace_path.clone();
+        let startup_progress = startup_progress.clone();
+        Arc::new(move |message: String| {
+            startup_progress.lock().unwrap().push(message.clone());
+            config_agent_tx
+                .unbounded_send(ConfigAgentMessage::ResolutionProgress {
+                    workspace_path: workspace_path.clone(),
+                    message,
+                })
+                .ok();
+        })
+    };
+    let resolved = registry::with_progress(
+        progress,
+        resolve_components(&workspace_path, agent, &proxies, &mcp_mods),
+    )
+    .await;
+    config_agent_tx
+        .unbounded_send(ConfigAgentMessage::ResolutionFinished {
+            workspace_path: workspace_path.clone(),
+            error: resolved.as_ref().err().map(|e| e.to_string()),
+        })
+        .ok();
+    let startup_progress = std::mem::take(&mut *startup_progress.lock().unwrap());
+    // The requests queued so far were waiting for this start
+    let mut waiting = rx.len();
+    let ResolvedComponents {
+        agent: agent_server,
+        proxies: proxy_servers,
+        mcp_servers,
+        warnings,
+    } = match resolved {
+        Ok(resolved) => resolved,
+        Err(error) => {
+            // The session requests that started this conductor can't be served
+            reject_pending(
+                &mut rx,
+                &error,
+                &config_agent_tx,
+                &self_handle,
+                &workspace_path,
+            )
+            .await;
+            return Ok(());
+        }
+    };
+
+    // Problems with mods are shown in the workspace's sessions
+    let report: CrashReporter = {
+        let config_agent_tx = config_agent_tx.clone();
+        let workspace_path = workspace_path.clone();
+        Arc::new(move |message| {
+            config_agent_tx
+                .unbounded_send(ConfigAgentMessage::ModWarning {
+                    workspace_path: workspace_path.clone(),
+                    message,
+                })
+                .ok();
+        })
+    };
+    for warning in &warnings {
+        report(warning.clone());
+    }
 
     let agent = AcpAgent::new(agent_server);
 
     // Build the conductor
-    let conductor = Conductor::new_agent(
+    let mut conductor = Conductor::new_agent(
         "symposium-conductor",
         {
             async move |init_req| {
                 tracing::info!(
                     "Building proxy chain with mods: {:?}",
-                    proxies.iter().map(|s| s.display_name()).collect::<Vec<_>>()
+                    proxy_servers
+                        .iter()
+                        .map(|(m, _)| m.source.display_name())
+                        .collect::<Vec<_>>()
                 );
-                let proxies = build_proxies(proxies).await?;
+                let proxies: Vec<DynComponent<ProxyToConductor>> = proxy_servers
+                    .into_iter()
+                    .map(|(m, server)| {
+                        DynComponent::new(SupervisedProxy::new(
+                            m.source.display_name(),
+                            server,
+                            m.policy,
+                            report.clone(),
+                        ))
+                    })
+                    .collect();
                 Ok((init_req, proxies, DynComponent::new(agent)))
             }
         },
         McpBridgeMode::default(),
     );
+    let traces = match &trace_dir {
+        Some(dir) => {
+            let traces = SessionTraces::new(dir, &workspace_path).map_err(|e| {
+                sacp::util::internal_error(format!("Failed to set up tracing: {}", e))
+            })?;
+            Some(SharedSessionTraces::new(traces))
+        }
+        None => None,
+    };
+    if let Some(traces) = &traces {
+        conductor = conductor.trace_to(traces.clone());
+    }
+
+    // Sessions being taken over; the agent replays their history, which the
+    // client has already seen
+    let replaying: Arc<Mutex<FxHashSet<SessionId>>> = Default::default();
 
     // Connect to the conductor
-    ClientToAgent::builder()
+    let result = ClientToAgent::builder()
         .on_receive_message(
             async |message_cx: MessageCx, _cx| {
+                if let MessageCx::Notification(_) = &message_cx
+                    && let Some(session_id) = get_session_id(&message_cx)?
+                    && replaying.lock().unwrap().contains(&session_id)
+                {
+                    return Ok(());
+                }
+
                 // Incoming message from the conductor: forward via ConfigAgent to client
                 config_agent_tx
                     .unbounded_send(ConfigAgentMessage::MessageToClient(message_cx))
@@ -276,24 +663,39 @@ async fn run_actor(
         )
         .run_until(conductor, async |conductor_cx| {
             // Initialize the conductor
-            let _init_response = conductor_cx
+            let init_response = conductor_cx
                 .send_request(InitializeRequest::new(
                     sacp::schema::ProtocolVersion::LATEST,
                 ))
                 .block_task()
                 .await?;
+            let can_load_sessions = init_response.agent_capabilities.load_session;
+            config_agent_tx
+                .unbounded_send(ConfigAgentMessage::AgentCapabilities {
+                    agent: self_handle.agent(),
+                    load_session: can_load_sessions,
+                })
+                .ok();
 
             while let Some(message) = rx.recv().await {
+                let progress = if waiting > 0 {
+                    waiting -= 1;
+                    startup_progress.clone()
+                } else {
+                    vec![]
+                };
                 match message {
                     ConductorMessage::NewSession {
                         mut request,
                         request_cx,
+                        starting,
                     } => {
                         request.mcp_servers.extend(mcp_servers.clone());
 
                         let config_agent_tx = config_agent_tx.clone();
                         let self_handle = self_handle.clone();
                         let workspace_path = workspace_path.clone();
+                        let warnings = warnings.clone();
                         conductor_cx.send_request(request).on_receiving_result(
                             async move |result| {
                                 match result {
@@ -304,7 +706,10 @@ async fn run_actor(
                                                 response,
                                                 conductor: self_handle,
                                                 workspace_path,
+                                                progress,
+                                                warnings,
                                                 request_cx,
+                                                starting,
                                             })
                                             .map_err(|_| {
                                                 sacp::util::internal_error("ConfigAgent closed")
@@ -319,6 +724,91 @@ async fn run_actor(
                         )?;
                     }
 
+                    ConductorMessage::LoadSession {
+                        mut request,
+                        request_cx,
+                        starting,
+                    } => {
+                        if !can_load_sessions {
+                            request_cx.respond_with_error(sacp::Error::new(
+                                -32601,
+                                "The agent doesn't support loading sessions",
+                            ))?;
+                            continue;
+                        }
+
+                        request.mcp_servers.extend(mcp_servers.clone());
+
+                        let config_agent_tx = config_agent_tx.clone();
+                        let self_handle = self_handle.clone();
+                        let workspace_path = workspace_path.clone();
+                        let warnings = warnings.clone();
+                        let session_id = request.session_id.clone();
+                        conductor_cx.send_request(request).on_receiving_result(
+                            async move |result| match result {
+                                Ok(response) => config_agent_tx
+                                    .unbounded_send(ConfigAgentMessage::SessionLoaded {
+                                        session_id,
+                                        response,
+                                        conductor: self_handle,
+                                        workspace_path,
+                                        progress,
+                                        warnings,
+                                        request_cx,
+                                        starting,
+                                    })
+                                    .map_err(|_| sacp::util::internal_error("ConfigAgent closed")),
+                                Err(e) => request_cx.respond_with_error(e),
+                            },
+                        )?;
+                    }
+
+                    ConductorMessage::Handover { session_id } => {
+                        if !can_load_sessions {
+                            send_handover_finished(
+                                &config_agent_tx,
+                                session_id,
+                                self_handle.clone(),
+                                workspace_path.clone(),
+                                warnings.clone(),
+                                Err("the agent doesn't support loading sessions".to_string()),
+                            );
+                            continue;
+                        }
+
+                        replaying.lock().unwrap().insert(session_id.clone());
+                        let request = LoadSessionRequest::new(session_id.clone(), &workspace_path)
+                            .mcp_servers(mcp_servers.clone());
+
+                        let config_agent_tx = config_agent_tx.clone();
+                        let self_handle = self_handle.clone();
+                        let workspace_path = workspace_path.clone();
+                        let warnings = warnings.clone();
+                        let replaying = replaying.clone();
+                        conductor_cx.send_request(request).on_receiving_result(
+                            async move |result| {
+                                replaying.lock().unwrap().remove(&session_id);
+                                send_handover_finished(
+                                    &config_agent_tx,
+                                    session_id,
+                                    self_handle,
+                                    workspace_path,
+                                    warnings,
+                                    result.map(|_| ()).map_err(|e| e.to_string()),
+                                );
+                                Ok(())
+                            },
+                        )?;
+                    }
+
+                    ConductorMessage::ReleaseSession { session_id } => {
+                        if let Some(traces) = &traces {
+                            traces.close(&session_id.0);
+                        }
+                    }
+
+                    ConductorMessage::Shutdown => break,
+
                     ConductorMessage::Prompt {
                         request,
                         request_cx,
@@ -360,5 +850,83 @@ async fn run_actor(
             tracing::debug!("Conductor actor shutting down");
             Ok(())
         })
-        .await
+        .await;
+
+    // Requests that arrived after `Shutdown` are answered rather than dropped
+    let error = match &result {
+        Err(error) => error.clone(),
+        Ok(()) => sacp::util::internal_error("The conductor stopped. Please try again."),
+    };
+    reject_pending(
+        &mut rx,
+        &error,
+        &config_agent_tx,
+        &self_handle,
+        &workspace_path,
+    )
+    .await;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use symposium_recommendations::{ComponentSource, LocalDistribution};
+
+    fn agent(command: &str) -> GlobalAgentConfig {
+        GlobalAgentConfig::new(ComponentSource::Local(LocalDistribution {
+            name: None,
+            command: command.to_string(),
+            args: vec![],
+            env: BTreeMap::new(),
+        }))
+    }
+
+    fn handle(agent: GlobalAgentConfig, mods: Vec<ModConfig>) -> ConductorHandle {
+        let (tx, _rx) = mpsc::channel(1);
+        ConductorHandle {
+            tx,
+            config: Arc::new(Mutex::new(ConductorConfig { agent, mods })),
+            starting: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_handle_config() {
+        let handle = handle(agent("elizacp"), vec![]);
+        assert!(handle.has_config(&agent("elizacp"), &[]));
+        assert!(!handle.has_config(&agent("claude-code"), &[]));
+
+        let (tx, _other_rx) = mpsc::channel(1);
+        let other = ConductorHandle {
+            tx,
+            ..handle.clone()
+        };
+        assert!(handle.same_conductor(&handle.clone()));
+        assert!(!handle.same_conductor(&other));
+    }
+
+    #[test]
+    fn test_adopt_pins() {
+        let handle = handle(agent("elizacp"), vec![]);
+        let mut pinned = agent("elizacp");
+        pinned.version = Some("1.0.0".to_string());
+        assert!(!handle.has_config(&pinned, &[]));
+
+        // Every clone of the handle sees the pins
+        handle.clone().adopt_pins(Some(&pinned), &[]);
+        assert!(handle.has_config(&pinned, &[]));
+    }
+
+    #[test]
+    fn test_starting_sessions() {
+        let handle = handle(agent("elizacp"), vec![]);
+        assert!(!handle.is_starting_sessions());
+
+        let starting = StartingSession::new(&handle.starting);
+        assert!(handle.clone().is_starting_sessions());
+        drop(starting);
+        assert!(!handle.is_starting_sessions());
+    }
 }
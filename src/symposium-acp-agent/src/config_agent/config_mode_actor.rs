@@ -5,10 +5,16 @@
 //! text-based menu system.
 
 use super::ConfigAgentMessage;
+use crate::mod_overrides::{self, ModOverrides};
+use crate::mods_lock::ModsLock;
 use crate::recommendations::{RecommendationDiff, WorkspaceRecommendations};
 use crate::registry::list_agents_with_sources;
 use crate::remote_recommendations::{self, save_local_recommendations};
-use crate::user_config::{ConfigPaths, GlobalAgentConfig, WorkspaceModsConfig};
+use crate::user_config::{
+    ConfigPaths, FailurePolicy, GlobalAgentConfig, ModPosition, PUBLIC_REGISTRY_ID,
+    WorkspaceModsConfig,
+};
+use crate::versions;
 use futures::StreamExt;
 use futures::channel::mpsc::{self, UnboundedSender};
 use regex::Regex;
@@ -47,8 +53,8 @@ pub enum ConfigModeOutput {
     /// Configuration is complete - save and exit.
     Done {
         /// The agent to save globally.
-        agent: ComponentSource,
-        /// The mods to save per-workspace.
+        agent: GlobalAgentConfig,
+        /// The mods (and the workspace's own agent, if any) to save per-workspace.
         mods: WorkspaceModsConfig,
     },
 
@@ -66,7 +72,7 @@ pub struct ConfigModeHandle {
 enum StartingConfiguration {
     /// An existing configuration with agent and mods
     ExistingConfig {
-        agent: ComponentSource,
+        agent: GlobalAgentConfig,
         mods: WorkspaceModsConfig,
     },
 
@@ -79,24 +85,30 @@ impl ConfigModeHandle {
     ///
     /// Returns a handle for sending input to the actor.
     ///
+    /// If the workspace's recommendations changed (`diff` is not empty), the
+    /// actor presents them before the main menu.
+    ///
     /// The `resume_tx` is a oneshot sender that, when dropped, will
     /// signal the conductor to resume processing. It will be dropped
     /// when the actor exits (either save or cancel).
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn_reconfig(
-        agent: ComponentSource,
-        mods: WorkspaceModsConfig,
+        agent: GlobalAgentConfig,
+        mut mods: WorkspaceModsConfig,
         workspace_path: PathBuf,
         config_paths: ConfigPaths,
+        diff: RecommendationDiff,
         session_id: SessionId,
         config_agent_tx: UnboundedSender<ConfigAgentMessage>,
         resume_tx: oneshot::Sender<()>,
         cx: &JrConnectionCx<AgentToClient>,
     ) -> Result<Self, sacp::Error> {
+        diff.apply(&mut mods);
         Self::spawn_inner(
             StartingConfiguration::ExistingConfig { agent, mods },
             workspace_path,
             config_paths,
-            None,
+            Some(diff),
             session_id,
             config_agent_tx,
             Some(resume_tx),
@@ -141,7 +153,7 @@ impl ConfigModeHandle {
     /// The actor will only handle the recommendation diff prompt, then send
     /// `DiffCompleted` or `DiffCancelled` instead of showing the main menu.
     pub fn spawn_with_recommendations(
-        agent: ComponentSource,
+        agent: GlobalAgentConfig,
         mut mods: WorkspaceModsConfig,
         workspace_path: PathBuf,
         config_paths: ConfigPaths,
@@ -208,6 +220,14 @@ enum DiffResult {
     Config,
 }
 
+/// Which workspaces a newly selected agent is for.
+enum AgentScope {
+    /// Only this workspace (stored in its [`WorkspaceModsConfig::agent`])
+    Workspace,
+    /// All workspaces without their own agent, and this one
+    Global,
+}
+
 /// The config mode actor state.
 struct ConfigModeActor {
     /// The workspace this configuration is for.
@@ -235,7 +255,7 @@ impl ConfigModeActor {
 
                 // Check for global agent config
                 let global_agent = match GlobalAgentConfig::load(&self.config_paths) {
-                    Ok(Some(global)) => Some(global.agent),
+                    Ok(Some(global)) => Some(global),
                     Ok(None) => None,
                     Err(e) => {
                         tracing::warn!("Failed to load global agent config: {}", e);
@@ -247,7 +267,7 @@ impl ConfigModeActor {
                     Some(agent) => {
                         self.send_message(&format!(
                             "Using your selected agent: **{}**\n\n",
-                            agent.display_name()
+                            agent.agent.display_name()
                         ));
                         agent
                     }
@@ -257,8 +277,8 @@ impl ConfigModeActor {
                         match self.select_agent().await {
                             Some(agent) => {
                                 // Save as global agent
-                                let global_config = GlobalAgentConfig::new(agent.clone());
-                                if let Err(e) = global_config.save(&self.config_paths).await {
+                                let agent = GlobalAgentConfig::new(agent);
+                                if let Err(e) = agent.save(&self.config_paths).await {
                                     tracing::warn!("Failed to save global agent config: {}", e);
                                 }
                                 agent
@@ -304,7 +324,9 @@ impl ConfigModeActor {
 
         tracing::debug!(diff = ?self.diff);
 
-        self.send_message("# Recommendations have changed\n\n");
+        if !self.diff.to_add.is_empty() || !self.diff.to_remove.is_empty() {
+            self.send_message("# Recommendations have changed\n\n");
+        }
 
         if !self.diff.to_add.is_empty() {
             self.send_message("The following mods are now recommended:\n");
@@ -332,6 +354,22 @@ impl ConfigModeActor {
             self.send_message("\n");
         }
 
+        if !self.diff.lock_mismatches.is_empty() {
+            self.send_message("# Lockfile is out of date\n\n");
+            self.send_message(format!(
+                "`{}` does not match this workspace's configuration:\n",
+                ModsLock::path(&self.workspace_path).display()
+            ));
+            for mismatch in &self.diff.lock_mismatches {
+                self.send_message(format!("- {}\n", mismatch));
+            }
+            self.send_message(format!(
+                "\nLocked mods keep running at their locked versions. \
+                 Run `symposium-acp-agent lock {}` to update the lock.\n\n",
+                self.workspace_path.display()
+            ));
+        }
+
         loop {
             self.send_message("Options:\n");
             self.send_message("* `SAVE` - Accept the new recommendations\n");
@@ -375,6 +413,71 @@ impl ConfigModeActor {
         }
     }
 
+    /// List newer versions of the pinned agent and mods, letting the user
+    /// accept them one at a time.
+    async fn review_updates(
+        &mut self,
+        agent: &mut GlobalAgentConfig,
+        mods: &mut WorkspaceModsConfig,
+    ) -> MenuAction {
+        self.send_message("Checking for updates...\n");
+        let mut updates = versions::available_updates(agent, mods).await;
+
+        loop {
+            if updates.is_empty() {
+                self.send_message("Everything is up to date.\n");
+                return MenuAction::Redisplay;
+            }
+
+            let mut msg = String::new();
+            msg.push_str("# Available Updates\n\n");
+            for (update, display_index) in updates.iter().zip(1..) {
+                msg.push_str(&format!(
+                    "  {}. {}: {} -> {}\n",
+                    display_index, update.name, update.current, update.latest
+                ));
+            }
+            msg.push_str("\nOptions:\n");
+            match updates.len() {
+                1 => msg.push_str("* `1` - Update to the newer version\n"),
+                n => msg.push_str(&format!(
+                    "* `1` through `{n}` - Update to the newer version\n"
+                )),
+            }
+            msg.push_str("* `BACK` - Return to the main menu\n");
+            self.send_message(msg);
+
+            let Some(input) = self.next_input().await else {
+                return MenuAction::Done;
+            };
+            let input = input.trim();
+
+            if input.eq_ignore_ascii_case("BACK") {
+                return MenuAction::Redisplay;
+            }
+
+            match input.parse::<usize>() {
+                Ok(idx) if idx >= 1 && idx <= updates.len() => {
+                    let update = updates.remove(idx - 1);
+                    update.apply(agent, mods);
+                    self.send_message(format!(
+                        "`{}` will use version {}.\n",
+                        update.name, update.latest
+                    ));
+                }
+                Ok(_) => {
+                    self.send_message(format!(
+                        "Invalid index. Please enter 1-{}.\n",
+                        updates.len()
+                    ));
+                }
+                Err(_) => {
+                    self.send_message(format!("Unknown command: `{}`\n", input));
+                }
+            }
+        }
+    }
+
     /// Prompt user to select an agent from the registry.
     /// Returns None if cancelled or an error occurred.
     async fn select_agent(&mut self) -> Option<ComponentSource> {
@@ -397,7 +500,17 @@ impl ConfigModeActor {
         let mut msg = String::new();
         msg.push_str("# Select an Agent\n\n");
         for (i, (entry, _)) in agents.iter().enumerate() {
-            msg.push_str(&format!("{}. {}\n", i + 1, entry.name));
+            match &entry.registry {
+                Some(registry) if registry != PUBLIC_REGISTRY_ID => {
+                    msg.push_str(&format!(
+                        "{}. {} (from `{}`)\n",
+                        i + 1,
+                        entry.name,
+                        registry
+                    ));
+                }
+                _ => msg.push_str(&format!("{}. {}\n", i + 1, entry.name)),
+            }
         }
         msg.push_str("\nEnter a number to select, or `cancel` to abort:\n");
         self.send_message(msg);
@@ -428,6 +541,42 @@ impl ConfigModeActor {
         }
     }
 
+    /// Ask whether a newly selected agent is for this workspace or all workspaces.
+    /// Returns None if cancelled.
+    async fn select_agent_scope(
+        &mut self,
+        new_agent: &GlobalAgentConfig,
+        mods: &WorkspaceModsConfig,
+    ) -> Option<AgentScope> {
+        let mut msg = format!("Use **{}** for:\n", new_agent.agent.display_name());
+        msg.push_str("* `WORKSPACE` - This workspace only\n");
+        if mods.agent.is_some() {
+            msg.push_str(
+                "* `GLOBAL` - All workspaces without their own agent \
+                 (this workspace will use it too)\n",
+            );
+        } else {
+            msg.push_str("* `GLOBAL` - All workspaces without their own agent\n");
+        }
+        msg.push_str("* `CANCEL` - Keep the current agent\n");
+        self.send_message(msg);
+
+        loop {
+            let input = self.next_input().await?;
+            let input = input.trim();
+
+            match &input.to_uppercase()[..] {
+                "W" | "WORKSPACE" => return Some(AgentScope::Workspace),
+                "G" | "GLOBAL" => return Some(AgentScope::Global),
+                "CANCEL" => return None,
+                _ => self.send_message(format!(
+                    "Unknown command: `{}`. Please enter `WORKSPACE`, `GLOBAL` or `CANCEL`.\n",
+                    input
+                )),
+            }
+        }
+    }
+
     /// Wait for the next user input.
     async fn next_input(&mut self) -> Option<String> {
         match self.rx.next().await {
@@ -447,7 +596,7 @@ impl ConfigModeActor {
     }
 
     /// Signal that configuration is done (save and exit).
-    fn done(&self, agent: &ComponentSource, mods: &WorkspaceModsConfig) {
+    fn done(&self, agent: &GlobalAgentConfig, mods: &WorkspaceModsConfig) {
         self.config_agent_tx
             .unbounded_send(ConfigAgentMessage::ConfigModeOutput(
                 self.session_id.clone(),
@@ -473,7 +622,7 @@ impl ConfigModeActor {
     /// Main menu loop.
     async fn main_menu_loop(
         &mut self,
-        agent: &mut ComponentSource,
+        agent: &mut GlobalAgentConfig,
         mods: &mut WorkspaceModsConfig,
     ) {
         self.show_main_menu(agent, mods);
@@ -495,7 +644,7 @@ impl ConfigModeActor {
     async fn handle_main_menu_input(
         &mut self,
         text: &str,
-        agent: &mut ComponentSource,
+        agent: &mut GlobalAgentConfig,
         mods: &mut WorkspaceModsConfig,
     ) -> MenuAction {
         let text = text.trim();
@@ -513,16 +662,28 @@ impl ConfigModeActor {
             return MenuAction::Done;
         }
 
-        // Change agent
+        // Change agent, for this workspace or globally
         if text_upper == "A" || text_upper == "AGENT" {
             if let Some(new_agent) = self.select_agent().await {
-                *agent = new_agent.clone();
-                return MenuAction::Redisplay;
+                let new_agent = GlobalAgentConfig::new(new_agent);
+                match self.select_agent_scope(&new_agent, mods).await {
+                    Some(AgentScope::Workspace) => mods.agent = Some(Box::new(new_agent)),
+                    Some(AgentScope::Global) => {
+                        *agent = new_agent;
+                        mods.agent = None;
+                    }
+                    None => {}
+                }
             }
             // Selection was cancelled, just redisplay menu
             return MenuAction::Redisplay;
         }
 
+        // Review updates to pinned versions
+        if text_upper == "U" || text_upper == "UPDATES" {
+            return self.review_updates(agent, mods).await;
+        }
+
         // Manage local recommendations
         if text_upper == "R" || text_upper == "RECS" || text_upper == "RECOMMENDATIONS" {
             return self.manage_local_recommendations().await;
@@ -552,14 +713,148 @@ impl ConfigModeActor {
         }
 
         // Move command: "move X to Y" or "move X to start/end" (1-based)
-        // Note: Since we use BTreeMap, ordering is by key, not insertion order.
-        // For now, we don't support reordering - could add a priority field later.
         static MOVE_RE: LazyLock<Regex> =
             LazyLock::new(|| Regex::new(r"(?i)^move\s+(\d+)\s+to\s+(\d+|start|end)$").unwrap());
 
-        if MOVE_RE.captures(text).is_some() {
-            self.send_message("Mod reordering is not yet supported with the new config format.");
-            return MenuAction::Continue;
+        if let Some(captures) = MOVE_RE.captures(text) {
+            let len = mods.mods.len();
+            let from = captures[1]
+                .parse::<usize>()
+                .ok()
+                .filter(|&i| i >= 1 && i <= len);
+            let to = match &captures[2].to_lowercase()[..] {
+                "start" => Some(ModPosition::Start),
+                "end" => Some(ModPosition::End),
+                index => index
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&i| i >= 1 && i <= len)
+                    .map(|i| ModPosition::Index(i - 1)),
+            };
+
+            let (Some(from), Some(to)) = (from, to) else {
+                if len == 0 {
+                    self.send_message("No mods configured.");
+                } else {
+                    self.send_message(format!("Invalid index. Please enter 1-{len}."));
+                }
+                return MenuAction::Continue;
+            };
+
+            let name = mods.mods[from - 1].source.display_name();
+            mods.move_mod(from - 1, to);
+            self.send_message(format!("Moved mod `{}`.", name));
+            return MenuAction::Redisplay;
+        }
+
+        // Require command: "require X" toggles whether mod X must start (1-based)
+        static REQUIRE_RE: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"(?i)^require\s+(\d+)$").unwrap());
+
+        if let Some(captures) = REQUIRE_RE.captures(text) {
+            let Some(index) = self.parse_mod_index(&captures[1], mods) else {
+                return MenuAction::Continue;
+            };
+
+            let m = &mut mods.mods[index];
+            m.policy = match m.policy {
+                FailurePolicy::Required => FailurePolicy::Optional,
+                FailurePolicy::Optional => FailurePolicy::Required,
+            };
+            self.send_message(match m.policy {
+                FailurePolicy::Required => format!(
+                    "Mod `{}` is now required: sessions won't start without it.",
+                    m.source.display_name()
+                ),
+                FailurePolicy::Optional => format!(
+                    "Mod `{}` is now optional: sessions start without it if it fails.",
+                    m.source.display_name()
+                ),
+            });
+            return MenuAction::Redisplay;
+        }
+
+        // Override commands: "args X ...", "env X NAME=VALUE", "cwd X DIR" (1-based)
+        static OVERRIDE_RE: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"(?i)^(args|env|cwd)\s+(\d+)(?:\s+(.*))?$").unwrap());
+
+        if let Some(captures) = OVERRIDE_RE.captures(text) {
+            let Some(index) = self.parse_mod_index(&captures[2], mods) else {
+                return MenuAction::Continue;
+            };
+            let value = captures.get(3).map_or("", |m| m.as_str().trim());
+            let m = &mut mods.mods[index];
+            let name = m.source.display_name();
+
+            if matches!(m.source, ComponentSource::Http(_) | ComponentSource::Sse(_)) {
+                self.send_message(format!(
+                    "Mod `{}` is an HTTP server; `args`, `env` and `cwd` only apply to mods that run a command.",
+                    name
+                ));
+                return MenuAction::Continue;
+            }
+
+            let overrides = &mut m.overrides;
+            match &captures[1].to_lowercase()[..] {
+                "args" => {
+                    overrides.args = value.split_whitespace().map(str::to_string).collect();
+                    if overrides.args.is_empty() {
+                        self.send_message(format!("Removed extra arguments of mod `{}`.", name));
+                    } else {
+                        self.send_message(format!(
+                            "Mod `{}` now runs with extra arguments `{}`.",
+                            name, value
+                        ));
+                    }
+                }
+                "env" => match value.split_once('=') {
+                    Some((var, var_value)) => {
+                        let var = var.trim();
+                        let mut msg = format!("Set `{}` for mod `{}`.", var, name);
+                        if !mod_overrides::has_env_refs(var_value) {
+                            msg.push_str(
+                                " The value is stored in plain text; \
+                                 write `${NAME}` to read it from an environment variable instead.",
+                            );
+                        }
+                        overrides.env.insert(var.to_string(), var_value.to_string());
+                        self.send_message(msg);
+                    }
+                    None if !value.is_empty() => {
+                        if overrides.env.remove(value).is_some() {
+                            self.send_message(format!("Removed `{}` from mod `{}`.", value, name));
+                        } else {
+                            self.send_message(format!("Mod `{}` doesn't set `{}`.", name, value));
+                            return MenuAction::Continue;
+                        }
+                    }
+                    None => {
+                        self.send_message(
+                            "Usage: `env X NAME=VALUE` to set a variable, `env X NAME` to remove it.",
+                        );
+                        return MenuAction::Continue;
+                    }
+                },
+                _ => {
+                    if value.is_empty() {
+                        overrides.cwd = None;
+                        self.send_message(format!(
+                            "Removed the working directory of mod `{}`.",
+                            name
+                        ));
+                    } else {
+                        // Relative directories are relative to the workspace
+                        let cwd = self.workspace_path.join(value);
+                        self.send_message(format!(
+                            "Mod `{}` now runs in `{}`.",
+                            name,
+                            cwd.display()
+                        ));
+                        overrides.cwd = Some(cwd);
+                    }
+                }
+            }
+            return MenuAction::Redisplay;
         }
 
         // Unknown command
@@ -567,6 +862,21 @@ impl ConfigModeActor {
         MenuAction::Continue
     }
 
+    /// Parse a 1-based mod index from a command, reporting invalid ones.
+    /// Returns the 0-based index.
+    fn parse_mod_index(&self, index: &str, mods: &WorkspaceModsConfig) -> Option<usize> {
+        let len = mods.mods.len();
+        let index = index.parse::<usize>().ok().filter(|&i| i >= 1 && i <= len);
+        if index.is_none() {
+            if len == 0 {
+                self.send_message("No mods configured.");
+            } else {
+                self.send_message(format!("Invalid index. Please enter 1-{len}."));
+            }
+        }
+        index.map(|i| i - 1)
+    }
+
     /// Manage local recommendations file (`<config>/config/recommendations.toml`).
     /// Allows listing, adding, and removing single recommendation entries.
     async fn manage_local_recommendations(&mut self) -> MenuAction {
@@ -809,12 +1119,22 @@ impl ConfigModeActor {
     }
 
     /// Show the main menu.
-    fn show_main_menu(&self, agent: &ComponentSource, mods: &WorkspaceModsConfig) {
+    fn show_main_menu(&self, agent: &GlobalAgentConfig, mods: &WorkspaceModsConfig) {
         let mut msg = String::new();
         msg.push_str("# Configuration\n\n");
 
-        // Current agent (global)
-        msg.push_str(&format!("**Agent:** {}\n\n", agent.display_name()));
+        // Current agent (this workspace's own, or the global one)
+        match &mods.agent {
+            Some(own) => msg.push_str(&format!(
+                "**Agent:** {} (this workspace only; other workspaces use {})\n\n",
+                versions::pinned_name(&own.agent, &own.version),
+                versions::pinned_name(&agent.agent, &agent.version)
+            )),
+            None => msg.push_str(&format!(
+                "**Agent:** {}\n\n",
+                versions::pinned_name(&agent.agent, &agent.version)
+            )),
+        }
 
         // Mods (per-workspace)
         msg.push_str(&format!(
@@ -825,12 +1145,19 @@ impl ConfigModeActor {
             msg.push_str("  * (none configured)\n");
         } else {
             for (m, display_index) in mods.mods.iter().zip(1..) {
-                let name = m.source.display_name();
+                let name = versions::pinned_name(&m.source, &m.version);
                 let mcp = matches!(m.kind, ModKind::MCP)
                     .then_some(" (MCP)")
                     .unwrap_or("");
+                let required = matches!(m.policy, FailurePolicy::Required)
+                    .then_some(" (required)")
+                    .unwrap_or("");
+                let overrides = overrides_summary(&m.overrides);
                 if m.enabled {
-                    msg.push_str(&format!("  {}. {}{}\n", display_index, name, mcp));
+                    msg.push_str(&format!(
+                        "  {}. {}{}{}{}\n",
+                        display_index, name, mcp, required, overrides
+                    ));
                 } else {
                     msg.push_str(&format!(
                         "  {}. ~~{}{}~~ (disabled)\n",
@@ -843,14 +1170,27 @@ impl ConfigModeActor {
 
         // Commands
         msg.push_str("# Commands\n\n");
-        msg.push_str("- `AGENT` - Change agent (affects all workspaces)\n");
+        msg.push_str("- `AGENT` - Change the agent for this workspace or for all workspaces\n");
+        msg.push_str("- `UPDATES` - Check for newer versions of the agent and mods\n");
         msg.push_str("- `RECS` - Update user-defined recommendations\n");
         match mods.mods.len() {
             0 => {}
             1 => msg.push_str("- `1` - Toggle mod enabled/disabled in this workspace\n"),
-            n => msg.push_str(&format!(
-                "- `1` through `{n}` - Toggle mod enabled/disabled in this workspace\n"
-            )),
+            n => {
+                msg.push_str(&format!(
+                    "- `1` through `{n}` - Toggle mod enabled/disabled in this workspace\n"
+                ));
+                msg.push_str("- `move X to Y` - Reorder mods (`Y` can also be `start` or `end`)\n");
+            }
+        }
+        if !mods.mods.is_empty() {
+            msg.push_str(
+                "- `require X` - Toggle whether sessions may start without mod `X` if it fails\n",
+            );
+            msg.push_str(
+                "- `args X ...`, `env X NAME=VALUE`, `cwd X DIR` - Customize how mod `X` runs \
+                 (values can use `${NAME}` to read environment variables)\n",
+            );
         }
         msg.push_str("- `SAVE` - Save for future sessions\n");
         msg.push_str("- `CANCEL` - Exit without saving\n");
@@ -858,3 +1198,25 @@ impl ConfigModeActor {
         self.send_message(msg);
     }
 }
+
+/// Short description of a mod's overrides for the menu, e.g. ` [args: --verbose; env: TOKEN]`.
+///
+/// Only the names of environment variables are shown, as values may be secrets.
+fn overrides_summary(overrides: &ModOverrides) -> String {
+    let mut parts = vec![];
+    if !overrides.args.is_empty() {
+        parts.push(format!("args: {}", overrides.args.join(" ")));
+    }
+    if !overrides.env.is_empty() {
+        let names: Vec<_> = overrides.env.keys().map(String::as_str).collect();
+        parts.push(format!("env: {}", names.join(", ")));
+    }
+    if let Some(cwd) = &overrides.cwd {
+        parts.push(format!("cwd: {}", cwd.display()));
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", parts.join("; "))
+    }
+}
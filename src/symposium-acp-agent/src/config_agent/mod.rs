@@ -8,29 +8,39 @@
 
 mod conductor_actor;
 mod config_mode_actor;
+mod proxy_supervisor;
+mod recommendation_watcher;
+mod session_store;
+mod session_traces;
 mod uberconductor_actor;
 
 #[cfg(test)]
 mod tests;
 
-use crate::recommendations::{Recommendations, RecommendationsExt, WorkspaceRecommendations};
+use crate::recommendations::{
+    self, RecommendationDiff, Recommendations, RecommendationsExt, WorkspaceRecommendations,
+};
 use crate::remote_recommendations;
-use crate::user_config::{ConfigPaths, GlobalAgentConfig, WorkspaceModsConfig};
-use conductor_actor::ConductorHandle;
+use crate::user_config::{ConfigPaths, GlobalAgentConfig, ModConfig, WorkspaceModsConfig};
+use crate::versions;
+use conductor_actor::{ConductorHandle, StartingSession};
 use config_mode_actor::{ConfigModeHandle, ConfigModeOutput};
-use futures::StreamExt;
 use futures::channel::mpsc::{UnboundedReceiver, UnboundedSender, unbounded};
+use futures::{FutureExt, StreamExt};
 use fxhash::FxHashMap;
+use recommendation_watcher::{FileWatcher, recommendation_inputs};
 use sacp::link::AgentToClient;
 use sacp::schema::{
     AgentCapabilities, AvailableCommand, AvailableCommandsUpdate, ContentBlock, ContentChunk,
-    InitializeRequest, InitializeResponse, NewSessionRequest, NewSessionResponse, PromptRequest,
-    PromptResponse, SessionId, SessionNotification, SessionUpdate, StopReason, TextContent,
+    InitializeRequest, InitializeResponse, LoadSessionRequest, LoadSessionResponse,
+    NewSessionRequest, NewSessionResponse, PromptRequest, PromptResponse, SessionId,
+    SessionNotification, SessionUpdate, StopReason, TextContent,
 };
 use sacp::util::MatchMessage;
 use sacp::{ClientPeer, Component, JrConnectionCx, JrRequestCx, MessageCx};
+use session_store::{LoadSessionSupport, SavedSession, SavedSessions};
 use std::path::{Path, PathBuf};
-use symposium_recommendations::ComponentSource;
+use tokio::sync::oneshot;
 use uberconductor_actor::UberconductorHandle;
 
 /// The slash command name for entering config mode.
@@ -51,6 +61,21 @@ fn get_session_id(message: &MessageCx) -> Result<Option<SessionId>, sacp::Error>
     }
 }
 
+/// Show a session the progress starting the conductor it waited for.
+fn send_progress(
+    cx: &JrConnectionCx<AgentToClient>,
+    session_id: &SessionId,
+    progress: Vec<String>,
+) -> Result<(), sacp::Error> {
+    for message in progress {
+        cx.send_notification(SessionNotification::new(
+            session_id.clone(),
+            SessionUpdate::AgentMessageChunk(ContentChunk::new(format!("{}\n", message).into())),
+        ))?;
+    }
+    Ok(())
+}
+
 /// State for a single session.
 #[derive(Clone)]
 enum SessionState {
@@ -88,8 +113,37 @@ pub struct ConfigAgent {
     /// None only if loading failed and we're in a degraded state.
     recommendations: Option<Recommendations>,
 
+    /// The recommendation sets merged with the user's local recommendations
+    /// into `recommendations`, kept so that local changes can be merged again.
+    remote_recommendations: Vec<Recommendations>,
+
     /// Configuration paths (where to read/write config files).
     config_paths: ConfigPaths,
+
+    /// Finished setup sessions that are shown progress while their workspace's
+    /// conductor starts, keyed by workspace path.
+    setup_sessions: FxHashMap<PathBuf, Vec<SessionId>>,
+
+    /// Sessions moving to a conductor with a new configuration, with the
+    /// conductor they are moving away from.
+    /// Their prompts are refused until the move finishes.
+    reloading: FxHashMap<SessionId, ConductorHandle>,
+
+    /// Conductors whose sessions are moving away, paused so that only one
+    /// agent works on a session at a time, with the sender that resumes them.
+    retiring: Vec<(ConductorHandle, oneshot::Sender<()>)>,
+
+    /// Workspaces whose recommendation inputs are watched (see
+    /// [`recommendation_watcher`]) while they have sessions.
+    watched_workspaces: FxHashMap<PathBuf, WatchedWorkspace>,
+}
+
+/// A workspace whose recommendation inputs are watched.
+struct WatchedWorkspace {
+    /// The recommendation diff last computed for the workspace
+    diff: RecommendationDiff,
+    /// Dropping this stops the watcher
+    _stop: oneshot::Sender<()>,
 }
 
 impl ConfigAgent {
@@ -100,14 +154,7 @@ impl ConfigAgent {
     /// - The config directory cannot be determined
     /// - Recommendations cannot be loaded (no remote access AND no cache)
     pub async fn new() -> anyhow::Result<Self> {
-        let config_paths = ConfigPaths::default_location()?;
-        let recommendations = remote_recommendations::load_recommendations(&config_paths).await?;
-        Ok(Self {
-            sessions: Default::default(),
-            trace_dir: None,
-            recommendations: Some(recommendations),
-            config_paths,
-        })
+        Self::with_config_paths_async(ConfigPaths::default_location()?).await
     }
 
     /// Create a new ConfigAgent with custom config paths.
@@ -115,12 +162,21 @@ impl ConfigAgent {
     /// This loads recommendations from remote sources (with caching fallback).
     /// Useful for integration tests that need real recommendation loading behavior.
     pub async fn with_config_paths_async(config_paths: ConfigPaths) -> anyhow::Result<Self> {
-        let recommendations = remote_recommendations::load_recommendations(&config_paths).await?;
+        let remote = remote_recommendations::load_remote_recommendations(&config_paths).await?;
+        let local = remote_recommendations::load_local_recommendations(&config_paths).await?;
         Ok(Self {
             sessions: Default::default(),
             trace_dir: None,
-            recommendations: Some(recommendations),
+            recommendations: Some(remote_recommendations::merge_recommendations(
+                remote.clone(),
+                local,
+            )),
+            remote_recommendations: remote,
             config_paths,
+            setup_sessions: Default::default(),
+            reloading: Default::default(),
+            retiring: Default::default(),
+            watched_workspaces: Default::default(),
         })
     }
 
@@ -132,7 +188,12 @@ impl ConfigAgent {
             sessions: Default::default(),
             trace_dir: None,
             recommendations: None,
+            remote_recommendations: vec![],
             config_paths,
+            setup_sessions: Default::default(),
+            reloading: Default::default(),
+            retiring: Default::default(),
+            watched_workspaces: Default::default(),
         }
     }
 
@@ -144,14 +205,86 @@ impl ConfigAgent {
 
     /// Set recommendations (for testing).
     pub fn with_recommendations(mut self, recommendations: Recommendations) -> Self {
+        self.remote_recommendations = vec![recommendations.clone()];
         self.recommendations = Some(recommendations);
         self
     }
 
+    /// Sessions that should be told about progress starting the workspace's conductor:
+    /// finished setup sessions and sessions moving to the new conductor, none of
+    /// which are in the middle of a turn.
+    fn waiting_sessions(&self, workspace_path: &Path) -> Vec<SessionId> {
+        let reloading = self.reloading.keys().filter(|session_id| {
+            matches!(
+                self.sessions.get(*session_id),
+                Some(SessionState::Delegating { workspace_path: path, .. }) if path == workspace_path
+            )
+        });
+        let setup = self
+            .setup_sessions
+            .get(workspace_path)
+            .into_iter()
+            .flatten();
+        reloading.chain(setup).cloned().collect()
+    }
+
+    /// Sessions that should be told about problems with the workspace's mods.
+    fn workspace_sessions(&self, workspace_path: &Path) -> Vec<SessionId> {
+        let active = self.sessions.iter().filter_map(|(session_id, state)| {
+            let (SessionState::Config {
+                workspace_path: path,
+                ..
+            }
+            | SessionState::Delegating {
+                workspace_path: path,
+                ..
+            }) = state;
+            (path == workspace_path).then(|| session_id.clone())
+        });
+        let setup = self
+            .setup_sessions
+            .get(workspace_path)
+            .into_iter()
+            .flatten();
+        active.chain(setup.cloned()).collect()
+    }
+
+    /// Whether the configured agent is known to support `session/load`.
+    ///
+    /// This is only known once the agent has run; until then sessions can't be loaded.
+    fn can_load_sessions(&self) -> bool {
+        let Ok(Some(agent)) = GlobalAgentConfig::load(&self.config_paths) else {
+            return false;
+        };
+        match LoadSessionSupport::load(&self.config_paths) {
+            Ok(support) => support.supports(&agent),
+            Err(e) => {
+                tracing::warn!("Failed to load session support: {:#}", e);
+                false
+            }
+        }
+    }
+
+    /// Remember whether an agent supports `session/load`.
+    async fn record_load_session_support(&self, agent: &GlobalAgentConfig, load_session: bool) {
+        let result = match LoadSessionSupport::load(&self.config_paths) {
+            Ok(mut support) => {
+                if support.record(agent, load_session) {
+                    support.save(&self.config_paths).await
+                } else {
+                    Ok(())
+                }
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to save session support: {:#}", e);
+        }
+    }
+
     /// Load the global agent configuration.
-    fn load_global_agent(&self) -> Result<Option<ComponentSource>, sacp::Error> {
+    fn load_global_agent(&self) -> Result<Option<GlobalAgentConfig>, sacp::Error> {
         GlobalAgentConfig::load(&self.config_paths)
-            .map(|opt| opt.map(|c| c.agent))
             .map_err(|e| sacp::util::internal_error(e.to_string()))
     }
 
@@ -176,6 +309,126 @@ impl ConfigAgent {
             .unwrap_or_default()
     }
 
+    /// Compute how a workspace's mods differ from its recommendations and its
+    /// `mods.lock`.
+    fn recommendation_diff(
+        &self,
+        workspace_path: &Path,
+        mods_config: &WorkspaceModsConfig,
+    ) -> RecommendationDiff {
+        recommendations::workspace_diff(self.load_recommendations(), workspace_path, mods_config)
+    }
+
+    /// Merge the user's local recommendations again, after they changed on disk.
+    async fn reload_local_recommendations(&mut self) {
+        if self.recommendations.is_none() {
+            return;
+        }
+        match remote_recommendations::load_local_recommendations(&self.config_paths).await {
+            Ok(local) => {
+                self.recommendations = Some(remote_recommendations::merge_recommendations(
+                    self.remote_recommendations.clone(),
+                    local,
+                ));
+            }
+            Err(e) => tracing::warn!("Failed to reload local recommendations: {:#}", e),
+        }
+    }
+
+    /// Start watching the files a workspace's recommendations depend on,
+    /// unless they are watched already.
+    fn watch_workspace(
+        &mut self,
+        workspace_path: &Path,
+        config_agent_tx: &UnboundedSender<ConfigAgentMessage>,
+        cx: &JrConnectionCx<AgentToClient>,
+    ) {
+        if self.watched_workspaces.contains_key(workspace_path) {
+            return;
+        }
+
+        let mut watcher =
+            match FileWatcher::new(recommendation_inputs(&self.config_paths, workspace_path)) {
+                Ok(watcher) => watcher,
+                Err(e) => {
+                    tracing::warn!(?workspace_path, "Failed to watch workspace: {:#}", e);
+                    return;
+                }
+            };
+
+        // Changes are reported relative to the diff as it is now
+        let diff = match self.load_mods(workspace_path) {
+            Ok(Some(mods_config)) => self.recommendation_diff(workspace_path, &mods_config),
+            _ => RecommendationDiff::default(),
+        };
+        let (stop_tx, stop_rx) = oneshot::channel();
+        self.watched_workspaces.insert(
+            workspace_path.to_path_buf(),
+            WatchedWorkspace {
+                diff,
+                _stop: stop_tx,
+            },
+        );
+
+        let tx = config_agent_tx.clone();
+        let workspace_path = workspace_path.to_path_buf();
+        let result = cx.spawn(async move {
+            let mut stop = stop_rx.fuse();
+            loop {
+                futures::select! {
+                    changed = watcher.changed().fuse() => {
+                        let message = ConfigAgentMessage::RecommendationInputsChanged {
+                            workspace_path: workspace_path.clone(),
+                        };
+                        if !changed || tx.unbounded_send(message).is_err() {
+                            break;
+                        }
+                    }
+                    _ = stop => break,
+                }
+            }
+            Ok(())
+        });
+        if let Err(e) = result {
+            tracing::warn!("Failed to spawn workspace watcher: {}", e);
+        }
+    }
+
+    /// Recompute the recommendation diff of a watched workspace.
+    ///
+    /// Returns the new diff if it changed since it was last computed.
+    fn refresh_recommendation_diff(
+        &mut self,
+        workspace_path: &Path,
+        mods_config: &WorkspaceModsConfig,
+    ) -> Option<RecommendationDiff> {
+        if !self.watched_workspaces.contains_key(workspace_path) {
+            return None;
+        }
+        let diff = self.recommendation_diff(workspace_path, mods_config);
+        let watched = self.watched_workspaces.get_mut(workspace_path)?;
+        let previous = std::mem::replace(&mut watched.diff, diff.clone());
+        (previous != diff).then_some(diff)
+    }
+
+    /// Stop watching a workspace once it has no sessions left.
+    fn unwatch_if_unused(&mut self, workspace_path: &Path) {
+        let used = self.sessions.values().any(|state| {
+            let (SessionState::Config {
+                workspace_path: path,
+                ..
+            }
+            | SessionState::Delegating {
+                workspace_path: path,
+                ..
+            }) = state;
+            path == workspace_path
+        });
+        if !used {
+            self.watched_workspaces.remove(workspace_path);
+        }
+    }
+
     /// The main "config agent method"
     async fn run(
         mut self,
@@ -201,7 +454,10 @@ impl ConfigAgent {
                     response,
                     conductor,
                     workspace_path,
+                    progress,
+                    warnings,
                     request_cx,
+                    starting: _starting,
                 } => {
                     let session_id = response.session_id.clone();
 
@@ -209,30 +465,223 @@ impl ConfigAgent {
                     self.sessions.insert(
                         session_id.clone(),
                         SessionState::Delegating {
-                            conductor,
-                            workspace_path,
+                            conductor: conductor.clone(),
+                            workspace_path: workspace_path.clone(),
                         },
                     );
 
                     // Respond to the client
                     request_cx.respond(response)?;
+                    send_progress(&cx, &session_id, progress)?;
 
-                    // Send initial available commands with /symposium:config
-                    // This ensures the command is available even if the downstream agent
-                    // doesn't send its own AvailableCommandsUpdate
-                    cx.send_notification(SessionNotification::new(
+                    self.session_started(
                         session_id,
-                        SessionUpdate::AvailableCommandsUpdate(AvailableCommandsUpdate::new(vec![
-                            AvailableCommand::new(
-                                CONFIG_SLASH_COMMAND,
-                                "Configure Symposium settings",
-                            ),
-                        ])),
-                    ))?;
+                        &conductor,
+                        workspace_path,
+                        warnings,
+                        &tx,
+                        &cx,
+                    )
+                    .await?;
+                }
+
+                ConfigAgentMessage::SessionLoaded {
+                    session_id,
+                    response,
+                    conductor,
+                    workspace_path,
+                    progress,
+                    mut warnings,
+                    request_cx,
+                    starting: _starting,
+                } => {
+                    match SavedSessions::load(&self.config_paths, &workspace_path) {
+                        Ok(saved) => {
+                            if let Some(saved) = saved.get(&session_id)
+                                && saved.config_differs(&conductor.agent(), &conductor.mods())
+                            {
+                                warnings.push(
+                                    "This session was started with a different agent or mods; \
+                                     it continues with the current configuration."
+                                        .to_string(),
+                                );
+                            }
+                        }
+                        Err(e) => tracing::warn!("Failed to load saved sessions: {:#}", e),
+                    }
+
+                    self.sessions.insert(
+                        session_id.clone(),
+                        SessionState::Delegating {
+                            conductor: conductor.clone(),
+                            workspace_path: workspace_path.clone(),
+                        },
+                    );
+                    request_cx.respond(response)?;
+                    send_progress(&cx, &session_id, progress)?;
+
+                    self.session_started(
+                        session_id,
+                        &conductor,
+                        workspace_path,
+                        warnings,
+                        &tx,
+                        &cx,
+                    )
+                    .await?;
+                }
+
+                ConfigAgentMessage::HandoverFinished {
+                    session_id,
+                    conductor,
+                    workspace_path,
+                    warnings,
+                    result,
+                } => {
+                    let previous = self.reloading.remove(&session_id);
+                    let delegating = matches!(
+                        self.sessions.get(&session_id),
+                        Some(SessionState::Delegating { .. })
+                    );
+                    if !delegating {
+                        // The session ended or entered config mode meanwhile;
+                        // it stays where it is
+                        if let Some(previous) = &previous {
+                            self.retire_if_unused(previous).await;
+                        }
+                        self.retire_if_unused(&conductor).await;
+                        continue;
+                    }
+
+                    match result {
+                        Ok(()) => {
+                            if let Some(previous) = &previous
+                                && let Err(e) = previous.release_session(session_id.clone()).await
+                            {
+                                tracing::debug!("Conductor already stopped: {}", e);
+                            }
+                            self.sessions.insert(
+                                session_id.clone(),
+                                SessionState::Delegating {
+                                    conductor: conductor.clone(),
+                                    workspace_path: workspace_path.clone(),
+                                },
+                            );
+                            self.session_started(
+                                session_id.clone(),
+                                &conductor,
+                                workspace_path,
+                                warnings,
+                                &tx,
+                                &cx,
+                            )
+                            .await?;
+                            cx.send_notification(SessionNotification::new(
+                                session_id,
+                                SessionUpdate::AgentMessageChunk(ContentChunk::new(
+                                    "Mods reloaded. The conversation continues with the new configuration.\n"
+                                        .into(),
+                                )),
+                            ))?;
+                        }
+                        Err(error) => {
+                            cx.send_notification(SessionNotification::new(
+                                session_id,
+                                SessionUpdate::AgentMessageChunk(ContentChunk::new(
+                                    format!(
+                                        "Warning: Could not move this session to the new configuration ({}). \
+                                         It keeps its previous mods; new sessions use the new ones.\n",
+                                        error
+                                    )
+                                    .into(),
+                                )),
+                            ))?;
+                        }
+                    }
+
+                    if let Some(previous) = &previous {
+                        self.retire_if_unused(previous).await;
+                    }
+                    self.retire_if_unused(&conductor).await;
                 }
 
                 ConfigAgentMessage::ConfigModeOutput(session_id, output) => {
-                    self.handle_config_mode_output(session_id, output, &uberconductor, &cx)
+                    self.handle_config_mode_output(session_id, output, &uberconductor, &cx, &tx)
+                        .await?;
+                }
+
+                ConfigAgentMessage::AgentCapabilities {
+                    agent,
+                    load_session,
+                } => {
+                    self.record_load_session_support(&agent, load_session).await;
+                }
+
+                ConfigAgentMessage::ResolutionProgress {
+                    workspace_path,
+                    message,
+                } => {
+                    for session_id in self.waiting_sessions(&workspace_path) {
+                        cx.send_notification(SessionNotification::new(
+                            session_id,
+                            SessionUpdate::AgentMessageChunk(ContentChunk::new(
+                                format!("{}\n", message).into(),
+                            )),
+                        ))?;
+                    }
+                }
+
+                ConfigAgentMessage::ResolutionFinished {
+                    workspace_path,
+                    error,
+                } => {
+                    let text = match error {
+                        Some(error) => format!("Failed to start mods: {}\n", error),
+                        None => "Mods are ready.\n".to_string(),
+                    };
+                    for session_id in self
+                        .setup_sessions
+                        .remove(&workspace_path)
+                        .unwrap_or_default()
+                    {
+                        cx.send_notification(SessionNotification::new(
+                            session_id,
+                            SessionUpdate::AgentMessageChunk(ContentChunk::new(
+                                text.clone().into(),
+                            )),
+                        ))?;
+                    }
+                }
+
+                ConfigAgentMessage::ModWarning {
+                    workspace_path,
+                    message,
+                } => {
+                    for session_id in self.workspace_sessions(&workspace_path) {
+                        cx.send_notification(SessionNotification::new(
+                            session_id,
+                            SessionUpdate::AgentMessageChunk(ContentChunk::new(
+                                format!("Warning: {}\n", message).into(),
+                            )),
+                        ))?;
+                    }
+                }
+
+                ConfigAgentMessage::VersionsRecorded {
+                    workspace_path,
+                    agent,
+                    mods,
+                } => {
+                    self.save_recorded_versions(&workspace_path, agent, &mods, &uberconductor)
+                        .await?;
+                }
+
+                ConfigAgentMessage::ConductorReplaced { conductor } => {
+                    self.retire_if_unused(&conductor).await;
+                }
+
+                ConfigAgentMessage::RecommendationInputsChanged { workspace_path } => {
+                    self.handle_recommendation_inputs_changed(&workspace_path, &cx)
                         .await?;
                 }
             }
@@ -240,13 +689,277 @@ impl ConfigAgent {
         Ok(())
     }
 
+    /// Set up a session that was just created or loaded and is delegating to `conductor`.
+    ///
+    /// Tells the client about `/symposium:config` and any warnings, saves the
+    /// session so it can be loaded after a restart, and watches the workspace
+    /// for changed recommendations.
+    async fn session_started(
+        &mut self,
+        session_id: SessionId,
+        conductor: &ConductorHandle,
+        workspace_path: PathBuf,
+        warnings: Vec<String>,
+        config_agent_tx: &UnboundedSender<ConfigAgentMessage>,
+        cx: &JrConnectionCx<AgentToClient>,
+    ) -> Result<(), sacp::Error> {
+        self.watch_workspace(&workspace_path, config_agent_tx, cx);
+
+        // Send initial available commands with /symposium:config
+        // This ensures the command is available even if the downstream agent
+        // doesn't send its own AvailableCommandsUpdate
+        cx.send_notification(SessionNotification::new(
+            session_id.clone(),
+            SessionUpdate::AvailableCommandsUpdate(AvailableCommandsUpdate::new(vec![
+                AvailableCommand::new(CONFIG_SLASH_COMMAND, "Configure Symposium settings"),
+            ])),
+        ))?;
+
+        for warning in warnings {
+            cx.send_notification(SessionNotification::new(
+                session_id.clone(),
+                SessionUpdate::AgentMessageChunk(ContentChunk::new(
+                    format!("Warning: {}\n", warning).into(),
+                )),
+            ))?;
+        }
+
+        // A session that can't be saved still works, it just can't be loaded later
+        let saved = SavedSessions::load(&self.config_paths, &workspace_path).map(|mut saved| {
+            saved.record(SavedSession::new(
+                session_id,
+                workspace_path.clone(),
+                &conductor.agent(),
+                &conductor.mods(),
+            ));
+            saved
+        });
+        let result = match saved {
+            Ok(saved) => saved.save(&self.config_paths, &workspace_path).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to save session: {:#}", e);
+        }
+
+        Ok(())
+    }
+
+    /// Recompute a watched workspace's recommendation diff after one of its
+    /// inputs changed, and suggest `/symposium:config` to the workspace's
+    /// sessions if it changed.
+    async fn handle_recommendation_inputs_changed(
+        &mut self,
+        workspace_path: &Path,
+        cx: &JrConnectionCx<AgentToClient>,
+    ) -> Result<(), sacp::Error> {
+        self.reload_local_recommendations().await;
+
+        let mods_config = match self.load_mods(workspace_path) {
+            Ok(Some(mods_config)) => mods_config,
+            Ok(None) => return Ok(()),
+            Err(e) => {
+                tracing::warn!("Failed to load mods: {}", e);
+                return Ok(());
+            }
+        };
+
+        let Some(diff) = self.refresh_recommendation_diff(workspace_path, &mods_config) else {
+            return Ok(());
+        };
+        tracing::debug!(?diff, "recommendation diff changed");
+        if diff.is_empty() {
+            return Ok(());
+        }
+
+        // Sessions in config mode see the diff the next time they enter it
+        let sessions = self
+            .sessions
+            .iter()
+            .filter_map(|(session_id, state)| match state {
+                SessionState::Delegating {
+                    workspace_path: path,
+                    ..
+                } if path == workspace_path => Some(session_id.clone()),
+                _ => None,
+            });
+        for session_id in sessions {
+            cx.send_notification(SessionNotification::new(
+                session_id,
+                SessionUpdate::AgentMessageChunk(ContentChunk::new(
+                    format!(
+                        "Recommendations for this workspace changed ({}). \
+                         Run `/{}` to review them.\n",
+                        diff.summary(),
+                        CONFIG_SLASH_COMMAND
+                    )
+                    .into(),
+                )),
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Move the workspace's sessions to a conductor with the new agent and mods.
+    ///
+    /// Sessions whose conductor already runs this configuration are left alone.
+    /// Returns whether any session is being moved; each one finishes with
+    /// `HandoverFinished`.
+    async fn reload_workspace(
+        &mut self,
+        workspace_path: &Path,
+        agent: GlobalAgentConfig,
+        mods: Vec<ModConfig>,
+        uberconductor: &UberconductorHandle,
+    ) -> Result<bool, sacp::Error> {
+        let stale: Vec<(SessionId, ConductorHandle)> = self
+            .sessions
+            .iter()
+            .filter_map(|(session_id, state)| match state {
+                SessionState::Delegating {
+                    conductor,
+                    workspace_path: path,
+                } if path == workspace_path && !conductor.has_config(&agent, &mods) => {
+                    Some((session_id.clone(), conductor.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+        if stale.is_empty() {
+            return Ok(false);
+        }
+
+        for (_, conductor) in &stale {
+            // Conductors already paused (by config mode or an earlier reload) stay as they are
+            let paused = self
+                .retiring
+                .iter()
+                .any(|(c, _)| c.same_conductor(conductor))
+                || self.sessions.values().any(|state| {
+                    matches!(state, SessionState::Config { return_to: Some(c), .. }
+                        if c.same_conductor(conductor))
+                });
+            if paused || conductor.is_closed() {
+                continue;
+            }
+            match conductor.pause().await {
+                Ok(resume_tx) => self.retiring.push((conductor.clone(), resume_tx)),
+                Err(e) => tracing::warn!("Failed to pause conductor: {}", e),
+            }
+        }
+
+        self.reloading.extend(stale.iter().cloned());
+        let sessions: Vec<SessionId> = stale.into_iter().map(|(id, _)| id).collect();
+        uberconductor
+            .reload(workspace_path.to_path_buf(), agent, mods, sessions)
+            .await?;
+        Ok(true)
+    }
+
+    /// Resume a conductor once none of its sessions is moving away, and stop it
+    /// once no session uses it.
+    async fn retire_if_unused(&mut self, conductor: &ConductorHandle) {
+        let uses = |state: &SessionState| match state {
+            SessionState::Delegating { conductor: c, .. }
+            | SessionState::Config {
+                return_to: Some(c), ..
+            } => c.same_conductor(conductor),
+            SessionState::Config {
+                return_to: None, ..
+            } => false,
+        };
+
+        let moving = self.reloading.values().any(|c| c.same_conductor(conductor));
+        if moving || conductor.is_starting_sessions() {
+            return;
+        }
+        // Dropping the sender resumes the conductor
+        self.retiring.retain(|(c, _)| !c.same_conductor(conductor));
+
+        if !self.sessions.values().any(uses)
+            && let Err(e) = conductor.shutdown().await
+        {
+            tracing::debug!("Conductor already stopped: {}", e);
+        }
+    }
+
+    /// Pin the unpinned agents and mods of a configuration to the versions they
+    /// resolve to now.
+    ///
+    /// Looking the versions up can take a while, so it happens in the background;
+    /// the result comes back as [`ConfigAgentMessage::VersionsRecorded`].
+    fn record_versions(
+        &self,
+        workspace_path: &Path,
+        mut agent: Option<GlobalAgentConfig>,
+        mut mods: WorkspaceModsConfig,
+        config_agent_tx: &UnboundedSender<ConfigAgentMessage>,
+        cx: &JrConnectionCx<AgentToClient>,
+    ) {
+        let tx = config_agent_tx.clone();
+        let workspace_path = workspace_path.to_path_buf();
+        let result = cx.spawn(async move {
+            if let Some(agent) = &mut agent {
+                versions::record_agent_version(agent).await;
+            }
+            versions::record_mod_versions(&mut mods).await;
+            // The ConfigAgent may have stopped meanwhile
+            let _ = tx.unbounded_send(ConfigAgentMessage::VersionsRecorded {
+                workspace_path,
+                agent,
+                mods,
+            });
+            Ok(())
+        });
+        if let Err(e) = result {
+            tracing::warn!("Failed to spawn version lookup: {}", e);
+        }
+    }
+
+    /// Save the pins from [`Self::record_versions`] to the configuration on disk,
+    /// for the agents and mods that are still there and unpinned.
+    ///
+    /// The workspace's conductor takes the pins over as well, so new sessions
+    /// keep using it.
+    async fn save_recorded_versions(
+        &self,
+        workspace_path: &Path,
+        agent: Option<GlobalAgentConfig>,
+        mods: &WorkspaceModsConfig,
+        uberconductor: &UberconductorHandle,
+    ) -> Result<(), sacp::Error> {
+        if let Some(pinned) = &agent
+            && let Ok(Some(mut config)) = self.load_global_agent()
+            && versions::copy_agent_pin(pinned, &mut config)
+            && let Err(e) = config.save(&self.config_paths).await
+        {
+            tracing::warn!("Failed to save agent version: {}", e);
+        }
+
+        if let Ok(Some(mut config)) = self.load_mods(workspace_path) {
+            let mut changed = versions::copy_mod_pins(&mods.mods, &mut config.mods);
+            if let (Some(pinned), Some(agent)) = (&mods.agent, &mut config.agent) {
+                changed |= versions::copy_agent_pin(pinned, agent);
+            }
+            if changed && let Err(e) = config.save(&self.config_paths, workspace_path).await {
+                tracing::warn!("Failed to save mod versions: {}", e);
+            }
+        }
+
+        let agent = mods.agent.as_deref().cloned().or(agent);
+        uberconductor
+            .adopt_pins(workspace_path.to_path_buf(), agent, mods.mods.clone())
+            .await
+    }
+
     /// Handle output from a config mode actor.
     async fn handle_config_mode_output(
         &mut self,
         session_id: SessionId,
         output: ConfigModeOutput,
-        _uberconductor: &UberconductorHandle,
+        uberconductor: &UberconductorHandle,
         cx: &JrConnectionCx<AgentToClient>,
+        config_agent_tx: &UnboundedSender<ConfigAgentMessage>,
     ) -> Result<(), sacp::Error> {
         match output {
             ConfigModeOutput::SendMessage(text) => {
@@ -271,8 +984,7 @@ impl ConfigAgent {
                 };
 
                 // Save the global agent configuration
-                let global_agent_config = GlobalAgentConfig::new(agent);
-                if let Err(e) = global_agent_config.save(&self.config_paths).await {
+                if let Err(e) = agent.save(&self.config_paths).await {
                     cx.send_notification(SessionNotification::new(
                         session_id.clone(),
                         SessionUpdate::AgentMessageChunk(ContentChunk::new(
@@ -291,31 +1003,82 @@ impl ConfigAgent {
                     ))?;
                 }
 
+                // Pin whatever is unpinned to the version it resolves to now
+                self.record_versions(
+                    &workspace_path,
+                    Some(agent.clone()),
+                    mods.clone(),
+                    config_agent_tx,
+                    cx,
+                );
+
+                // The user has seen the recommendations; only report later changes
+                self.refresh_recommendation_diff(&workspace_path, &mods);
+
+                // The workspace runs its own agent, if it has one
+                let agent = mods.agent_or(&agent);
+
                 if let Some(conductor) = return_to {
                     // Return to the previous session
                     self.sessions.insert(
                         session_id.clone(),
                         SessionState::Delegating {
                             conductor,
-                            workspace_path,
+                            workspace_path: workspace_path.clone(),
                         },
                     );
+                    let text = if self
+                        .reload_workspace(&workspace_path, agent, mods.mods, uberconductor)
+                        .await?
+                    {
+                        "Configuration saved. Reloading mods for your session...\n"
+                    } else {
+                        "Configuration saved. Returning to your session."
+                    };
                     cx.send_notification(SessionNotification::new(
                         session_id,
-                        SessionUpdate::AgentMessageChunk(ContentChunk::new(
-                            "Configuration saved. Returning to your session.".into(),
-                        )),
+                        SessionUpdate::AgentMessageChunk(ContentChunk::new(text.into())),
                     ))?;
                 } else {
                     // No session to return to - this was initial setup or standalone
-                    // For now, just remove the session
                     self.sessions.remove(&session_id);
+                    self.unwatch_if_unused(&workspace_path);
                     cx.send_notification(SessionNotification::new(
-                        session_id,
+                        session_id.clone(),
                         SessionUpdate::AgentMessageChunk(ContentChunk::new(
-                            "Configuration saved. Please start a new session.".into(),
+                            "Configuration saved. Please start a new session.\n".into(),
                         )),
                     ))?;
+
+                    // Start the workspace's conductor right away, so that any downloads
+                    // and installs are shown here rather than delaying the next session.
+                    // Sessions already open in the workspace move to it.
+                    self.setup_sessions
+                        .entry(workspace_path.clone())
+                        .or_default()
+                        .push(session_id);
+                    let started = match self
+                        .reload_workspace(
+                            &workspace_path,
+                            agent.clone(),
+                            mods.mods.clone(),
+                            uberconductor,
+                        )
+                        .await
+                    {
+                        Ok(true) => Ok(()),
+                        Ok(false) => {
+                            uberconductor
+                                .prepare(workspace_path.clone(), agent, mods.mods)
+                                .await
+                        }
+                        Err(error) => Err(error),
+                    };
+                    if started.is_err() {
+                        // No ResolutionFinished is coming for this workspace
+                        self.setup_sessions.remove(&workspace_path);
+                    }
+                    started?;
                 }
             }
 
@@ -358,6 +1121,7 @@ impl ConfigAgent {
                 } else {
                     // No session to return to
                     self.sessions.remove(&session_id);
+                    self.unwatch_if_unused(&workspace_path);
                     cx.send_notification(SessionNotification::new(
                         session_id,
                         SessionUpdate::AgentMessageChunk(ContentChunk::new(
@@ -433,6 +1197,7 @@ impl ConfigAgent {
                 if let Err(e) = config.save(&self.config_paths, &workspace_path).await {
                     tracing::warn!("Failed to save initial mods config: {}", e);
                 }
+                self.record_versions(&workspace_path, None, config.clone(), config_agent_tx, cx);
 
                 config
             }
@@ -445,37 +1210,34 @@ impl ConfigAgent {
         );
 
         // Check for recommendation diff on mods
-        if let Some(recs) = self.load_recommendations() {
-            tracing::debug!(?recs);
-            let workspace_recs = recs.for_workspace(&workspace_path);
-            if let Some(diff) = workspace_recs.diff_against(&mods_config) {
-                tracing::debug!(?diff, "handle_new_session: diff computed");
+        let diff = self.recommendation_diff(&workspace_path, &mods_config);
+        if !diff.is_empty() {
+            tracing::debug!(?diff, "handle_new_session: diff computed");
 
-                let session_id = SessionId::new(uuid::Uuid::new_v4().to_string());
-                request_cx.respond(NewSessionResponse::new(session_id.clone()))?;
+            let session_id = SessionId::new(uuid::Uuid::new_v4().to_string());
+            request_cx.respond(NewSessionResponse::new(session_id.clone()))?;
 
-                let actor_handle = ConfigModeHandle::spawn_with_recommendations(
-                    agent,
-                    mods_config,
-                    workspace_path.clone(),
-                    self.config_paths.clone(),
-                    diff,
-                    session_id.clone(),
-                    config_agent_tx.clone(),
-                    cx,
-                )?;
+            let actor_handle = ConfigModeHandle::spawn_with_recommendations(
+                agent,
+                mods_config,
+                workspace_path.clone(),
+                self.config_paths.clone(),
+                diff,
+                session_id.clone(),
+                config_agent_tx.clone(),
+                cx,
+            )?;
 
-                self.sessions.insert(
-                    session_id,
-                    SessionState::Config {
-                        actor: actor_handle,
-                        workspace_path,
-                        return_to: None,
-                    },
-                );
+            self.sessions.insert(
+                session_id,
+                SessionState::Config {
+                    actor: actor_handle,
+                    workspace_path,
+                    return_to: None,
+                },
+            );
 
-                return Ok(());
-            }
+            return Ok(());
         }
 
         tracing::debug!(
@@ -485,11 +1247,39 @@ impl ConfigAgent {
         );
 
         // No diff changes - proceed directly to uberconductor
+        let agent = mods_config.agent_or(&agent);
         uberconductor
             .new_session(workspace_path, agent, mods_config.mods, request, request_cx)
             .await
     }
 
+    /// Handle a request to load a session, e.g. one from before the editor restarted.
+    ///
+    /// The workspace's conductor is started if needed and passes the request on
+    /// to the agent, which replays the conversation.
+    async fn handle_load_session(
+        &mut self,
+        request: LoadSessionRequest,
+        request_cx: JrRequestCx<LoadSessionResponse>,
+        uberconductor: &UberconductorHandle,
+    ) -> Result<(), sacp::Error> {
+        let workspace_path = request.cwd.clone();
+
+        let (Some(agent), Some(mods_config)) =
+            (self.load_global_agent()?, self.load_mods(&workspace_path)?)
+        else {
+            return request_cx.respond_with_error(sacp::Error::new(
+                -32600,
+                "Symposium is not configured for this workspace; start a new session to set it up",
+            ));
+        };
+
+        let agent = mods_config.agent_or(&agent);
+        uberconductor
+            .load_session(workspace_path, agent, mods_config.mods, request, request_cx)
+            .await
+    }
+
     /// Enter configuration mode for a session.
     ///
     /// If the session was delegating to a conductor, we store the conductor handle
@@ -528,9 +1318,10 @@ impl ConfigAgent {
             // The normal case: both exist, configure them
             (Some(agent), Some(mods)) => ConfigModeHandle::spawn_reconfig(
                 agent,
-                mods,
+                mods.clone(),
                 workspace_path.clone(),
                 self.config_paths.clone(),
+                self.recommendation_diff(&workspace_path, &mods),
                 session_id.clone(),
                 config_agent_tx.clone(),
                 resume_tx,
@@ -598,6 +1389,19 @@ impl ConfigAgent {
         let session_state = self.sessions.get(&session_id).cloned();
 
         match session_state {
+            Some(SessionState::Delegating { .. }) if self.reloading.contains_key(&session_id) => {
+                request_cx.respond_with_error(sacp::Error::new(
+                    -32603,
+                    "Mods are reloading for this session. Try again in a moment.",
+                ))
+            }
+            Some(SessionState::Delegating { conductor, .. }) if conductor.is_closed() => {
+                // The workspace's mods stopped; loading the session starts them again
+                request_cx.respond_with_error(sacp::Error::new(
+                    -32603,
+                    "Mods for this workspace stopped. Reopen the session to continue.",
+                ))
+            }
             Some(SessionState::Delegating { conductor, .. }) => {
                 // Check if this is the config command
                 if Self::is_config_command(&request) {
@@ -710,7 +1514,9 @@ impl ConfigAgent {
                 async |_init: InitializeRequest, request_cx: JrRequestCx<InitializeResponse>| {
                     request_cx.respond(
                         InitializeResponse::new(sacp::schema::ProtocolVersion::LATEST)
-                            .agent_capabilities(AgentCapabilities::new()),
+                            .agent_capabilities(
+                                AgentCapabilities::new().load_session(self.can_load_sessions()),
+                            ),
                     )
                 },
             )
@@ -722,6 +1528,14 @@ impl ConfigAgent {
                 },
             )
             .await
+            .if_request(
+                async |request: LoadSessionRequest,
+                       request_cx: JrRequestCx<LoadSessionResponse>| {
+                    self.handle_load_session(request, request_cx, uberconductor)
+                        .await
+                },
+            )
+            .await
             .if_request(
                 async |request: PromptRequest, request_cx: JrRequestCx<PromptResponse>| {
                     self.handle_prompt(request, request_cx, cx, config_agent_tx)
@@ -763,11 +1577,92 @@ pub enum ConfigAgentMessage {
         response: NewSessionResponse,
         conductor: ConductorHandle,
         workspace_path: PathBuf,
+        /// Progress starting the conductor, if the session waited for it
+        progress: Vec<String>,
+        /// Problems starting the conductor's mods, shown in the new session
+        warnings: Vec<String>,
         request_cx: JrRequestCx<NewSessionResponse>,
+        /// Keeps the conductor from being retired until the session is stored
+        starting: StartingSession,
+    },
+
+    /// Sent when a conductor has loaded a session.
+    /// ConfigAgent stores the session mapping, then responds to the client.
+    SessionLoaded {
+        session_id: SessionId,
+        response: LoadSessionResponse,
+        conductor: ConductorHandle,
+        workspace_path: PathBuf,
+        /// Progress starting the conductor, if the session waited for it
+        progress: Vec<String>,
+        /// Problems starting the conductor's mods, shown in the session
+        warnings: Vec<String>,
+        request_cx: JrRequestCx<LoadSessionResponse>,
+        /// Keeps the conductor from being retired until the session is stored
+        starting: StartingSession,
+    },
+
+    /// Sent when a conductor has tried to take over a session after a
+    /// configuration change (see `UberconductorHandle::reload`).
+    HandoverFinished {
+        session_id: SessionId,
+        /// The conductor that took over the session
+        conductor: ConductorHandle,
+        workspace_path: PathBuf,
+        /// Problems starting the conductor's mods, shown in the session
+        warnings: Vec<String>,
+        /// Why the session stays with its previous conductor, if it does
+        result: Result<(), String>,
     },
 
     /// Output from a config mode actor.
     ConfigModeOutput(SessionId, ConfigModeOutput),
+
+    /// A conductor's agent told whether it supports `session/load`.
+    /// ConfigAgent remembers it for the next `initialize`.
+    AgentCapabilities {
+        agent: GlobalAgentConfig,
+        load_session: bool,
+    },
+
+    /// A conductor is downloading or installing something while starting up.
+    /// ConfigAgent shows it in the sessions waiting for the conductor.
+    ResolutionProgress {
+        workspace_path: PathBuf,
+        message: String,
+    },
+
+    /// A conductor finished resolving its agent and mods.
+    ResolutionFinished {
+        workspace_path: PathBuf,
+        /// Why resolution failed, if it did
+        error: Option<String>,
+    },
+
+    /// A mod was skipped, crashed, or stopped the conductor.
+    /// ConfigAgent shows it in the workspace's sessions.
+    ModWarning {
+        workspace_path: PathBuf,
+        message: String,
+    },
+
+    /// The versions of a configuration's unpinned agents and mods were looked up.
+    /// ConfigAgent saves them as pins (see `ConfigAgent::record_versions`).
+    VersionsRecorded {
+        workspace_path: PathBuf,
+        /// The global agent, if it was to be pinned
+        agent: Option<GlobalAgentConfig>,
+        mods: WorkspaceModsConfig,
+    },
+
+    /// A workspace's configuration changed and a new conductor took the place
+    /// of this one for new sessions (see `UberconductorHandle::new_session`).
+    /// ConfigAgent stops it once no session uses it.
+    ConductorReplaced { conductor: ConductorHandle },
+
+    /// A file that a watched workspace's recommendations depend on changed.
+    /// ConfigAgent recomputes the recommendation diff.
+    RecommendationInputsChanged { workspace_path: PathBuf },
 }
 
 impl Component<AgentToClient> for ConfigAgent {
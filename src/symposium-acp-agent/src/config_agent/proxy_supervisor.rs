@@ -0,0 +1,339 @@
+//! Supervision for proxy mods.
+//!
+//! The conductor runs every proxy mod as a [`SupervisedProxy`], which sits
+//! between the conductor and the proxy process and relays messages unchanged.
+//! When the process exits mid-session:
+//! - requests the proxy was handling fail with an error
+//! - the crash is reported to the workspace's sessions
+//! - a fresh process is started and re-initialized, without re-initializing
+//!   the agent behind it; the agent and the other proxies keep running
+//!
+//! The new process hasn't seen the sessions created before the crash, so mods
+//! that keep per-session state start over. After [`MAX_RESTARTS`] crashes the
+//! mod's [`FailurePolicy`] decides: optional mods are bypassed (messages pass
+//! straight through to the next component), required mods stop the conductor.
+
+use crate::user_config::FailurePolicy;
+use futures::future::{BoxFuture, Either};
+use futures::{FutureExt, StreamExt};
+use sacp::jsonrpcmsg::{self, Id, Message, Params, Request, Response};
+use sacp::link::{ConductorToProxy, ProxyToConductor};
+use sacp::schema::{METHOD_INITIALIZE_PROXY, METHOD_SUCCESSOR_MESSAGE, McpServer};
+use sacp::{Channel, Component};
+use sacp_tokio::AcpAgent;
+use std::sync::Arc;
+
+/// How often a proxy is restarted before its failure policy applies
+pub const MAX_RESTARTS: usize = 3;
+
+/// JSON-RPC "internal error" code, used for requests lost in a crash
+const INTERNAL_ERROR_CODE: i32 = -32603;
+
+/// Receives the messages shown to the user when a proxy crashes.
+pub type CrashReporter = Arc<dyn Fn(String) + Send + Sync>;
+
+/// A proxy mod that is restarted when its process crashes.
+pub struct SupervisedProxy {
+    /// Display name of the mod, used in crash reports
+    name: String,
+    server: McpServer,
+    policy: FailurePolicy,
+    report: CrashReporter,
+}
+
+impl SupervisedProxy {
+    pub fn new(
+        name: String,
+        server: McpServer,
+        policy: FailurePolicy,
+        report: CrashReporter,
+    ) -> Self {
+        Self {
+            name,
+            server,
+            policy,
+            report,
+        }
+    }
+
+    /// Relay messages between the conductor and successive proxy processes.
+    async fn supervise(self, mut conductor: Channel) -> Result<(), sacp::Error> {
+        let mut replay = Replay::default();
+        let mut crashes = 0;
+
+        loop {
+            let bypass = crashes > MAX_RESTARTS;
+            let (mut proxy, process_side) = Channel::duplex();
+            let process: BoxFuture<'static, Result<(), sacp::Error>> = if bypass {
+                // sacp's default proxy behavior forwards everything to the successor
+                Box::pin(Component::<ProxyToConductor>::serve(
+                    ProxyToConductor::builder(),
+                    process_side,
+                ))
+            } else {
+                Box::pin(Component::<ProxyToConductor>::serve(
+                    AcpAgent::new(self.server.clone()),
+                    process_side,
+                ))
+            };
+            let mut process = process.fuse();
+
+            if crashes > 0 {
+                replay.start(&proxy, crashes);
+            }
+
+            // Requests from the conductor that the proxy hasn't answered yet
+            let mut pending: Vec<Id> = vec![];
+
+            let error = loop {
+                futures::select! {
+                    result = process => {
+                        break result.err().unwrap_or_else(|| {
+                            sacp::util::internal_error("Process exited")
+                        });
+                    }
+
+                    message = conductor.rx.next() => {
+                        // The conductor is shutting down
+                        let Some(message) = message else { return Ok(()) };
+                        match &message {
+                            Ok(Message::Request(request)) => {
+                                if request.method == METHOD_INITIALIZE_PROXY {
+                                    replay.initialize = Some(request.clone());
+                                }
+                                pending.extend(request.id.clone());
+                            }
+                            Ok(Message::Response(response)) => replay.record_response(response),
+                            Err(_) => {}
+                        }
+                        proxy.tx.unbounded_send(message).ok();
+                    }
+
+                    message = proxy.rx.next() => {
+                        let Some(message) = message else { continue };
+                        match &message {
+                            Ok(Message::Response(response)) => {
+                                if replay.is_replayed(response) {
+                                    continue;
+                                }
+                                pending.retain(|id| Some(id) != response.id.as_ref());
+                            }
+                            Ok(Message::Request(request)) => {
+                                if let Some(response) = replay.intercept(request) {
+                                    proxy.tx.unbounded_send(Ok(Message::Response(response))).ok();
+                                    continue;
+                                }
+                            }
+                            Err(_) => {}
+                        }
+                        conductor.tx.unbounded_send(message).ok();
+                    }
+                }
+            };
+
+            // Nobody will answer the requests the crashed process was handling
+            for id in pending {
+                let error = jsonrpcmsg::Error::new(
+                    INTERNAL_ERROR_CODE,
+                    format!("Mod `{}` crashed", self.name),
+                );
+                conductor
+                    .tx
+                    .unbounded_send(Ok(Message::Response(Response::error(error, Some(id)))))
+                    .ok();
+            }
+
+            tracing::warn!(name = %self.name, %error, crashes, "Proxy crashed");
+            crashes += 1;
+            if crashes <= MAX_RESTARTS {
+                (self.report)(format!(
+                    "Mod `{}` crashed and was restarted ({}).",
+                    self.name, error
+                ));
+            } else if bypass {
+                // The in-process pass-through failed, the conductor is going away
+                return Err(error);
+            } else {
+                match self.policy {
+                    FailurePolicy::Required => {
+                        (self.report)(format!(
+                            "Required mod `{}` crashed {} times and was stopped ({}).",
+                            self.name, crashes, error
+                        ));
+                        return Err(error);
+                    }
+                    FailurePolicy::Optional => {
+                        (self.report)(format!(
+                            "Mod `{}` crashed {} times and is now bypassed ({}).",
+                            self.name, crashes, error
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Component<ProxyToConductor> for SupervisedProxy {
+    async fn serve(self, client: impl Component<ConductorToProxy>) -> Result<(), sacp::Error> {
+        let (channel, conductor) = Channel::duplex();
+        match futures::future::select(
+            Box::pin(client.serve(channel)),
+            Box::pin(self.supervise(conductor)),
+        )
+        .await
+        {
+            Either::Left((result, _)) | Either::Right((result, _)) => result,
+        }
+    }
+}
+
+/// Re-initializes a restarted proxy.
+///
+/// The `_proxy/initialize` request from the conductor is sent to the new
+/// process again. When the proxy passes `initialize` on to its successor, it
+/// gets the successor's original answer instead, so the agent is initialized
+/// only once.
+#[derive(Default)]
+struct Replay {
+    /// The conductor's `_proxy/initialize` request
+    initialize: Option<Request>,
+    /// ID of the proxy's `initialize` request to its successor
+    successor_request: Option<Id>,
+    /// The successor's answer to `initialize`
+    successor_response: Option<serde_json::Value>,
+    /// ID of the replayed `_proxy/initialize`; its response is not relayed
+    replayed: Option<Id>,
+}
+
+impl Replay {
+    /// Send the recorded `_proxy/initialize` to a restarted process.
+    fn start(&mut self, proxy: &Channel, restart: usize) {
+        let Some(initialize) = &self.initialize else {
+            return;
+        };
+        let id = Id::String(format!("symposium-restart-{restart}"));
+        let mut request = initialize.clone();
+        request.id = Some(id.clone());
+        self.replayed = Some(id);
+        proxy.tx.unbounded_send(Ok(Message::Request(request))).ok();
+    }
+
+    /// Record the successor's answer to `initialize`, if this is it.
+    fn record_response(&mut self, response: &Response) {
+        if self.successor_request.is_some() && response.id == self.successor_request {
+            self.successor_response = response.result.clone();
+        }
+    }
+
+    /// Whether this is the proxy's response to the replayed `_proxy/initialize`.
+    fn is_replayed(&self, response: &Response) -> bool {
+        self.replayed.is_some() && response.id == self.replayed
+    }
+
+    /// Answer a restarted proxy's `initialize` for its successor, or note the
+    /// ID of the first one so its answer can be recorded.
+    fn intercept(&mut self, request: &Request) -> Option<Response> {
+        if !is_successor_initialize(request) {
+            return None;
+        }
+        match &self.successor_response {
+            Some(result) => Some(Response::success(result.clone(), request.id.clone())),
+            None => {
+                self.successor_request = request.id.clone();
+                None
+            }
+        }
+    }
+}
+
+/// Whether this is a proxy sending `initialize` to its successor.
+fn is_successor_initialize(request: &Request) -> bool {
+    request.method == METHOD_SUCCESSOR_MESSAGE
+        && matches!(
+            &request.params,
+            Some(Params::Object(params))
+                if params.get("method").and_then(|m| m.as_str()) == Some("initialize")
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(method: &str, params: serde_json::Value, id: u64) -> Request {
+        let Ok(Params::Object(params)) = serde_json::from_value(params) else {
+            panic!("params must be an object");
+        };
+        Request::new(
+            method.to_string(),
+            Some(Params::Object(params)),
+            Some(Id::Number(id)),
+        )
+    }
+
+    #[test]
+    fn test_replay_answers_successor_initialize() {
+        let mut replay = Replay::default();
+
+        // First run: the proxy forwards `initialize`, the successor answers
+        let forwarded = request(
+            METHOD_SUCCESSOR_MESSAGE,
+            json!({ "method": "initialize", "params": {} }),
+            7,
+        );
+        assert_eq!(replay.intercept(&forwarded), None);
+        replay.record_response(&Response::success(
+            json!({ "protocolVersion": 1 }),
+            Some(Id::Number(7)),
+        ));
+
+        // After a restart, the recorded answer is returned instead
+        let forwarded = request(
+            METHOD_SUCCESSOR_MESSAGE,
+            json!({ "method": "initialize", "params": {} }),
+            0,
+        );
+        assert_eq!(
+            replay.intercept(&forwarded),
+            Some(Response::success(
+                json!({ "protocolVersion": 1 }),
+                Some(Id::Number(0))
+            ))
+        );
+
+        // Other successor messages are relayed
+        let prompt = request(
+            METHOD_SUCCESSOR_MESSAGE,
+            json!({ "method": "session/prompt", "params": {} }),
+            1,
+        );
+        assert_eq!(replay.intercept(&prompt), None);
+    }
+
+    #[test]
+    fn test_replay_resends_proxy_initialize() {
+        let mut replay = Replay::default();
+        let (proxy, mut process_side) = Channel::duplex();
+
+        // Nothing to replay before the conductor initialized the proxy
+        replay.start(&proxy, 1);
+        assert!(process_side.rx.try_next().is_err());
+
+        replay.initialize = Some(request(METHOD_INITIALIZE_PROXY, json!({}), 0));
+        replay.start(&proxy, 2);
+        let Ok(Some(Ok(Message::Request(replayed)))) = process_side.rx.try_next() else {
+            panic!("expected the replayed request");
+        };
+        assert_eq!(replayed.method, METHOD_INITIALIZE_PROXY);
+        assert_eq!(
+            replayed.id,
+            Some(Id::String("symposium-restart-2".to_string()))
+        );
+
+        // The proxy's answer to the replayed request is not relayed
+        assert!(replay.is_replayed(&Response::success(json!({}), replayed.id.clone())));
+        assert!(!replay.is_replayed(&Response::success(json!({}), Some(Id::Number(0)))));
+    }
+}
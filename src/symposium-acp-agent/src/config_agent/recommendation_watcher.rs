@@ -0,0 +1,180 @@
+//! Watches the files a workspace's recommendations depend on.
+//!
+//! Recommendation diffs are computed when a session starts, so a dependency
+//! added mid-session would go unnoticed until the next one. While a workspace
+//! has sessions, ConfigAgent watches its `Cargo.toml` and `Cargo.lock`, its
+//! `.symposium/recommendations.toml` and `.symposium/mods.lock`, and the user's
+//! local recommendations, and recomputes the diff when one of them changes.
+//!
+//! Only the manifest and lockfile at the workspace root are watched. A
+//! dependency added to a member crate shows up once cargo (or the editor's
+//! language server) updates `Cargo.lock`.
+
+use crate::mods_lock::ModsLock;
+use crate::remote_recommendations::workspace_recommendations_path;
+use crate::user_config::ConfigPaths;
+use anyhow::Result;
+use futures::StreamExt;
+use futures::channel::mpsc::{UnboundedReceiver, unbounded};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How long the files must be left alone before a change is reported, so a
+/// build rewriting `Cargo.lock` several times is reported once
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Files whose changes can change a workspace's recommendation diff.
+pub fn recommendation_inputs(config_paths: &ConfigPaths, workspace_path: &Path) -> Vec<PathBuf> {
+    vec![
+        workspace_path.join("Cargo.toml"),
+        workspace_path.join("Cargo.lock"),
+        workspace_recommendations_path(workspace_path),
+        ModsLock::path(workspace_path),
+        config_paths.local_reccomendations_path(),
+    ]
+}
+
+/// Reports changes to a set of files.
+///
+/// Files are watched through their directories, so they may be created or
+/// replaced after the watcher starts. A directory that doesn't exist yet is
+/// watched once it appears in a watched directory (e.g. `.symposium`).
+pub struct FileWatcher {
+    watcher: RecommendedWatcher,
+    events: UnboundedReceiver<notify::Result<Event>>,
+    files: Vec<PathBuf>,
+    /// Directories of `files` that are not watched yet
+    unwatched: Vec<PathBuf>,
+}
+
+impl FileWatcher {
+    pub fn new(files: Vec<PathBuf>) -> Result<Self> {
+        let (tx, events) = unbounded();
+        let watcher = notify::recommended_watcher(move |event| {
+            // Fails only once the `FileWatcher` is dropped
+            let _ = tx.unbounded_send(event);
+        })?;
+
+        let mut unwatched: Vec<PathBuf> = files
+            .iter()
+            .filter_map(|file| file.parent())
+            .map(Path::to_path_buf)
+            .collect();
+        unwatched.sort();
+        unwatched.dedup();
+
+        let mut watcher = Self {
+            watcher,
+            events,
+            files,
+            unwatched,
+        };
+        watcher.watch_new_dirs();
+        Ok(watcher)
+    }
+
+    /// Wait until a watched file changes and the files have been left alone
+    /// for [`DEBOUNCE`].
+    ///
+    /// Returns false if the watcher stopped.
+    pub async fn changed(&mut self) -> bool {
+        loop {
+            let Some(event) = self.events.next().await else {
+                return false;
+            };
+            if self.is_relevant(event) {
+                break;
+            }
+        }
+
+        loop {
+            match tokio::time::timeout(DEBOUNCE, self.events.next()).await {
+                Ok(Some(event)) => {
+                    self.is_relevant(event);
+                }
+                Ok(None) => return false,
+                Err(_) => return true,
+            }
+        }
+    }
+
+    /// Whether an event touches one of the files.
+    ///
+    /// A directory of the files that was just created is watched from now on,
+    /// and counts as a change since the files may already be in it.
+    fn is_relevant(&mut self, event: notify::Result<Event>) -> bool {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                tracing::warn!("File watcher error: {}", e);
+                return false;
+            }
+        };
+        if matches!(event.kind, EventKind::Access(_)) {
+            return false;
+        }
+
+        let created_dir = event.paths.iter().any(|path| self.unwatched.contains(path));
+        if created_dir {
+            self.watch_new_dirs();
+        }
+        created_dir || event.paths.iter().any(|path| self.files.contains(path))
+    }
+
+    /// Start watching the directories that exist now.
+    fn watch_new_dirs(&mut self) {
+        let watcher = &mut self.watcher;
+        self.unwatched.retain(|dir| {
+            if !dir.is_dir() {
+                return true;
+            }
+            match watcher.watch(dir, RecursiveMode::NonRecursive) {
+                Ok(()) => false,
+                Err(e) => {
+                    tracing::warn!(?dir, "Failed to watch directory: {}", e);
+                    true
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether the watcher reports a change within a few seconds.
+    async fn reports_change(watcher: &mut FileWatcher) -> bool {
+        tokio::time::timeout(Duration::from_secs(5), watcher.changed())
+            .await
+            .unwrap_or(false)
+    }
+
+    #[tokio::test]
+    async fn test_reports_watched_files() {
+        let workspace = tempfile::TempDir::new().unwrap();
+        let config_dir = tempfile::TempDir::new().unwrap();
+        let config_paths = ConfigPaths::with_root(config_dir.path());
+        let mut watcher =
+            FileWatcher::new(recommendation_inputs(&config_paths, workspace.path())).unwrap();
+
+        // Other files in the workspace don't count
+        std::fs::write(workspace.path().join("README.md"), "# Hello").unwrap();
+        assert!(
+            tokio::time::timeout(DEBOUNCE * 3, watcher.changed())
+                .await
+                .is_err()
+        );
+
+        std::fs::write(workspace.path().join("Cargo.toml"), "[package]").unwrap();
+        assert!(reports_change(&mut watcher).await);
+
+        // `.symposium` doesn't exist when the watcher starts
+        let symposium_dir = workspace.path().join(".symposium");
+        std::fs::create_dir(&symposium_dir).unwrap();
+        assert!(reports_change(&mut watcher).await);
+        std::fs::write(symposium_dir.join("recommendations.toml"), "").unwrap();
+        assert!(reports_change(&mut watcher).await);
+    }
+}
@@ -0,0 +1,261 @@
+//! Saved sessions, so they can be loaded after Symposium restarts.
+//!
+//! ConfigAgent hands out the conductor's session IDs unchanged, so the ID the
+//! editor keeps is also the one the downstream agent knows. For each session
+//! we record a fingerprint of the agent and mods it ran with; the configs
+//! themselves are not stored, as they may contain secrets. When the editor asks
+//! to load the session later, the workspace's conductor is started again and
+//! the request goes on to the agent, which replays the conversation if it
+//! supports `session/load`.
+//!
+//! Whether an agent supports `session/load` is only known once it is running,
+//! so it is remembered ([`LoadSessionSupport`]) for the next `initialize`.
+
+use crate::user_config::{ConfigPaths, GlobalAgentConfig, ModConfig};
+use anyhow::{Context, Result};
+use sacp::schema::SessionId;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// How many sessions are kept per workspace; the least recently used go first
+const MAX_SAVED_SESSIONS: usize = 100;
+
+/// Metadata of one session.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SavedSession {
+    pub session_id: SessionId,
+    pub workspace_path: PathBuf,
+    /// Fingerprint of the agent and mods the session was last started with
+    /// (see [`config_fingerprint`])
+    pub config: String,
+    /// When the session was last created or loaded (RFC 3339)
+    pub last_used: String,
+}
+
+impl SavedSession {
+    pub fn new(
+        session_id: SessionId,
+        workspace_path: PathBuf,
+        agent: &GlobalAgentConfig,
+        mods: &[ModConfig],
+    ) -> Self {
+        Self {
+            session_id,
+            workspace_path,
+            config: config_fingerprint(agent, mods),
+            last_used: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Whether the session ran with a different agent or mods than `agent` and `mods`.
+    pub fn config_differs(&self, agent: &GlobalAgentConfig, mods: &[ModConfig]) -> bool {
+        self.config != config_fingerprint(agent, mods)
+    }
+}
+
+/// A fingerprint of an agent and mods, which tells configs apart without
+/// revealing them.
+pub fn config_fingerprint(agent: &GlobalAgentConfig, mods: &[ModConfig]) -> String {
+    fingerprint(&(agent, mods))
+}
+
+fn fingerprint(value: &impl Serialize) -> String {
+    let json = serde_json::to_vec(value).expect("configs serialize to JSON");
+    Sha256::digest(&json)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Which agents support `session/load`, keyed by a fingerprint of their config.
+///
+/// Stored at `<config_dir>/cache/load-session.json`
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LoadSessionSupport {
+    #[serde(default)]
+    pub agents: BTreeMap<String, bool>,
+}
+
+impl LoadSessionSupport {
+    /// Load what is known; empty if nothing is yet.
+    pub fn load(config_paths: &ConfigPaths) -> Result<Self> {
+        let path = config_paths.load_session_support_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub async fn save(&self, config_paths: &ConfigPaths) -> Result<()> {
+        let path = config_paths.load_session_support_path();
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        tokio::fs::write(&path, &content)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Whether the agent is known to support `session/load`
+    pub fn supports(&self, agent: &GlobalAgentConfig) -> bool {
+        self.agents
+            .get(&fingerprint(agent))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Record what an agent advertised; returns whether that is news.
+    pub fn record(&mut self, agent: &GlobalAgentConfig, load_session: bool) -> bool {
+        self.agents.insert(fingerprint(agent), load_session) != Some(load_session)
+    }
+}
+
+/// The saved sessions of a workspace, least recently used first.
+///
+/// Stored at `<config_dir>/config/<encoded-workspace-path>/sessions.json`
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SavedSessions {
+    #[serde(default)]
+    pub sessions: Vec<SavedSession>,
+}
+
+impl SavedSessions {
+    /// Load a workspace's saved sessions; empty if there are none yet.
+    pub fn load(config_paths: &ConfigPaths, workspace_path: &Path) -> Result<Self> {
+        let path = config_paths.workspace_sessions_path(workspace_path);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read sessions from {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse sessions from {}", path.display()))
+    }
+
+    /// Save a workspace's sessions.
+    pub async fn save(&self, config_paths: &ConfigPaths, workspace_path: &Path) -> Result<()> {
+        config_paths
+            .ensure_workspace_config_dir(workspace_path)
+            .await?;
+        let path = config_paths.workspace_sessions_path(workspace_path);
+        let content = serde_json::to_string_pretty(self)?;
+        tokio::fs::write(&path, &content)
+            .await
+            .with_context(|| format!("Failed to write sessions to {}", path.display()))
+    }
+
+    pub fn get(&self, session_id: &SessionId) -> Option<&SavedSession> {
+        self.sessions.iter().find(|s| s.session_id == *session_id)
+    }
+
+    /// Record that a session was started, replacing what was saved for it before.
+    pub fn record(&mut self, session: SavedSession) {
+        self.sessions.retain(|s| s.session_id != session.session_id);
+        self.sessions.push(session);
+        if self.sessions.len() > MAX_SAVED_SESSIONS {
+            let excess = self.sessions.len() - MAX_SAVED_SESSIONS;
+            self.sessions.drain(..excess);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use symposium_recommendations::{ComponentSource, LocalDistribution};
+
+    fn agent(command: &str) -> GlobalAgentConfig {
+        GlobalAgentConfig::new(ComponentSource::Local(LocalDistribution {
+            name: None,
+            command: command.to_string(),
+            args: vec![],
+            env: BTreeMap::new(),
+        }))
+    }
+
+    fn session(id: &str) -> SavedSession {
+        SavedSession::new(
+            SessionId::new(id),
+            PathBuf::from("/work"),
+            &agent("elizacp"),
+            &[],
+        )
+    }
+
+    #[tokio::test]
+    async fn test_sessions_round_trip() {
+        let config_dir = tempfile::TempDir::new().unwrap();
+        let config_paths = ConfigPaths::with_root(config_dir.path());
+        let workspace = Path::new("/work");
+
+        let mut sessions = SavedSessions::load(&config_paths, workspace).unwrap();
+        assert!(sessions.sessions.is_empty());
+
+        sessions.record(session("a"));
+        sessions.record(session("b"));
+        sessions.save(&config_paths, workspace).await.unwrap();
+
+        let loaded = SavedSessions::load(&config_paths, workspace).unwrap();
+        assert_eq!(loaded, sessions);
+        assert!(loaded.get(&SessionId::new("a")).is_some());
+        assert!(loaded.get(&SessionId::new("c")).is_none());
+
+        // Other workspaces have their own sessions
+        let other = SavedSessions::load(&config_paths, Path::new("/other")).unwrap();
+        assert!(other.sessions.is_empty());
+    }
+
+    #[test]
+    fn test_record_keeps_most_recent() {
+        let mut sessions = SavedSessions::default();
+        for i in 0..MAX_SAVED_SESSIONS + 2 {
+            sessions.record(session(&i.to_string()));
+        }
+        // Using a session again moves it to the end
+        sessions.record(session("5"));
+
+        let ids: Vec<_> = sessions
+            .sessions
+            .iter()
+            .map(|s| s.session_id.to_string())
+            .collect();
+        assert_eq!(ids.len(), MAX_SAVED_SESSIONS);
+        assert_eq!(ids[0], "2");
+        assert_eq!(ids.last().unwrap(), "5");
+        assert_eq!(ids.iter().filter(|id| *id == "5").count(), 1);
+    }
+
+    #[test]
+    fn test_config_differs() {
+        let saved = session("a");
+        assert!(!saved.config_differs(&agent("elizacp"), &[]));
+        assert!(saved.config_differs(&agent("claude-code"), &[]));
+    }
+
+    #[tokio::test]
+    async fn test_load_session_support() {
+        let config_dir = tempfile::TempDir::new().unwrap();
+        let config_paths = ConfigPaths::with_root(config_dir.path());
+
+        let mut support = LoadSessionSupport::load(&config_paths).unwrap();
+        assert!(!support.supports(&agent("elizacp")));
+
+        assert!(support.record(&agent("elizacp"), true));
+        assert!(!support.record(&agent("elizacp"), true));
+        assert!(support.record(&agent("claude-code"), false));
+        support.save(&config_paths).await.unwrap();
+
+        let loaded = LoadSessionSupport::load(&config_paths).unwrap();
+        assert!(loaded.supports(&agent("elizacp")));
+        assert!(!loaded.supports(&agent("claude-code")));
+    }
+}
@@ -0,0 +1,290 @@
+//! Per-session trace files for ConfigAgent conductors.
+//!
+//! One conductor serves every session of a workspace, so its trace is split by
+//! ACP session: events go to `<trace_dir>/<workspace>/<timestamp>-<session>.jsons`,
+//! where `<workspace>` is the encoded workspace path (as for workspace configs).
+//! Events that don't belong to a session (`initialize`, log lines, ...) go to
+//! `<timestamp>-conductor.jsons` in the same directory.
+//!
+//! Responses carry no session ID, so they follow the request they answer. A
+//! `session/new` request is written to the new session's trace together with its
+//! response, once the response reveals the session ID.
+//!
+//! When a session moves to another conductor, its file is closed
+//! ([`SharedSessionTraces::close`]).
+
+use crate::trace_tools::request_key;
+use crate::user_config::encode_path;
+use fxhash::FxHashMap;
+use sacp_conductor::trace::{EventWriter, TraceEvent, WriteEvent};
+use std::fs::{File, OpenOptions};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Name of the trace file for events outside of any session
+const CONDUCTOR_TRACE: &str = "conductor";
+
+/// Routes a conductor's trace events to per-session files.
+pub struct SessionTraces {
+    /// Directory for this workspace's traces
+    dir: PathBuf,
+    /// When the conductor started, prefix of all its file names
+    timestamp: String,
+    /// Open trace files, keyed by session ID (or [`CONDUCTOR_TRACE`])
+    files: FxHashMap<String, EventWriter<BufWriter<File>>>,
+    /// Requests awaiting a response, keyed by [`request_key`]
+    requests: FxHashMap<String, PendingRequest>,
+}
+
+/// Where a request went, so its response can follow it.
+enum PendingRequest {
+    Session(String),
+    Conductor,
+    /// A `session/new` request, written once its session is known
+    NewSession(TraceEvent),
+}
+
+impl SessionTraces {
+    pub fn new(trace_dir: &Path, workspace_path: &Path) -> std::io::Result<Self> {
+        let dir = trace_dir.join(encode_path(workspace_path));
+        std::fs::create_dir_all(&dir)?;
+        let timestamp = chrono::Utc::now().format("%Y%m%d-%H%M%S").to_string();
+        tracing::info!("Tracing to {}", dir.display());
+        Ok(Self {
+            dir,
+            timestamp,
+            files: FxHashMap::default(),
+            requests: FxHashMap::default(),
+        })
+    }
+
+    /// Path of the trace file for a session (or [`CONDUCTOR_TRACE`]).
+    fn path(&self, name: &str) -> PathBuf {
+        // Session IDs are chosen by the agent; keep them from escaping `dir`
+        let name: String = name
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+                _ => '_',
+            })
+            .collect();
+        self.dir.join(format!("{}-{}.jsons", self.timestamp, name))
+    }
+
+    fn write(&mut self, name: &str, event: &TraceEvent) -> std::io::Result<()> {
+        if !self.files.contains_key(name) {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.path(name))?;
+            self.files
+                .insert(name.to_string(), EventWriter::new(BufWriter::new(file)));
+        }
+        self.files.get_mut(name).unwrap().write_event(event)
+    }
+
+    /// Close a session's trace file and forget its unanswered requests.
+    fn close(&mut self, session: &str) {
+        self.files.remove(session);
+        self.requests
+            .retain(|_, pending| !matches!(pending, PendingRequest::Session(s) if s == session));
+    }
+}
+
+/// [`SessionTraces`] shared between a conductor, which writes to them, and the
+/// actor that owns the conductor, which closes the traces of sessions that move away.
+#[derive(Clone)]
+pub struct SharedSessionTraces(Arc<Mutex<SessionTraces>>);
+
+impl SharedSessionTraces {
+    pub fn new(traces: SessionTraces) -> Self {
+        Self(Arc::new(Mutex::new(traces)))
+    }
+
+    /// Close the trace file of a session that no longer uses this conductor.
+    pub fn close(&self, session: &str) {
+        self.0.lock().unwrap().close(session);
+    }
+}
+
+impl WriteEvent for SharedSessionTraces {
+    fn write_event(&mut self, event: &TraceEvent) -> std::io::Result<()> {
+        self.0.lock().unwrap().write_event(event)
+    }
+}
+
+impl WriteEvent for SessionTraces {
+    fn write_event(&mut self, event: &TraceEvent) -> std::io::Result<()> {
+        match event {
+            TraceEvent::Request(request) => {
+                let pending = match &request.session {
+                    Some(session) => PendingRequest::Session(session.clone()),
+                    None if request.method == "session/new" => {
+                        PendingRequest::NewSession(event.clone())
+                    }
+                    None => PendingRequest::Conductor,
+                };
+                let key = request_key(&request.from, &request.to, &request.id);
+                let result = match &pending {
+                    PendingRequest::Session(session) => self.write(session, event),
+                    PendingRequest::Conductor => self.write(CONDUCTOR_TRACE, event),
+                    PendingRequest::NewSession(_) => Ok(()),
+                };
+                self.requests.insert(key, pending);
+                result
+            }
+
+            TraceEvent::Response(response) => {
+                // The response goes back the way the request came
+                let key = request_key(&response.to, &response.from, &response.id);
+                match self.requests.remove(&key) {
+                    Some(PendingRequest::Session(session)) => self.write(&session, event),
+                    Some(PendingRequest::NewSession(request)) => {
+                        match response.payload.get("sessionId").and_then(|id| id.as_str()) {
+                            Some(session) => {
+                                self.write(session, &request)?;
+                                self.write(session, event)
+                            }
+                            None => {
+                                self.write(CONDUCTOR_TRACE, &request)?;
+                                self.write(CONDUCTOR_TRACE, event)
+                            }
+                        }
+                    }
+                    Some(PendingRequest::Conductor) | None => self.write(CONDUCTOR_TRACE, event),
+                }
+            }
+
+            TraceEvent::Notification(notification) => match &notification.session {
+                Some(session) => self.write(session, event),
+                None => self.write(CONDUCTOR_TRACE, event),
+            },
+
+            TraceEvent::Trace(_) => self.write(CONDUCTOR_TRACE, event),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sacp_conductor::trace::{NotificationEvent, Protocol, RequestEvent, ResponseEvent};
+    use serde_json::json;
+
+    fn request(method: &str, id: u64, session: Option<&str>) -> TraceEvent {
+        TraceEvent::Request(RequestEvent {
+            ts: 0.0,
+            protocol: Protocol::Acp,
+            from: "client".to_string(),
+            to: "agent".to_string(),
+            id: json!(id),
+            method: method.to_string(),
+            session: session.map(str::to_string),
+            params: json!({}),
+        })
+    }
+
+    fn response(id: u64, payload: serde_json::Value) -> TraceEvent {
+        TraceEvent::Response(ResponseEvent {
+            ts: 0.0,
+            from: "agent".to_string(),
+            to: "client".to_string(),
+            id: json!(id),
+            is_error: false,
+            payload,
+        })
+    }
+
+    fn methods(traces: &SessionTraces, name: &str) -> Vec<String> {
+        std::fs::read_to_string(traces.path(name))
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let event: serde_json::Value = serde_json::from_str(line).unwrap();
+                match event.get("method") {
+                    Some(method) => method.as_str().unwrap().to_string(),
+                    None => format!("response {}", event["id"]),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_events_are_split_by_session() {
+        let trace_dir = tempfile::TempDir::new().unwrap();
+        let mut traces = SessionTraces::new(trace_dir.path(), Path::new("/work/project")).unwrap();
+
+        let events = [
+            request("initialize", 0, None),
+            response(0, json!({})),
+            request("session/new", 1, None),
+            request("session/new", 2, None),
+            response(2, json!({ "sessionId": "b" })),
+            response(1, json!({ "sessionId": "a" })),
+            request("session/prompt", 3, Some("a")),
+            TraceEvent::Notification(NotificationEvent {
+                ts: 0.0,
+                protocol: Protocol::Acp,
+                from: "agent".to_string(),
+                to: "client".to_string(),
+                method: "session/update".to_string(),
+                session: Some("b".to_string()),
+                params: json!({}),
+            }),
+            response(3, json!({ "stopReason": "end_turn" })),
+        ];
+        for event in &events {
+            traces.write_event(event).unwrap();
+        }
+
+        assert!(traces.dir.starts_with(trace_dir.path()));
+        assert!(
+            traces
+                .dir
+                .ends_with(encode_path(Path::new("/work/project")))
+        );
+        assert_eq!(
+            methods(&traces, CONDUCTOR_TRACE),
+            ["initialize", "response 0"]
+        );
+        assert_eq!(
+            methods(&traces, "a"),
+            ["session/new", "response 1", "session/prompt", "response 3"]
+        );
+        assert_eq!(
+            methods(&traces, "b"),
+            ["session/new", "response 2", "session/update"]
+        );
+    }
+
+    #[test]
+    fn test_close_session() {
+        let trace_dir = tempfile::TempDir::new().unwrap();
+        let mut traces = SessionTraces::new(trace_dir.path(), Path::new("/work/project")).unwrap();
+
+        for event in [
+            request("session/prompt", 1, Some("a")),
+            request("session/prompt", 2, Some("b")),
+        ] {
+            traces.write_event(&event).unwrap();
+        }
+        traces.close("a");
+        assert!(!traces.files.contains_key("a"));
+        assert_eq!(traces.requests.len(), 1);
+
+        // A late response no longer belongs to the session
+        traces.write_event(&response(1, json!({}))).unwrap();
+        traces.write_event(&response(2, json!({}))).unwrap();
+        assert_eq!(methods(&traces, "a"), ["session/prompt"]);
+        assert_eq!(methods(&traces, "b"), ["session/prompt", "response 2"]);
+        assert_eq!(methods(&traces, CONDUCTOR_TRACE), ["response 1"]);
+    }
+
+    #[test]
+    fn test_session_ids_stay_in_trace_dir() {
+        let trace_dir = tempfile::TempDir::new().unwrap();
+        let traces = SessionTraces::new(trace_dir.path(), Path::new("/work/project")).unwrap();
+        assert_eq!(traces.path("../../etc/passwd").parent(), Some(&*traces.dir));
+    }
+}
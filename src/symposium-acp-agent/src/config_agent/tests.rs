@@ -297,6 +297,76 @@ async fn test_new_session_with_config() -> Result<(), sacp::Error> {
         .await
 }
 
+/// Test that a configuration changed outside of config mode (e.g. with
+/// `symposium-acp-agent config`) is used by the next new session.
+#[tokio::test]
+async fn test_new_session_after_config_change() -> Result<(), sacp::Error> {
+    init_tracing();
+
+    let config_temp_dir = TempDir::new().unwrap();
+    let config_paths = ConfigPaths::with_root(config_temp_dir.path());
+    let workspace_path = PathBuf::from("/fake/workspace");
+    let mods = empty_mods();
+    write_workspace_config(&config_paths, &workspace_path, &elizacp_agent(), &mods).await;
+
+    let config_agent = ConfigAgent::with_config_paths(config_paths.clone())
+        .with_recommendations(Recommendations::empty());
+
+    ClientToAgent::builder()
+        .connect_to(config_agent)?
+        .run_until(async |cx| {
+            cx.send_request(InitializeRequest::new(ProtocolVersion::LATEST))
+                .block_task()
+                .await?;
+
+            let first = cx
+                .send_request(NewSessionRequest::new(&workspace_path))
+                .block_task()
+                .await?
+                .session_id;
+
+            // Change the agent, as `config set-agent` would
+            let mut agent = GlobalAgentConfig::new(elizacp_agent());
+            if let ComponentSource::Local(local) = &mut agent.agent {
+                local.name = Some("eliza-2".to_string());
+            }
+            agent.save(&config_paths).await.unwrap();
+
+            let second = cx
+                .send_request(NewSessionRequest::new(&workspace_path))
+                .block_task()
+                .await?
+                .session_id;
+
+            // The first session keeps its conductor
+            cx.send_request(PromptRequest::new(
+                first.clone(),
+                vec![ContentBlock::Text(TextContent::new("Hello"))],
+            ))
+            .block_task()
+            .await?;
+
+            // Sessions are saved with the config of the conductor they run on
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let saved = SavedSessions::load(&config_paths, &workspace_path).unwrap();
+            let config = |session_id| saved.get(session_id).map(|s| s.config.clone());
+            assert_eq!(
+                config(&first),
+                Some(session_store::config_fingerprint(
+                    &GlobalAgentConfig::new(elizacp_agent()),
+                    &mods.mods
+                ))
+            );
+            assert_eq!(
+                config(&second),
+                Some(session_store::config_fingerprint(&agent, &mods.mods))
+            );
+
+            Ok(())
+        })
+        .await
+}
+
 /// Test that /symposium:config enters config mode.
 #[tokio::test]
 async fn test_config_mode_entry() -> Result<(), sacp::Error> {
@@ -374,3 +444,40 @@ async fn test_config_mode_entry() -> Result<(), sacp::Error> {
         })
         .await
 }
+
+/// Test that loading a session needs a configured workspace.
+#[tokio::test]
+async fn test_load_session_without_config() -> Result<(), sacp::Error> {
+    init_tracing();
+
+    let config_temp_dir = TempDir::new().unwrap();
+    let config_paths = ConfigPaths::with_root(config_temp_dir.path());
+    let workspace_path = PathBuf::from("/fake/workspace");
+
+    let agent =
+        ConfigAgent::with_config_paths(config_paths).with_recommendations(Recommendations::empty());
+
+    ClientToAgent::builder()
+        .connect_to(agent)?
+        .run_until(async |cx| {
+            let init_response = cx
+                .send_request(InitializeRequest::new(ProtocolVersion::LATEST))
+                .block_task()
+                .await?;
+            // No agent is configured, so none is known to load sessions
+            assert!(!init_response.agent_capabilities.load_session);
+
+            let result = cx
+                .send_request(LoadSessionRequest::new("earlier-session", &workspace_path))
+                .block_task()
+                .await;
+            let error = result.expect_err("loading without a configuration should fail");
+            assert!(
+                error.to_string().contains("not configured"),
+                "unexpected error: {error}"
+            );
+
+            Ok(())
+        })
+        .await
+}
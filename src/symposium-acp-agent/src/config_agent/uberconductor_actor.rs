@@ -3,31 +3,68 @@
 //! This actor:
 //! - Receives requests to create/get conductors for configurations
 //! - Maintains a map of config -> conductor handle
-//! - Spawns new conductors as needed
+//! - Spawns new conductors as needed (or ahead of time, see `prepare`)
 //! - Forwards new session requests to the appropriate conductor
+//! - Replaces a workspace's conductor when its configuration changes (see `reload`),
+//!   or when a session asks for another configuration than it runs
 
 use super::ConfigAgentMessage;
 use super::conductor_actor::ConductorHandle;
-use crate::user_config::ModConfig;
+use crate::user_config::{GlobalAgentConfig, ModConfig};
 use futures::channel::mpsc::UnboundedSender;
 use fxhash::FxHashMap;
 use sacp::link::AgentToClient;
-use sacp::schema::{NewSessionRequest, NewSessionResponse};
+use sacp::schema::{
+    LoadSessionRequest, LoadSessionResponse, NewSessionRequest, NewSessionResponse, SessionId,
+};
 use sacp::{JrConnectionCx, JrRequestCx};
-use std::path::PathBuf;
-use symposium_recommendations::ComponentSource;
+use std::path::{Path, PathBuf};
 use tokio::sync::mpsc;
 
 /// Messages that can be sent to the UberconductorActor.
 pub enum UberconductorMessage {
-    /// Create/get a conductor for this config and forward the session request to it.
+    /// Get the workspace's conductor if it runs this config (or start one)
+    /// and forward the session request to it.
     NewSession {
         workspace_path: PathBuf,
-        agent: ComponentSource,
+        agent: GlobalAgentConfig,
         mods: Vec<ModConfig>,
         request: NewSessionRequest,
         request_cx: JrRequestCx<NewSessionResponse>,
     },
+
+    /// Get the workspace's conductor if it runs this config (or start one)
+    /// and forward the load request to it.
+    LoadSession {
+        workspace_path: PathBuf,
+        agent: GlobalAgentConfig,
+        mods: Vec<ModConfig>,
+        request: LoadSessionRequest,
+        request_cx: JrRequestCx<LoadSessionResponse>,
+    },
+
+    /// Start a new conductor for this config, replacing the workspace's current one,
+    /// and move the given sessions to it.
+    Reload {
+        workspace_path: PathBuf,
+        agent: GlobalAgentConfig,
+        mods: Vec<ModConfig>,
+        sessions: Vec<SessionId>,
+    },
+
+    /// Start the conductor for this config if it isn't running yet, without creating a session.
+    Prepare {
+        workspace_path: PathBuf,
+        agent: GlobalAgentConfig,
+        mods: Vec<ModConfig>,
+    },
+
+    /// Pins were recorded for the workspace's configuration; its conductor takes them over.
+    AdoptPins {
+        workspace_path: PathBuf,
+        agent: Option<GlobalAgentConfig>,
+        mods: Vec<ModConfig>,
+    },
 }
 
 /// Handle for communicating with the UberconductorActor.
@@ -51,10 +88,15 @@ impl UberconductorHandle {
     }
 
     /// Request a new session with the given agent and mods.
+    ///
+    /// If the workspace's conductor runs another configuration (e.g. it was
+    /// changed with `symposium-acp-agent config`), a new conductor is started
+    /// for the session. The old one keeps its sessions; ConfigAgent stops it
+    /// once it has none (`ConductorReplaced`).
     pub async fn new_session(
         &self,
         workspace_path: PathBuf,
-        agent: ComponentSource,
+        agent: GlobalAgentConfig,
         mods: Vec<ModConfig>,
         request: NewSessionRequest,
         request_cx: JrRequestCx<NewSessionResponse>,
@@ -70,6 +112,89 @@ impl UberconductorHandle {
             .await
             .map_err(|_| sacp::util::internal_error("Uberconductor actor closed"))
     }
+
+    /// Request to load a session with the given agent and mods.
+    pub async fn load_session(
+        &self,
+        workspace_path: PathBuf,
+        agent: GlobalAgentConfig,
+        mods: Vec<ModConfig>,
+        request: LoadSessionRequest,
+        request_cx: JrRequestCx<LoadSessionResponse>,
+    ) -> Result<(), sacp::Error> {
+        self.tx
+            .send(UberconductorMessage::LoadSession {
+                workspace_path,
+                agent,
+                mods,
+                request,
+                request_cx,
+            })
+            .await
+            .map_err(|_| sacp::util::internal_error("Uberconductor actor closed"))
+    }
+
+    /// Replace the workspace's conductor with one for the given agent and mods,
+    /// and hand `sessions` over to it.
+    ///
+    /// The new conductor sends `HandoverFinished` to ConfigAgent for each session.
+    /// The old conductor keeps running; ConfigAgent shuts it down once no
+    /// session uses it anymore.
+    pub async fn reload(
+        &self,
+        workspace_path: PathBuf,
+        agent: GlobalAgentConfig,
+        mods: Vec<ModConfig>,
+        sessions: Vec<SessionId>,
+    ) -> Result<(), sacp::Error> {
+        self.tx
+            .send(UberconductorMessage::Reload {
+                workspace_path,
+                agent,
+                mods,
+                sessions,
+            })
+            .await
+            .map_err(|_| sacp::util::internal_error("Uberconductor actor closed"))
+    }
+
+    /// Start the conductor for a workspace ahead of its first session.
+    ///
+    /// The conductor reports its progress to ConfigAgent (`ResolutionProgress`), followed by
+    /// `ResolutionFinished`; if a conductor is already running, only the latter is sent.
+    pub async fn prepare(
+        &self,
+        workspace_path: PathBuf,
+        agent: GlobalAgentConfig,
+        mods: Vec<ModConfig>,
+    ) -> Result<(), sacp::Error> {
+        self.tx
+            .send(UberconductorMessage::Prepare {
+                workspace_path,
+                agent,
+                mods,
+            })
+            .await
+            .map_err(|_| sacp::util::internal_error("Uberconductor actor closed"))
+    }
+
+    /// Let the workspace's conductor take over pins that were recorded for the
+    /// configuration it started with (see `ConductorHandle::adopt_pins`).
+    pub async fn adopt_pins(
+        &self,
+        workspace_path: PathBuf,
+        agent: Option<GlobalAgentConfig>,
+        mods: Vec<ModConfig>,
+    ) -> Result<(), sacp::Error> {
+        self.tx
+            .send(UberconductorMessage::AdoptPins {
+                workspace_path,
+                agent,
+                mods,
+            })
+            .await
+            .map_err(|_| sacp::util::internal_error("Uberconductor actor closed"))
+    }
 }
 
 /// The main actor loop.
@@ -91,27 +216,124 @@ async fn run_actor(
                 request,
                 request_cx,
             } => {
-                // Get or create conductor for this workspace
-                let handle = match conductors.get(&workspace_path) {
-                    Some(handle) => handle.clone(),
-                    None => {
-                        let handle = ConductorHandle::spawn(
-                            workspace_path.clone(),
-                            agent,
-                            mods,
-                            trace_dir.as_ref(),
-                            config_agent_tx.clone(),
-                            &client_cx,
-                        )
-                        .await?;
-                        conductors.insert(workspace_path.clone(), handle.clone());
-                        handle
-                    }
-                };
+                let handle = get_or_spawn(
+                    &mut conductors,
+                    &workspace_path,
+                    agent,
+                    mods,
+                    trace_dir.as_ref(),
+                    &config_agent_tx,
+                    &client_cx,
+                )
+                .await?;
 
                 // Forward the session request to the conductor
                 // The conductor will send NewSessionCreated back to ConfigAgent
-                handle.send_new_session(request, request_cx).await?;
+                if let Err(e) = handle.send_new_session(request, request_cx).await {
+                    tracing::error!(?workspace_path, "Failed to start session: {}", e);
+                }
+            }
+
+            UberconductorMessage::LoadSession {
+                workspace_path,
+                agent,
+                mods,
+                request,
+                request_cx,
+            } => {
+                let handle = get_or_spawn(
+                    &mut conductors,
+                    &workspace_path,
+                    agent,
+                    mods,
+                    trace_dir.as_ref(),
+                    &config_agent_tx,
+                    &client_cx,
+                )
+                .await?;
+
+                // The conductor will send SessionLoaded back to ConfigAgent
+                if let Err(e) = handle.send_load_session(request, request_cx).await {
+                    tracing::error!(?workspace_path, "Failed to load session: {}", e);
+                }
+            }
+
+            UberconductorMessage::Reload {
+                workspace_path,
+                agent,
+                mods,
+                sessions,
+            } => {
+                let handle = ConductorHandle::spawn(
+                    workspace_path.clone(),
+                    agent,
+                    mods,
+                    trace_dir.as_ref(),
+                    config_agent_tx.clone(),
+                    &client_cx,
+                )
+                .await?;
+                replace(
+                    &mut conductors,
+                    &workspace_path,
+                    handle.clone(),
+                    &config_agent_tx,
+                );
+
+                for session_id in sessions {
+                    if let Err(e) = handle.send_handover(session_id.clone()).await {
+                        tracing::error!(?workspace_path, "Failed to hand over session: {}", e);
+                        config_agent_tx
+                            .unbounded_send(ConfigAgentMessage::HandoverFinished {
+                                session_id,
+                                conductor: handle.clone(),
+                                workspace_path: workspace_path.clone(),
+                                warnings: vec![],
+                                result: Err(e.to_string()),
+                            })
+                            .ok();
+                    }
+                }
+            }
+
+            UberconductorMessage::Prepare {
+                workspace_path,
+                agent,
+                mods,
+            } => {
+                if conductors
+                    .get(&workspace_path)
+                    .is_some_and(|handle| !handle.is_closed() && handle.has_config(&agent, &mods))
+                {
+                    config_agent_tx
+                        .unbounded_send(ConfigAgentMessage::ResolutionFinished {
+                            workspace_path,
+                            error: None,
+                        })
+                        .ok();
+                    continue;
+                }
+
+                let handle = ConductorHandle::spawn(
+                    workspace_path.clone(),
+                    agent,
+                    mods,
+                    trace_dir.as_ref(),
+                    config_agent_tx.clone(),
+                    &client_cx,
+                )
+                .await?;
+                replace(&mut conductors, &workspace_path, handle, &config_agent_tx);
+            }
+
+            UberconductorMessage::AdoptPins {
+                workspace_path,
+                agent,
+                mods,
+            } => {
+                if let Some(handle) = conductors.get(&workspace_path) {
+                    handle.adopt_pins(agent.as_ref(), &mods);
+                }
             }
         }
     }
@@ -119,3 +341,54 @@ async fn run_actor(
     tracing::debug!("Uberconductor actor shutting down");
     Ok(())
 }
+
+/// Get the conductor for a workspace, or create it.
+/// A conductor that stopped (e.g. a required mod failed) or that runs another
+/// agent or other mods is replaced.
+async fn get_or_spawn(
+    conductors: &mut FxHashMap<PathBuf, ConductorHandle>,
+    workspace_path: &Path,
+    agent: GlobalAgentConfig,
+    mods: Vec<ModConfig>,
+    trace_dir: Option<&PathBuf>,
+    config_agent_tx: &UnboundedSender<ConfigAgentMessage>,
+    client_cx: &JrConnectionCx<AgentToClient>,
+) -> Result<ConductorHandle, sacp::Error> {
+    if let Some(handle) = conductors
+        .get(workspace_path)
+        .filter(|handle| !handle.is_closed() && handle.has_config(&agent, &mods))
+    {
+        return Ok(handle.clone());
+    }
+
+    let handle = ConductorHandle::spawn(
+        workspace_path.to_path_buf(),
+        agent,
+        mods,
+        trace_dir,
+        config_agent_tx.clone(),
+        client_cx,
+    )
+    .await?;
+    replace(conductors, workspace_path, handle.clone(), config_agent_tx);
+    Ok(handle)
+}
+
+/// Make `handle` the workspace's conductor. The one it replaces is retired by
+/// ConfigAgent once no session uses it.
+fn replace(
+    conductors: &mut FxHashMap<PathBuf, ConductorHandle>,
+    workspace_path: &Path,
+    handle: ConductorHandle,
+    config_agent_tx: &UnboundedSender<ConfigAgentMessage>,
+) {
+    if let Some(previous) = conductors.insert(workspace_path.to_path_buf(), handle)
+        && !previous.is_closed()
+    {
+        config_agent_tx
+            .unbounded_send(ConfigAgentMessage::ConductorReplaced {
+                conductor: previous,
+            })
+            .ok();
+    }
+}
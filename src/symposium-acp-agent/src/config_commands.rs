@@ -0,0 +1,414 @@
+//! Non-interactive configuration (`symposium-acp-agent config ...`).
+//!
+//! These commands edit the same files as `/symposium:config` - the global
+//! [`GlobalAgentConfig`] and a workspace's [`WorkspaceModsConfig`] - so a
+//! configuration can be scripted or managed with dotfiles. Sessions that are
+//! already open keep their mods; new sessions use the changed configuration.
+//!
+//! Mods are named as in `config list`: by their 1-based number or by their
+//! name (the source's display name).
+
+use crate::recommendations::RecommendationDiff;
+use crate::user_config::{GlobalAgentConfig, ModConfig, WorkspaceModsConfig};
+use crate::versions::pinned_name;
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+use symposium_recommendations::{CargoDistribution, ComponentSource, ModKind};
+
+/// The configuration of a workspace, as printed by `config list`.
+#[derive(Debug, Serialize)]
+pub struct ConfigListing {
+    /// The workspace's agent: its own, or the global agent if one is configured
+    pub agent: Option<AgentListing>,
+    /// The workspace's mods, in chain order
+    pub mods: Vec<ModListing>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AgentListing {
+    pub name: String,
+    /// Whether this is the workspace's own agent rather than the global one
+    pub workspace: bool,
+    #[serde(flatten)]
+    pub config: GlobalAgentConfig,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModListing {
+    pub name: String,
+    #[serde(flatten)]
+    pub config: ModConfig,
+}
+
+impl ConfigListing {
+    pub fn new(global_agent: Option<GlobalAgentConfig>, mods: &WorkspaceModsConfig) -> Self {
+        let agent = match mods.agent.as_deref() {
+            Some(config) => Some((config.clone(), true)),
+            None => global_agent.map(|config| (config, false)),
+        };
+        Self {
+            agent: agent.map(|(config, workspace)| AgentListing {
+                name: config.agent.display_name(),
+                workspace,
+                config,
+            }),
+            mods: mods
+                .mods
+                .iter()
+                .map(|m| ModListing {
+                    name: m.source.display_name(),
+                    config: m.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Print the listing for people, one mod per line.
+    pub fn write(&self, workspace_path: &Path, out: &mut dyn Write) -> std::io::Result<()> {
+        match &self.agent {
+            Some(agent) => writeln!(
+                out,
+                "Agent: {}{}",
+                pinned_name(&agent.config.agent, &agent.config.version),
+                if agent.workspace {
+                    " (this workspace only)"
+                } else {
+                    ""
+                }
+            )?,
+            None => writeln!(out, "Agent: (none configured)")?,
+        }
+
+        writeln!(out, "Mods for {}:", workspace_path.display())?;
+        if self.mods.is_empty() {
+            writeln!(out, "  (none configured)")?;
+        }
+        for (m, number) in self.mods.iter().zip(1..) {
+            let mut notes = vec![];
+            if matches!(m.config.kind, ModKind::MCP) {
+                notes.push("MCP");
+            }
+            if !m.config.policy.is_optional() {
+                notes.push("required");
+            }
+            if !m.config.enabled {
+                notes.push("disabled");
+            }
+            let notes = if notes.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", notes.join(", "))
+            };
+            writeln!(
+                out,
+                "  {}. {}{}",
+                number,
+                pinned_name(&m.config.source, &m.config.version),
+                notes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Find a mod by its number in `config list` or by its name.
+///
+/// Returns its 0-based index in `config.mods`.
+pub fn find_mod(config: &WorkspaceModsConfig, name: &str) -> Result<usize> {
+    if let Ok(number) = name.parse::<usize>() {
+        if number == 0 || number > config.mods.len() {
+            bail!(
+                "There is no mod number {} (the workspace has {} mods)",
+                number,
+                config.mods.len()
+            );
+        }
+        return Ok(number - 1);
+    }
+
+    let matches: Vec<usize> = config
+        .mods
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m.source.display_name() == name)
+        .map(|(index, _)| index)
+        .collect();
+    match matches[..] {
+        [index] => Ok(index),
+        [] => bail!("No mod named `{}` in this workspace", name),
+        _ => bail!(
+            "Several mods are named `{}`; use their number from `config list`",
+            name
+        ),
+    }
+}
+
+/// Parse a mod or agent source given on the command line:
+/// a `ComponentSource` as JSON, `cargo:<crate>`, or a registry ID.
+pub fn parse_source(spec: &str) -> Result<ComponentSource> {
+    if spec.starts_with('{') {
+        return serde_json::from_str(spec)
+            .with_context(|| format!("Failed to parse source `{}`", spec));
+    }
+    if let Some(crate_name) = spec.strip_prefix("cargo:") {
+        return Ok(ComponentSource::Cargo(CargoDistribution {
+            crate_name: crate_name.to_string(),
+            version: None,
+            binary: None,
+            args: vec![],
+        }));
+    }
+    Ok(ComponentSource::Registry(spec.to_string()))
+}
+
+/// Add a mod at the end of the chain, enabled.
+pub fn add_mod(
+    config: &mut WorkspaceModsConfig,
+    kind: ModKind,
+    source: ComponentSource,
+) -> Result<()> {
+    if config.mods.iter().any(|m| m.source == source) {
+        bail!("`{}` is already configured", source.display_name());
+    }
+    config.push_mod(ModConfig {
+        kind,
+        source,
+        enabled: true,
+        when: Default::default(),
+        order: 0,
+        version: None,
+        policy: Default::default(),
+        overrides: Default::default(),
+    });
+    Ok(())
+}
+
+/// Print a diff for people: `+` for newly recommended mods, `-` for mods that
+/// are no longer recommended and `!` for lockfile mismatches.
+pub fn write_diff(diff: &RecommendationDiff, out: &mut dyn Write) -> std::io::Result<()> {
+    if diff.is_empty() {
+        return writeln!(out, "The configuration matches the recommendations.");
+    }
+    let reasons = |reasons: Vec<String>| {
+        if reasons.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", reasons.join(", "))
+        }
+    };
+    for m in &diff.to_add {
+        writeln!(
+            out,
+            "+ {}{}",
+            m.source.display_name(),
+            reasons(m.when.explain_why_added())
+        )?;
+    }
+    for m in &diff.to_remove {
+        writeln!(
+            out,
+            "- {}{}",
+            m.source.display_name(),
+            reasons(m.when.explain_why_stale())
+        )?;
+    }
+    for mismatch in &diff.lock_mismatches {
+        writeln!(out, "! {}", mismatch)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mods_lock::LockMismatch;
+    use crate::recommendations::{Recommendations, workspace_diff};
+    use expect_test::expect;
+    use symposium_recommendations::When;
+
+    fn mods(names: &[&str]) -> WorkspaceModsConfig {
+        let mut config = WorkspaceModsConfig::new(vec![]);
+        for name in names {
+            add_mod(
+                &mut config,
+                ModKind::Proxy,
+                ComponentSource::Builtin(name.to_string()),
+            )
+            .unwrap();
+        }
+        config
+    }
+
+    #[test]
+    fn test_find_mod() {
+        let config = mods(&["sparkle", "ferris"]);
+        assert_eq!(find_mod(&config, "2").unwrap(), 1);
+        assert_eq!(find_mod(&config, "sparkle").unwrap(), 0);
+        assert!(find_mod(&config, "0").is_err());
+        assert!(find_mod(&config, "3").is_err());
+        assert!(find_mod(&config, "cargo").is_err());
+    }
+
+    #[test]
+    fn test_find_mod_ambiguous() {
+        let mut config = mods(&["ferris"]);
+        add_mod(
+            &mut config,
+            ModKind::MCP,
+            ComponentSource::Registry("ferris".to_string()),
+        )
+        .unwrap();
+        assert!(find_mod(&config, "ferris").is_err());
+        assert_eq!(find_mod(&config, "2").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_parse_source() {
+        assert_eq!(
+            parse_source("ferris").unwrap(),
+            ComponentSource::Registry("ferris".to_string())
+        );
+        let ComponentSource::Cargo(cargo) = parse_source("cargo:sparkle-mcp").unwrap() else {
+            panic!("expected a cargo source");
+        };
+        assert_eq!(cargo.crate_name, "sparkle-mcp");
+        assert_eq!(
+            parse_source(r#"{"builtin": "eliza"}"#).unwrap(),
+            ComponentSource::Builtin("eliza".to_string())
+        );
+        assert!(parse_source("{not json").is_err());
+    }
+
+    #[test]
+    fn test_add_mod_twice() {
+        let mut config = mods(&["sparkle"]);
+        assert!(
+            add_mod(
+                &mut config,
+                ModKind::Proxy,
+                ComponentSource::Builtin("sparkle".to_string())
+            )
+            .is_err()
+        );
+        assert_eq!(config.mods.len(), 1);
+    }
+
+    #[test]
+    fn test_write_listing() {
+        let mut config = mods(&["sparkle", "ferris", "cargo"]);
+        config.mods[0].version = Some("0.3.1".to_string());
+        config.mods[1].enabled = false;
+        config.mods[2].kind = ModKind::MCP;
+        let agent = GlobalAgentConfig::new(ComponentSource::Builtin("eliza".to_string()));
+
+        let mut out = vec![];
+        ConfigListing::new(Some(agent.clone()), &config)
+            .write(Path::new("/work"), &mut out)
+            .unwrap();
+        expect![[r#"
+            Agent: eliza
+            Mods for /work:
+              1. sparkle@0.3.1
+              2. ferris (disabled)
+              3. cargo (MCP)
+        "#]]
+        .assert_eq(&String::from_utf8(out).unwrap());
+
+        // The workspace's own agent replaces the global agent
+        config.agent = Some(Box::new(GlobalAgentConfig::new(ComponentSource::Builtin(
+            "ferris-agent".to_string(),
+        ))));
+        let mut out = vec![];
+        ConfigListing::new(Some(agent), &config)
+            .write(Path::new("/work"), &mut out)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap().lines().next(),
+            Some("Agent: ferris-agent (this workspace only)")
+        );
+    }
+
+    #[test]
+    fn test_listing_json() {
+        let listing = ConfigListing::new(None, &mods(&["sparkle"]));
+        expect![[r#"
+            {
+              "agent": null,
+              "mods": [
+                {
+                  "name": "sparkle",
+                  "kind": "proxy",
+                  "source": {
+                    "builtin": "sparkle"
+                  },
+                  "enabled": true,
+                  "when": {},
+                  "order": 0
+                }
+              ]
+            }"#]]
+        .assert_eq(&serde_json::to_string_pretty(&listing).unwrap());
+    }
+
+    #[test]
+    fn test_write_diff() {
+        let mut to_add = mods(&["ferris"]).mods;
+        to_add[0].when = When {
+            file_exists: Some("Cargo.toml".to_string()),
+            ..Default::default()
+        };
+        let diff = RecommendationDiff {
+            to_add,
+            to_remove: mods(&["old"]).mods,
+            lock_mismatches: vec![LockMismatch::NotEnabled {
+                name: "sparkle".to_string(),
+            }],
+        };
+
+        let mut out = vec![];
+        write_diff(&diff, &mut out).unwrap();
+        expect![[r#"
+            + ferris [because `Cargo.toml` exists]
+            - old
+            ! `sparkle` is locked but not enabled
+        "#]]
+        .assert_eq(&String::from_utf8(out).unwrap());
+
+        let mut out = vec![];
+        write_diff(&RecommendationDiff::default(), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "The configuration matches the recommendations.\n"
+        );
+    }
+
+    #[test]
+    fn test_workspace_diff_apply() {
+        let workspace = tempfile::TempDir::new().unwrap();
+        let recommendations = Recommendations::from_toml(
+            r#"
+[[recommendation]]
+source.builtin = "ferris"
+"#,
+        )
+        .unwrap();
+        let mut config = mods(&["old"]);
+
+        let diff = workspace_diff(Some(&recommendations), workspace.path(), &config);
+        assert_eq!(
+            diff.summary(),
+            "newly recommended: ferris; no longer recommended: old"
+        );
+
+        diff.apply(&mut config);
+        let names: Vec<_> = config
+            .mods
+            .iter()
+            .map(|m| m.source.display_name())
+            .collect();
+        assert_eq!(names, ["ferris"]);
+        assert!(workspace_diff(Some(&recommendations), workspace.path(), &config).is_empty());
+    }
+}
@@ -0,0 +1,354 @@
+//! Workspace dependency index - which crates a workspace uses
+//!
+//! Evaluating `using-crate` conditions needs the resolved dependency graph from
+//! `cargo metadata`, which is slow on large workspaces. The index runs it once
+//! per workspace and is reused until `Cargo.lock` (or the workspace `Cargo.toml`)
+//! changes.
+
+use anyhow::{Context, Result};
+use cargo_metadata::semver::{Version, VersionReq};
+use cargo_metadata::{DependencyKind, Metadata, MetadataCommand, Node, Package, PackageId};
+use fxhash::FxHashMap;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use symposium_recommendations::CrateRequirement;
+
+/// Indexes built so far, keyed by workspace path
+static INDEX_CACHE: LazyLock<Mutex<FxHashMap<PathBuf, CachedIndex>>> =
+    LazyLock::new(Default::default);
+
+struct CachedIndex {
+    /// Lockfile the index was resolved from
+    lock_path: PathBuf,
+    /// Hash of the manifest and lockfile contents when the index was built
+    stamp: u64,
+    index: Arc<WorkspaceDependencyIndex>,
+}
+
+/// A crate used by a workspace member
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedDependency {
+    /// Workspace member that depends on the crate
+    pub member: String,
+    /// Resolved version of the crate
+    pub version: Version,
+    /// How the member depends on it (normal, dev, build)
+    pub kinds: Vec<DependencyKind>,
+    /// Features enabled on the resolved crate
+    pub features: Vec<String>,
+}
+
+/// The resolved dependencies of every member of a workspace.
+///
+/// Battery packs (crates ending in `-battery-pack`) are "transparent" - their
+/// dependencies are indexed as if the member depended on them directly. This
+/// means if your workspace depends on `cli-battery-pack` which depends on `clap`,
+/// then `using-crate = "clap"` will match.
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceDependencyIndex {
+    members: Vec<String>,
+    dependencies: FxHashMap<String, Vec<ResolvedDependency>>,
+}
+
+impl WorkspaceDependencyIndex {
+    /// Load the index for a workspace, reusing a cached one if the workspace's
+    /// `Cargo.toml` and `Cargo.lock` are unchanged.
+    ///
+    /// Workspaces without a `Cargo.toml` get an empty index.
+    pub fn load(workspace_path: &Path) -> Result<Arc<Self>> {
+        let manifest_path = workspace_path.join("Cargo.toml");
+        if !manifest_path.exists() {
+            return Ok(Arc::new(Self::default()));
+        }
+
+        // The cache isn't locked while `cargo metadata` runs, so other
+        // workspaces aren't held up by this one
+        if let Some(cached) = INDEX_CACHE.lock().unwrap().get(workspace_path)
+            && cached.stamp == stamp(&manifest_path, &cached.lock_path)
+        {
+            return Ok(cached.index.clone());
+        }
+
+        tracing::debug!(?workspace_path, "Resolving workspace dependencies");
+        let mut command = MetadataCommand::new();
+        command.current_dir(workspace_path);
+        if crate::offline::is_offline() {
+            command.other_options(vec!["--offline".to_string()]);
+        }
+        let metadata = command.exec().with_context(|| {
+            format!(
+                "Failed to run cargo metadata in {}",
+                workspace_path.display()
+            )
+        })?;
+
+        // `cargo metadata` may have just written the lockfile, so stamp afterwards
+        let lock_path = metadata
+            .workspace_root
+            .join("Cargo.lock")
+            .into_std_path_buf();
+        let stamp = stamp(&manifest_path, &lock_path);
+
+        // Keep an index for the same files that was built in the meantime
+        let mut cache = INDEX_CACHE.lock().unwrap();
+        if let Some(cached) = cache.get(workspace_path)
+            && cached.stamp == stamp
+        {
+            return Ok(cached.index.clone());
+        }
+        let index = Arc::new(Self::from_metadata(&metadata));
+        cache.insert(
+            workspace_path.to_path_buf(),
+            CachedIndex {
+                stamp,
+                lock_path,
+                index: index.clone(),
+            },
+        );
+
+        Ok(index)
+    }
+
+    /// Build an index from `cargo metadata` output (which must include the resolve graph).
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        let packages: FxHashMap<&PackageId, &Package> =
+            metadata.packages.iter().map(|p| (&p.id, p)).collect();
+        let nodes: FxHashMap<&PackageId, &Node> = metadata
+            .resolve
+            .iter()
+            .flat_map(|r| &r.nodes)
+            .map(|n| (&n.id, n))
+            .collect();
+
+        let mut index = Self::default();
+        for member_id in &metadata.workspace_members {
+            let Some(member) = packages.get(member_id) else {
+                continue;
+            };
+            index.members.push(member.name.to_string());
+
+            if let Some(node) = nodes.get(member_id) {
+                let mut visited = HashSet::new();
+                index.add_dependencies(&member.name, node, &packages, &nodes, &mut visited);
+            }
+        }
+
+        index
+    }
+
+    /// Record the dependencies of `node` as dependencies of `member`,
+    /// recursing into battery packs.
+    fn add_dependencies(
+        &mut self,
+        member: &str,
+        node: &Node,
+        packages: &FxHashMap<&PackageId, &Package>,
+        nodes: &FxHashMap<&PackageId, &Node>,
+        visited: &mut HashSet<PackageId>,
+    ) {
+        for dep in &node.deps {
+            let Some(package) = packages.get(&dep.pkg) else {
+                continue;
+            };
+            let dep_node = nodes.get(&dep.pkg);
+
+            let mut kinds: Vec<DependencyKind> = vec![];
+            for info in &dep.dep_kinds {
+                if !kinds.contains(&info.kind) {
+                    kinds.push(info.kind);
+                }
+            }
+
+            self.dependencies
+                .entry(package.name.to_string())
+                .or_default()
+                .push(ResolvedDependency {
+                    member: member.to_string(),
+                    version: package.version.clone(),
+                    kinds,
+                    features: dep_node
+                        .map(|n| n.features.iter().map(|f| f.to_string()).collect())
+                        .unwrap_or_default(),
+                });
+
+            if package.name.ends_with("-battery-pack")
+                && let Some(dep_node) = dep_node
+                && visited.insert(dep.pkg.clone())
+            {
+                self.add_dependencies(member, dep_node, packages, nodes, visited);
+            }
+        }
+    }
+
+    /// Names of the workspace members
+    pub fn members(&self) -> &[String] {
+        &self.members
+    }
+
+    /// Every way the workspace uses the given crate (empty if it doesn't)
+    pub fn dependency(&self, crate_name: &str) -> &[ResolvedDependency] {
+        self.dependencies
+            .get(crate_name)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Check if the workspace depends on a crate.
+    ///
+    /// If the requirement has a version, some resolved version of the crate
+    /// must match it (e.g., `>=1.30`).
+    pub fn is_using_crate(&self, krate: &CrateRequirement) -> bool {
+        let deps = self.dependency(krate.name());
+
+        let Some(version) = krate.version() else {
+            return !deps.is_empty();
+        };
+
+        match VersionReq::parse(version) {
+            Ok(req) => deps.iter().any(|d| req.matches(&d.version)),
+            Err(e) => {
+                tracing::warn!(
+                    crate_name = krate.name(),
+                    error = %e,
+                    "Invalid version requirement in recommendation condition"
+                );
+                false
+            }
+        }
+    }
+}
+
+/// Hash the contents of the manifest and lockfile (missing files hash as empty)
+fn stamp(manifest_path: &Path, lock_path: &Path) -> u64 {
+    let mut contents = std::fs::read(manifest_path).unwrap_or_default();
+    contents.extend(std::fs::read(lock_path).unwrap_or_default());
+    fxhash::hash64(&contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+    use std::io::Write;
+
+    /// Write content to a file and sync to disk to avoid race conditions with cargo metadata
+    fn write_synced(path: &Path, content: &str) {
+        let mut file = std::fs::File::create(path).unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file.sync_all().unwrap();
+    }
+
+    /// Create a library crate at `dir`, with `deps` appended to its manifest
+    fn write_crate(dir: &Path, name: &str, version: &str, deps: &str) {
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        write_synced(
+            &dir.join("Cargo.toml"),
+            &format!(
+                "[package]\nname = \"{name}\"\nversion = \"{version}\"\nedition = \"2021\"\n\n{deps}"
+            ),
+        );
+        write_synced(&dir.join("src/lib.rs"), "");
+    }
+
+    #[test]
+    fn test_index_without_cargo_toml_is_empty() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let index = WorkspaceDependencyIndex::load(temp_dir.path()).unwrap();
+        assert!(index.members().is_empty());
+        assert!(!index.is_using_crate(&"serde".into()));
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "https://github.com/symposium-dev/symposium/issues/112"]
+    fn test_index_resolves_kinds_versions_and_battery_packs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+
+        write_crate(&root.join("helper"), "helper", "0.2.3", "");
+        write_crate(&root.join("codegen"), "codegen", "1.0.0", "");
+        write_crate(
+            &root.join("demo-battery-pack"),
+            "demo-battery-pack",
+            "0.1.0",
+            "[dependencies]\nhelper = { path = \"../helper\" }\n",
+        );
+        write_crate(
+            root,
+            "app",
+            "0.1.0",
+            r#"[dependencies]
+demo-battery-pack = { path = "demo-battery-pack" }
+
+[dev-dependencies]
+helper = { path = "helper" }
+
+[build-dependencies]
+codegen = { path = "codegen" }
+"#,
+        );
+
+        let index = WorkspaceDependencyIndex::load(root).unwrap();
+        assert_eq!(index.members(), ["app"]);
+
+        let codegen = index.dependency("codegen");
+        assert_eq!(codegen.len(), 1);
+        assert_eq!(codegen[0].kinds, [DependencyKind::Build]);
+
+        // `helper` is used both directly (dev) and through the battery pack
+        let helper = index.dependency("helper");
+        assert_eq!(helper.len(), 2);
+        assert!(helper.iter().all(|d| d.member == "app"));
+        assert!(
+            helper
+                .iter()
+                .any(|d| d.kinds == [DependencyKind::Development])
+        );
+        assert!(helper.iter().any(|d| d.kinds == [DependencyKind::Normal]));
+
+        let helper_version = |version: &str| CrateRequirement::Versioned {
+            name: "helper".to_string(),
+            version: Some(version.to_string()),
+        };
+        assert!(index.is_using_crate(&"helper".into()));
+        assert!(index.is_using_crate(&helper_version("^0.2")));
+        assert!(!index.is_using_crate(&helper_version(">=0.3")));
+        assert!(!index.is_using_crate(&"serde".into()));
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "https://github.com/symposium-dev/symposium/issues/112"]
+    fn test_index_invalidated_when_lockfile_changes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+
+        write_crate(&root.join("helper"), "helper", "0.1.0", "");
+        write_crate(root, "app", "0.1.0", "");
+
+        let first = WorkspaceDependencyIndex::load(root).unwrap();
+        assert!(!first.is_using_crate(&"helper".into()));
+
+        // Unchanged workspace reuses the cached index
+        let second = WorkspaceDependencyIndex::load(root).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // Touching the lockfile invalidates it
+        let lock_path = root.join("Cargo.lock");
+        let lock = std::fs::read_to_string(&lock_path).unwrap();
+        write_synced(&lock_path, &format!("{lock}\n"));
+        let third = WorkspaceDependencyIndex::load(root).unwrap();
+        assert!(!Arc::ptr_eq(&second, &third));
+
+        // So does adding a dependency to the manifest
+        write_crate(
+            root,
+            "app",
+            "0.1.0",
+            "[dependencies]\nhelper = { path = \"helper\" }\n",
+        );
+        let fourth = WorkspaceDependencyIndex::load(root).unwrap();
+        assert!(fourth.is_using_crate(&"helper".into()));
+    }
+}
@@ -4,11 +4,18 @@
 //! Language Model Provider backend.
 
 pub mod config_agent;
+pub mod config_commands;
+pub mod dependency_index;
+pub mod mod_overrides;
+pub mod mods_lock;
+pub mod offline;
 pub mod recommendations;
 pub mod registry;
 pub mod remote_recommendations;
 pub mod symposium;
+pub mod trace_tools;
 pub mod user_config;
+pub mod versions;
 pub mod vscodelm;
 
 pub use config_agent::ConfigAgent;
@@ -26,6 +26,24 @@
 //! symposium-acp-agent vscodelm
 //! ```
 //!
+//! ### config
+//! Change the agent and a workspace's mods without the chat-based menu:
+//! ```bash
+//! symposium-acp-agent config list --json
+//! symposium-acp-agent config disable sparkle --workspace ~/src/project
+//! symposium-acp-agent config add cargo:my-mod
+//! symposium-acp-agent config set-agent claude-code --this-workspace
+//! symposium-acp-agent config diff
+//! ```
+//!
+//! ### trace
+//! Inspect and replay the `.jsons` traces written with `--trace-dir`:
+//! ```bash
+//! symposium-acp-agent trace show trace.jsons --session <id>
+//! symposium-acp-agent trace filter trace.jsons --method 'session/*' > prompts.jsons
+//! symposium-acp-agent trace replay trace.jsons
+//! ```
+//!
 //! ## Proxy Configuration
 //!
 //! Use `--proxy <json>` to specify mods. Order matters - proxies are
@@ -35,21 +53,34 @@ use anyhow::Result;
 use clap::{Parser, Subcommand};
 use sacp::{Component, DynComponent, ProxyToConductor};
 use sacp_tokio::AcpAgent;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use symposium_acp_agent::ConfigAgent;
+use symposium_acp_agent::config_commands::{self, ConfigListing};
+use symposium_acp_agent::mods_lock::ModsLock;
+use symposium_acp_agent::offline;
 use symposium_acp_agent::recommendations::RecommendationsExt;
+use symposium_acp_agent::recommendations::{self, RecommendationDiff};
 use symposium_acp_agent::registry;
 use symposium_acp_agent::remote_recommendations;
 use symposium_acp_agent::symposium::{Symposium, SymposiumConfig};
+use symposium_acp_agent::trace_tools::{self, Replay, TraceFilter};
 use symposium_acp_agent::user_config::{ConfigPaths, GlobalAgentConfig, WorkspaceModsConfig};
+use symposium_acp_agent::versions;
 use symposium_acp_agent::vscodelm;
+use symposium_acp_agent::vscodelm::permissions::AllowedTools;
+use symposium_recommendations::ModKind;
 
 #[derive(Parser, Debug)]
 #[command(name = "symposium-acp-agent")]
 #[command(about = "Symposium-enriched ACP agent and proxy")]
 struct Cli {
+    /// Never access the network; use only cached recommendations, registry
+    /// data and binaries. Can also be enabled with `SYMPOSIUM_OFFLINE=1`.
+    #[arg(long, global = true)]
+    offline: bool,
+
     #[command(subcommand)]
     command: Command,
 }
@@ -72,6 +103,51 @@ struct LoggingOptions {
     log_dir: Option<PathBuf>,
 }
 
+/// Options selecting the events of a trace
+#[derive(clap::Args, Debug, Clone)]
+struct TraceFilterOptions {
+    /// Only events of this ACP session
+    #[arg(long)]
+    session: Option<String>,
+
+    /// Only messages with this method; a trailing `*` matches a prefix (e.g. "session/*")
+    #[arg(long)]
+    method: Option<String>,
+
+    /// Only messages from or to this component ("client", "proxy:0", ..., "agent")
+    #[arg(long)]
+    component: Option<String>,
+}
+
+/// The workspace a `config` subcommand works on
+#[derive(clap::Args, Debug, Clone)]
+struct WorkspaceOption {
+    /// Workspace directory (defaults to the current directory)
+    #[arg(long)]
+    workspace: Option<PathBuf>,
+}
+
+impl WorkspaceOption {
+    /// The workspace as an absolute path, which is how sessions refer to it.
+    fn path(self) -> Result<PathBuf> {
+        let path = match self.workspace {
+            Some(path) => path,
+            None => std::env::current_dir()?,
+        };
+        Ok(std::path::absolute(path)?)
+    }
+}
+
+impl TraceFilterOptions {
+    fn into_filter(self) -> TraceFilter {
+        TraceFilter {
+            session: self.session,
+            method: self.method,
+            component: self.component,
+        }
+    }
+}
+
 impl LoggingOptions {
     /// Set up logging based on the options.
     fn setup(&self) {
@@ -140,6 +216,37 @@ enum Command {
         #[arg(long)]
         no_mods: bool,
     },
+
+    /// Update the workspace lockfile (`.symposium/mods.lock`)
+    ///
+    /// Resolves every enabled mod and records its exact version and checksum,
+    /// so everyone working in the workspace runs identical mods.
+    Lock {
+        /// Workspace directory to lock
+        workspace: PathBuf,
+    },
+
+    /// View and change the agent and a workspace's mods
+    ///
+    /// Edits the same configuration as `/symposium:config`. Open sessions keep
+    /// their mods; new sessions use the changes.
+    #[command(subcommand)]
+    Config(ConfigCommand),
+
+    /// Inspect and replay traces written with --trace-dir
+    #[command(subcommand)]
+    Trace(TraceCommand),
+
+    /// Run a command in another directory (used for mods with a `cwd` override)
+    #[command(name = "run-in", hide = true)]
+    RunIn {
+        /// Working directory for the command
+        dir: PathBuf,
+
+        /// The command and its arguments
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
 }
 
 /// Registry subcommands - output JSON for tooling integration
@@ -164,6 +271,165 @@ enum RegistryCommand {
     },
 }
 
+/// Config subcommands
+///
+/// Mods are named by their number in `config list` or by their name.
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Show the agent and the workspace's mods
+    List {
+        #[command(flatten)]
+        workspace: WorkspaceOption,
+
+        /// Print JSON instead of one mod per line
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Enable a mod
+    Enable {
+        /// Number or name of the mod
+        #[arg(value_name = "MOD")]
+        name: String,
+
+        #[command(flatten)]
+        workspace: WorkspaceOption,
+    },
+
+    /// Disable a mod, keeping it in the configuration
+    Disable {
+        /// Number or name of the mod
+        #[arg(value_name = "MOD")]
+        name: String,
+
+        #[command(flatten)]
+        workspace: WorkspaceOption,
+    },
+
+    /// Add a mod at the end of the chain
+    Add {
+        /// Registry ID, `cargo:<crate>`, or a source as JSON (e.g. '{"npx":{"package":"..."}}')
+        source: String,
+
+        /// The mod is an MCP server rather than a proxy
+        #[arg(long)]
+        mcp: bool,
+
+        #[command(flatten)]
+        workspace: WorkspaceOption,
+    },
+
+    /// Remove a mod from the configuration
+    Remove {
+        /// Number or name of the mod
+        #[arg(value_name = "MOD")]
+        name: String,
+
+        #[command(flatten)]
+        workspace: WorkspaceOption,
+    },
+
+    /// Change the agent (for all workspaces without their own agent)
+    SetAgent {
+        /// Agent ID, as for `init`, or a source as JSON
+        agent: String,
+
+        /// Use the agent in the workspace only, instead of the global agent
+        #[arg(long)]
+        this_workspace: bool,
+
+        #[command(flatten)]
+        workspace: WorkspaceOption,
+    },
+
+    /// Remove the workspace's own agent, so it uses the global agent again
+    ResetAgent {
+        #[command(flatten)]
+        workspace: WorkspaceOption,
+    },
+
+    /// Show how the mods differ from the current recommendations and the lockfile
+    Diff {
+        #[command(flatten)]
+        workspace: WorkspaceOption,
+    },
+
+    /// Add newly recommended mods and remove those no longer recommended
+    ApplyRecommendations {
+        #[command(flatten)]
+        workspace: WorkspaceOption,
+    },
+
+    /// List the tools always allowed in VS Code chats
+    AllowedTools {
+        #[command(flatten)]
+        workspace: WorkspaceOption,
+    },
+
+    /// Ask again before running a tool that was always allowed
+    Disallow {
+        /// The tool, as listed by `allowed-tools`
+        tool: String,
+
+        #[command(flatten)]
+        workspace: WorkspaceOption,
+    },
+}
+
+/// Trace subcommands
+#[derive(Subcommand, Debug)]
+enum TraceCommand {
+    /// Print the message flow of a trace, grouped by session
+    Show {
+        /// The `.jsons` trace file
+        file: PathBuf,
+
+        #[command(flatten)]
+        filter: TraceFilterOptions,
+
+        /// Print full params and payloads instead of one-line summaries
+        #[arg(long)]
+        params: bool,
+    },
+
+    /// Print the events of a trace that match a filter, as `.jsons`
+    Filter {
+        /// The `.jsons` trace file
+        file: PathBuf,
+
+        #[command(flatten)]
+        filter: TraceFilterOptions,
+    },
+
+    /// Replay the client side of a trace against an agent chain
+    ///
+    /// Without --agent, the chain comes from your configuration, as with `run`.
+    /// Requests from the agent get the answers recorded in the trace.
+    Replay {
+        /// The `.jsons` trace file
+        file: PathBuf,
+
+        /// Only replay this ACP session
+        #[arg(long)]
+        session: Option<String>,
+
+        /// Create sessions in this directory instead of the recorded one
+        #[arg(long)]
+        cwd: Option<PathBuf>,
+
+        /// Mod proxy to include in the chain, as for `run-with`
+        #[arg(long = "proxy", value_name = "NAME", requires = "agent")]
+        proxies: Vec<String>,
+
+        /// Agent to replay against, as for `run-with`
+        #[arg(long)]
+        agent: Option<String>,
+
+        #[command(flatten)]
+        logging: LoggingOptions,
+    },
+}
+
 /// Build proxy components from the configured sources, preserving order.
 fn build_proxies(raw_proxies: Vec<String>) -> Result<Vec<DynComponent<ProxyToConductor>>> {
     let mut proxies = Vec::with_capacity(raw_proxies.len());
@@ -174,6 +440,185 @@ fn build_proxies(raw_proxies: Vec<String>) -> Result<Vec<DynComponent<ProxyToCon
     Ok(proxies)
 }
 
+/// Load a workspace's mods, which `init` or `/symposium:config` must have created.
+fn load_mods_config(config_paths: &ConfigPaths, workspace: &Path) -> Result<WorkspaceModsConfig> {
+    match WorkspaceModsConfig::load(config_paths, workspace)? {
+        Some(mods_config) => Ok(mods_config),
+        None => anyhow::bail!(
+            "No configuration for {}; run `init` or `/symposium:config` first",
+            workspace.display()
+        ),
+    }
+}
+
+/// Run a `config` subcommand.
+async fn run_config_command(command: ConfigCommand) -> Result<()> {
+    let config_paths = ConfigPaths::default_location()?;
+
+    match command {
+        ConfigCommand::List { workspace, json } => {
+            let workspace = workspace.path()?;
+            let global_agent = GlobalAgentConfig::load(&config_paths)?;
+            let mods_config = WorkspaceModsConfig::load(&config_paths, &workspace)?
+                .unwrap_or_else(|| WorkspaceModsConfig::new(vec![]));
+            let listing = ConfigListing::new(global_agent, &mods_config);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&listing)?);
+            } else {
+                listing.write(&workspace, &mut std::io::stdout().lock())?;
+            }
+        }
+
+        ConfigCommand::Enable { name, workspace } => {
+            set_mod_enabled(&config_paths, &workspace.path()?, &name, true).await?;
+        }
+
+        ConfigCommand::Disable { name, workspace } => {
+            set_mod_enabled(&config_paths, &workspace.path()?, &name, false).await?;
+        }
+
+        ConfigCommand::Add {
+            source,
+            mcp,
+            workspace,
+        } => {
+            let workspace = workspace.path()?;
+            let mut mods_config = load_mods_config(&config_paths, &workspace)?;
+            let source = config_commands::parse_source(&source)?;
+            let name = source.display_name();
+            let kind = if mcp { ModKind::MCP } else { ModKind::Proxy };
+            config_commands::add_mod(&mut mods_config, kind, source)?;
+
+            // Pin the new mod to its current version, as `/symposium:config` does
+            versions::record_mod_versions(&mut mods_config).await;
+            mods_config.save(&config_paths, &workspace).await?;
+            eprintln!("Added {}", name);
+        }
+
+        ConfigCommand::Remove { name, workspace } => {
+            let workspace = workspace.path()?;
+            let mut mods_config = load_mods_config(&config_paths, &workspace)?;
+            let index = config_commands::find_mod(&mods_config, &name)?;
+            let removed = mods_config.mods.remove(index);
+            mods_config.save(&config_paths, &workspace).await?;
+            eprintln!("Removed {}", removed.source.display_name());
+        }
+
+        ConfigCommand::SetAgent {
+            agent,
+            this_workspace,
+            workspace,
+        } => {
+            let source = if agent.starts_with('{') {
+                config_commands::parse_source(&agent)?
+            } else {
+                registry::lookup_agent_source(&agent).await?
+            };
+            let mut agent_config = GlobalAgentConfig::new(source);
+            versions::record_agent_version(&mut agent_config).await;
+            let name = agent_config.agent.display_name();
+            if this_workspace {
+                let workspace = workspace.path()?;
+                let mut mods_config = load_mods_config(&config_paths, &workspace)?;
+                mods_config.agent = Some(Box::new(agent_config));
+                mods_config.save(&config_paths, &workspace).await?;
+                eprintln!("Agent for {} set to {}", workspace.display(), name);
+            } else {
+                agent_config.save(&config_paths).await?;
+                eprintln!("Agent set to {}", name);
+            }
+        }
+
+        ConfigCommand::ResetAgent { workspace } => {
+            let workspace = workspace.path()?;
+            let mut mods_config = load_mods_config(&config_paths, &workspace)?;
+            if mods_config.agent.take().is_none() {
+                anyhow::bail!("{} doesn't have its own agent", workspace.display());
+            }
+            mods_config.save(&config_paths, &workspace).await?;
+            eprintln!("{} now uses the global agent", workspace.display());
+        }
+
+        ConfigCommand::Diff { workspace } => {
+            let workspace = workspace.path()?;
+            let mods_config = load_mods_config(&config_paths, &workspace)?;
+            let recommendations =
+                remote_recommendations::load_recommendations(&config_paths).await?;
+            let diff =
+                recommendations::workspace_diff(Some(&recommendations), &workspace, &mods_config);
+            config_commands::write_diff(&diff, &mut std::io::stdout().lock())?;
+        }
+
+        ConfigCommand::ApplyRecommendations { workspace } => {
+            let workspace = workspace.path()?;
+            let mut mods_config = load_mods_config(&config_paths, &workspace)?;
+            let recommendations =
+                remote_recommendations::load_recommendations(&config_paths).await?;
+            let diff =
+                recommendations::workspace_diff(Some(&recommendations), &workspace, &mods_config);
+
+            let changes = RecommendationDiff {
+                lock_mismatches: vec![],
+                ..diff.clone()
+            };
+            if changes.is_empty() {
+                eprintln!("The mods already match the recommendations.");
+            } else {
+                changes.apply(&mut mods_config);
+                versions::record_mod_versions(&mut mods_config).await;
+                mods_config.save(&config_paths, &workspace).await?;
+                eprintln!("Applied recommendations ({})", changes.summary());
+            }
+
+            if !diff.lock_mismatches.is_empty() {
+                eprintln!(
+                    "{} no longer matches the configuration; run `symposium-acp-agent lock {}` to update it.",
+                    ModsLock::path(&workspace).display(),
+                    workspace.display()
+                );
+            }
+        }
+
+        ConfigCommand::AllowedTools { workspace } => {
+            let allowed_tools = AllowedTools::new(config_paths, workspace.path()?);
+            let tools = allowed_tools.list()?;
+            if tools.is_empty() {
+                eprintln!("No tools are always allowed in this workspace.");
+            }
+            for tool in tools {
+                println!("{}", tool);
+            }
+        }
+
+        ConfigCommand::Disallow { tool, workspace } => {
+            let allowed_tools = AllowedTools::new(config_paths, workspace.path()?);
+            if !allowed_tools.remove(&tool).await? {
+                anyhow::bail!("{} isn't always allowed in this workspace", tool);
+            }
+            eprintln!("Removed {}; it will be asked about again", tool);
+        }
+    }
+
+    Ok(())
+}
+
+/// Enable or disable a workspace's mod.
+async fn set_mod_enabled(
+    config_paths: &ConfigPaths,
+    workspace: &Path,
+    name: &str,
+    enabled: bool,
+) -> Result<()> {
+    let mut mods_config = load_mods_config(config_paths, workspace)?;
+    let index = config_commands::find_mod(&mods_config, name)?;
+    let m = &mut mods_config.mods[index];
+    m.enabled = enabled;
+    let name = m.source.display_name();
+    mods_config.save(config_paths, workspace).await?;
+    eprintln!("{} {}", if enabled { "Enabled" } else { "Disabled" }, name);
+    Ok(())
+}
+
 /// Set up logging if requested.
 fn setup_logging(log: Option<String>, log_dir: Option<PathBuf>) {
     if let Some(filter) = &log {
@@ -218,6 +663,10 @@ fn setup_logging(log: Option<String>, log_dir: Option<PathBuf>) {
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    if cli.offline {
+        offline::enable();
+    }
+
     match cli.command {
         Command::RunWith {
             proxies,
@@ -315,16 +764,120 @@ async fn main() -> Result<()> {
                 recommendations.for_workspace(&workspace).mods
             };
 
-            // Save workspace mods
-            let mods_config = WorkspaceModsConfig::from_recommendations(recs);
+            // Save workspace mods, pinned to their current versions
+            let mut mods_config = WorkspaceModsConfig::from_recommendations(recs);
+            versions::record_mod_versions(&mut mods_config).await;
             mods_config.save(&config_paths, &workspace).await?;
 
             // Save global agent
-            let global_config = GlobalAgentConfig::new(agent);
+            let mut global_config = GlobalAgentConfig::new(agent);
+            versions::record_agent_version(&mut global_config).await;
             global_config.save(&config_paths).await?;
 
             eprintln!("Initialized config for {}", workspace.display());
         }
+
+        Command::Lock { workspace } => {
+            // Workspace configs are keyed by absolute path
+            let workspace = std::path::absolute(workspace)?;
+            let config_paths = ConfigPaths::default_location()?;
+            let mods_config = load_mods_config(&config_paths, &workspace)?;
+
+            let previous = ModsLock::load(&workspace)?;
+            let lock = ModsLock::lock(&mods_config.mods, previous.as_ref()).await?;
+            lock.save(&workspace).await?;
+
+            for locked in &lock.mods {
+                match &locked.version {
+                    Some(version) => {
+                        eprintln!("Locked {} {}", locked.source.display_name(), version)
+                    }
+                    None => eprintln!("Locked {}", locked.source.display_name()),
+                }
+            }
+            eprintln!("Wrote {}", ModsLock::path(&workspace).display());
+        }
+
+        Command::Config(config_cmd) => run_config_command(config_cmd).await?,
+
+        Command::Trace(trace_cmd) => match trace_cmd {
+            TraceCommand::Show {
+                file,
+                filter,
+                params,
+            } => {
+                let filter = filter.into_filter();
+                let events: Vec<_> = trace_tools::resolve(trace_tools::read_trace(&file)?)
+                    .into_iter()
+                    .filter(|event| filter.matches(event))
+                    .collect();
+                trace_tools::show(&events, params, &mut std::io::stdout().lock())?;
+            }
+            TraceCommand::Filter { file, filter } => {
+                let filter = filter.into_filter();
+                let events = trace_tools::resolve(trace_tools::read_trace(&file)?);
+                trace_tools::write_trace(
+                    events.iter().filter(|event| filter.matches(event)),
+                    &mut std::io::stdout().lock(),
+                )?;
+            }
+            TraceCommand::Replay {
+                file,
+                session,
+                cwd,
+                proxies,
+                agent,
+                logging,
+            } => {
+                logging.setup();
+
+                let filter = TraceFilter {
+                    session,
+                    ..Default::default()
+                };
+                let events: Vec<_> = trace_tools::resolve(trace_tools::read_trace(&file)?)
+                    .into_iter()
+                    .filter(|event| filter.matches(event))
+                    .collect();
+                let replay = Replay::new(&events)?.with_cwd(cwd);
+                let mut stdout = std::io::stdout();
+
+                if let Some(agent_spec) = agent {
+                    let mut config = SymposiumConfig::new();
+                    if let Some(trace_dir) = logging.trace_dir {
+                        config = config.trace_dir(trace_dir);
+                    }
+                    let agent: AcpAgent = agent_spec.parse()?;
+                    let chain = Symposium::new(config, build_proxies(proxies)?).with_agent(agent);
+                    replay.run(chain, &mut stdout).await?;
+                } else {
+                    let mut agent = ConfigAgent::new().await?;
+                    if let Some(dir) = logging.trace_dir {
+                        agent = agent.with_trace_dir(dir);
+                    }
+                    replay.run(agent, &mut stdout).await?;
+                }
+            }
+        },
+
+        Command::RunIn { dir, command } => {
+            let mut cmd = std::process::Command::new(&command[0]);
+            cmd.args(&command[1..]).current_dir(&dir);
+
+            // Replace this process so signals and stdio go straight to the command
+            #[cfg(unix)]
+            {
+                use std::os::unix::process::CommandExt;
+                let error = cmd.exec();
+                anyhow::bail!("Failed to run {}: {}", command[0], error);
+            }
+
+            #[cfg(not(unix))]
+            {
+                let status = cmd.status()?;
+                std::process::exit(status.code().unwrap_or(1));
+            }
+        }
     }
 
     Ok(())
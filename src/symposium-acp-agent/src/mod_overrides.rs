@@ -0,0 +1,213 @@
+//! Per-mod overrides of how a mod's process is launched.
+//!
+//! A [`ModConfig`](crate::user_config::ModConfig) can add arguments,
+//! environment variables and a working directory on top of whatever its source
+//! resolves to, so a recommended mod can be customized without rewriting it as
+//! a `local` source.
+//!
+//! Values of `env` and `args` may reference environment variables of the
+//! Symposium process as `${NAME}`. References are expanded each time the mod
+//! starts, so secrets such as API keys don't have to be written to the
+//! configuration file.
+
+use anyhow::{Context, Result, bail};
+use sacp::schema::{EnvVariable, McpServer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Hidden subcommand that runs a command in another directory.
+///
+/// ACP has no way to set the working directory of a stdio server, so mods
+/// with a `cwd` override run as `symposium-acp-agent run-in <cwd> -- <command>`.
+pub const RUN_IN_SUBCOMMAND: &str = "run-in";
+
+/// Overrides applied to a mod's resolved server.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ModOverrides {
+    /// Arguments appended to the mod's own arguments
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+
+    /// Environment variables to set, replacing those of the source
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+
+    /// Working directory of the mod's process
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+}
+
+impl ModOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty() && self.env.is_empty() && self.cwd.is_none()
+    }
+
+    /// Apply the overrides to a resolved server, expanding `${NAME}` references
+    /// from the environment of this process.
+    pub fn apply(&self, server: McpServer) -> Result<McpServer> {
+        self.apply_with(server, |name| std::env::var(name).ok())
+    }
+
+    /// Like [`Self::apply`], looking up references with `lookup`.
+    fn apply_with(
+        &self,
+        server: McpServer,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<McpServer> {
+        if self.is_empty() {
+            return Ok(server);
+        }
+
+        let McpServer::Stdio(mut stdio) = server else {
+            bail!("args, env and cwd can only be overridden for mods that run a command");
+        };
+
+        for arg in &self.args {
+            let arg =
+                expand_env_refs(arg, &lookup).with_context(|| format!("in argument `{}`", arg))?;
+            stdio.args.push(arg);
+        }
+
+        for (name, value) in &self.env {
+            let value = expand_env_refs(value, &lookup)
+                .with_context(|| format!("in environment variable `{}`", name))?;
+            stdio.env.retain(|var| var.name != *name);
+            stdio.env.push(EnvVariable::new(name, value));
+        }
+
+        if let Some(cwd) = &self.cwd {
+            let exe = std::env::current_exe().context("failed to find the Symposium executable")?;
+            let mut args = vec![
+                RUN_IN_SUBCOMMAND.to_string(),
+                cwd.to_string_lossy().to_string(),
+                "--".to_string(),
+                stdio.command.to_string_lossy().to_string(),
+            ];
+            args.append(&mut stdio.args);
+            stdio.command = exe;
+            stdio.args = args;
+        }
+
+        Ok(McpServer::Stdio(stdio))
+    }
+}
+
+/// Whether a value reads something from the environment rather than storing it.
+pub fn has_env_refs(value: &str) -> bool {
+    value.contains("${")
+}
+
+/// Replace each `${NAME}` in `value` with the variable `NAME`.
+fn expand_env_refs(value: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let Some(len) = rest[start + 2..].find('}') else {
+            bail!("unterminated `${{` in `{}`", value);
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        match lookup(name) {
+            Some(var) => expanded.push_str(&var),
+            None => bail!("environment variable `{}` is not set", name),
+        }
+        rest = &rest[start + 3 + len..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sacp::schema::McpServerStdio;
+
+    fn lookup(name: &str) -> Option<String> {
+        (name == "GITHUB_TOKEN").then(|| "secret".to_string())
+    }
+
+    fn server() -> McpServer {
+        McpServer::Stdio(
+            McpServerStdio::new("github-mcp", "/bin/github-mcp")
+                .args(vec!["--acp".to_string()])
+                .env(vec![EnvVariable::new("LOG", "info")]),
+        )
+    }
+
+    #[test]
+    fn test_expand_env_refs() {
+        assert_eq!(
+            expand_env_refs("Bearer ${GITHUB_TOKEN}", lookup).unwrap(),
+            "Bearer secret"
+        );
+        assert_eq!(
+            expand_env_refs("plain $HOME", lookup).unwrap(),
+            "plain $HOME"
+        );
+        assert_eq!(
+            expand_env_refs("${MISSING}", lookup)
+                .unwrap_err()
+                .to_string(),
+            "environment variable `MISSING` is not set"
+        );
+        assert!(expand_env_refs("${GITHUB_TOKEN", lookup).is_err());
+    }
+
+    #[test]
+    fn test_apply_args_and_env() {
+        let overrides = ModOverrides {
+            args: vec!["--verbose".to_string()],
+            env: BTreeMap::from([
+                ("GITHUB_TOKEN".to_string(), "${GITHUB_TOKEN}".to_string()),
+                ("LOG".to_string(), "debug".to_string()),
+            ]),
+            cwd: None,
+        };
+        let McpServer::Stdio(stdio) = overrides.apply_with(server(), lookup).unwrap() else {
+            panic!("expected a stdio server");
+        };
+        assert_eq!(stdio.command, PathBuf::from("/bin/github-mcp"));
+        assert_eq!(stdio.args, ["--acp", "--verbose"]);
+        let env: Vec<_> = stdio
+            .env
+            .iter()
+            .map(|var| format!("{}={}", var.name, var.value))
+            .collect();
+        assert_eq!(env, ["GITHUB_TOKEN=secret", "LOG=debug"]);
+    }
+
+    #[test]
+    fn test_apply_cwd() {
+        let overrides = ModOverrides {
+            cwd: Some(PathBuf::from("/work")),
+            ..Default::default()
+        };
+        let McpServer::Stdio(stdio) = overrides.apply_with(server(), lookup).unwrap() else {
+            panic!("expected a stdio server");
+        };
+        assert_eq!(stdio.command, std::env::current_exe().unwrap());
+        assert_eq!(
+            stdio.args,
+            [RUN_IN_SUBCOMMAND, "/work", "--", "/bin/github-mcp", "--acp"]
+        );
+    }
+
+    #[test]
+    fn test_apply_to_http_server() {
+        let http = McpServer::Http(sacp::schema::McpServerHttp::new(
+            "db",
+            "https://example.com/mcp",
+        ));
+        assert_eq!(
+            ModOverrides::default().apply(http.clone()).unwrap(),
+            http.clone()
+        );
+
+        let overrides = ModOverrides {
+            args: vec!["--verbose".to_string()],
+            ..Default::default()
+        };
+        assert!(overrides.apply(http).is_err());
+    }
+}
@@ -0,0 +1,390 @@
+//! Workspace mods lockfile - `.symposium/mods.lock`
+//!
+//! Checked into a repository next to `.symposium/recommendations.toml`, the
+//! lockfile makes every developer run identical mods. For each enabled mod it
+//! records the exact version it resolved to and, for binary distributions, the
+//! SHA-256 of the downloaded archive per platform.
+//!
+//! Conductors resolve mods through the lock when there is one (see
+//! [`resolve_mod`]); the lock is written by `symposium-acp-agent lock`.
+
+use crate::registry::{self, ComponentSourceExt};
+use crate::remote_recommendations::WORKSPACE_SYMPOSIUM_DIR;
+use crate::user_config::ModConfig;
+use crate::versions;
+use anyhow::{Context, Result, bail};
+use sacp::schema::McpServer;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use symposium_recommendations::ComponentSource;
+
+/// Filename of the lockfile in the workspace's `.symposium` directory.
+const MODS_LOCK_FILENAME: &str = "mods.lock";
+
+/// Written at the top of the lockfile
+const MODS_LOCK_HEADER: &str =
+    "# This file is generated by `symposium-acp-agent lock`. Do not edit it by hand.\n\n";
+
+/// The locked mods of a workspace
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModsLock {
+    #[serde(default, rename = "mod")]
+    pub mods: Vec<LockedMod>,
+}
+
+/// A mod as it was resolved when the lock was written
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedMod {
+    /// The mod's source as configured - matches [`ModConfig::source`]
+    pub source: ComponentSource,
+
+    /// Exact version the mod resolved to (`None` for sources without a version)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    /// The source with the version applied (e.g. `package@1.2.3` for npx)
+    pub resolved: ComponentSource,
+
+    /// SHA-256 of the downloaded archive, by platform (see [`registry::get_platform_key`]).
+    /// Empty unless the mod is a binary distribution.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub checksums: BTreeMap<String, String>,
+}
+
+/// A difference between the workspace configuration and its lockfile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LockMismatch {
+    /// An enabled mod isn't in the lock
+    Unlocked { name: String },
+    /// A locked mod isn't enabled in the configuration
+    NotEnabled { name: String },
+    /// The configuration pins a different version than the lock
+    Version {
+        name: String,
+        configured: String,
+        locked: String,
+    },
+}
+
+impl fmt::Display for LockMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockMismatch::Unlocked { name } => write!(f, "`{name}` is enabled but not locked"),
+            LockMismatch::NotEnabled { name } => write!(f, "`{name}` is locked but not enabled"),
+            LockMismatch::Version {
+                name,
+                configured,
+                locked,
+            } => write!(
+                f,
+                "`{name}` is pinned to {configured} but locked to {locked}"
+            ),
+        }
+    }
+}
+
+impl ModsLock {
+    /// Path of the lockfile for a workspace
+    pub fn path(workspace_path: &Path) -> PathBuf {
+        workspace_path
+            .join(WORKSPACE_SYMPOSIUM_DIR)
+            .join(MODS_LOCK_FILENAME)
+    }
+
+    /// Load the lockfile of a workspace.
+    /// Returns None if the workspace has no lockfile.
+    pub fn load(workspace_path: &Path) -> Result<Option<Self>> {
+        let path = Self::path(workspace_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read mods lock from {}", path.display()))?;
+        let lock = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse mods lock from {}", path.display()))?;
+        Ok(Some(lock))
+    }
+
+    /// Write the lockfile of a workspace, creating `.symposium` if needed.
+    pub async fn save(&self, workspace_path: &Path) -> Result<()> {
+        let path = Self::path(workspace_path);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let content = format!("{MODS_LOCK_HEADER}{}", toml::to_string_pretty(self)?);
+        tokio::fs::write(&path, content)
+            .await
+            .with_context(|| format!("Failed to write mods lock to {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Find the locked entry for a configured source
+    pub fn get(&self, source: &ComponentSource) -> Option<&LockedMod> {
+        self.mods.iter().find(|m| &m.source == source)
+    }
+
+    /// Lock the enabled mods, resolving (and downloading) each one.
+    ///
+    /// Mods pinned in the configuration are locked at their pin, the others at
+    /// their newest version. Checksums that `previous` recorded for other
+    /// platforms are kept as long as the mod's version is unchanged.
+    pub async fn lock(mods: &[ModConfig], previous: Option<&ModsLock>) -> Result<Self> {
+        let mut locked = vec![];
+        for m in mods.iter().filter(|m| m.enabled) {
+            let name = m.source.display_name();
+            tracing::debug!(mod_name = %name, "Locking mod");
+
+            let version = match &m.version {
+                Some(version) => Some(version.clone()),
+                None => versions::latest_version(&m.source)
+                    .await
+                    .with_context(|| format!("Failed to determine the version of `{name}`"))?,
+            };
+            let resolved = version
+                .as_deref()
+                .and_then(|version| versions::pin_source(&m.source, version))
+                .unwrap_or_else(|| m.source.clone());
+
+            let server = resolved
+                .resolve_version(version.as_deref())
+                .await
+                .with_context(|| format!("Failed to resolve `{name}`"))?;
+
+            let mut checksums = previous
+                .and_then(|lock| lock.get(&m.source))
+                .filter(|prev| prev.version == version)
+                .map(|prev| prev.checksums.clone())
+                .unwrap_or_default();
+            match archive_sha256(&server) {
+                Some(sha256) => {
+                    checksums.insert(registry::get_platform_key(), sha256);
+                }
+                None => checksums.clear(),
+            }
+
+            locked.push(LockedMod {
+                source: m.source.clone(),
+                version,
+                resolved,
+                checksums,
+            });
+        }
+
+        Ok(Self { mods: locked })
+    }
+
+    /// Compare the lock against the workspace configuration.
+    pub fn mismatches(&self, mods: &[ModConfig]) -> Vec<LockMismatch> {
+        let mut mismatches = vec![];
+
+        for m in mods.iter().filter(|m| m.enabled) {
+            let name = m.source.display_name();
+            match self.get(&m.source) {
+                None => mismatches.push(LockMismatch::Unlocked { name }),
+                Some(locked) => {
+                    if let Some(configured) = &m.version
+                        && let Some(locked) = &locked.version
+                        && configured != locked
+                    {
+                        mismatches.push(LockMismatch::Version {
+                            name,
+                            configured: configured.clone(),
+                            locked: locked.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        for locked in &self.mods {
+            if !mods.iter().any(|m| m.enabled && m.source == locked.source) {
+                mismatches.push(LockMismatch::NotEnabled {
+                    name: locked.source.display_name(),
+                });
+            }
+        }
+
+        mismatches
+    }
+}
+
+impl LockedMod {
+    /// Resolve the locked source, verifying the archive checksum for this platform.
+    pub async fn resolve(&self) -> Result<McpServer> {
+        let server = self
+            .resolved
+            .resolve_version(self.version.as_deref())
+            .await?;
+
+        if let Some(expected) = self.checksums.get(&registry::get_platform_key()) {
+            match archive_sha256(&server) {
+                Some(actual) if actual.eq_ignore_ascii_case(expected) => {}
+                Some(actual) => bail!(
+                    "`{}` does not match the lockfile: expected sha256 {}, got {}",
+                    self.source.display_name(),
+                    expected,
+                    actual
+                ),
+                None => bail!(
+                    "`{}` is locked to a binary distribution, but did not resolve to one",
+                    self.source.display_name()
+                ),
+            }
+        }
+
+        Ok(server)
+    }
+}
+
+/// Resolve a mod, preferring its locked version if the lock has it.
+pub async fn resolve_mod(lock: Option<&ModsLock>, m: &ModConfig) -> Result<McpServer> {
+    match lock.and_then(|lock| lock.get(&m.source)) {
+        Some(locked) => locked.resolve().await,
+        None => m.source.resolve_version(m.version.as_deref()).await,
+    }
+}
+
+/// Archive checksum of a server spawned from a cached binary distribution
+fn archive_sha256(server: &McpServer) -> Option<String> {
+    match server {
+        McpServer::Stdio(stdio) => registry::cached_archive_sha256(&stdio.command),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use symposium_recommendations::{LocalDistribution, ModKind, NpxDistribution, When};
+
+    fn mod_config(source: ComponentSource, version: Option<&str>) -> ModConfig {
+        ModConfig {
+            kind: ModKind::Proxy,
+            source,
+            enabled: true,
+            when: When::default(),
+            order: 0,
+            version: version.map(str::to_string),
+            policy: Default::default(),
+            overrides: Default::default(),
+        }
+    }
+
+    fn npx(package: &str) -> ComponentSource {
+        ComponentSource::Npx(NpxDistribution {
+            package: package.to_string(),
+            args: vec![],
+            env: Default::default(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_lock_roundtrip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let lock = ModsLock {
+            mods: vec![
+                LockedMod {
+                    source: npx("some-mod"),
+                    version: Some("1.2.3".to_string()),
+                    resolved: npx("some-mod@1.2.3"),
+                    checksums: BTreeMap::new(),
+                },
+                LockedMod {
+                    source: ComponentSource::Registry("acme:binary-mod".to_string()),
+                    version: Some("0.4.0".to_string()),
+                    resolved: ComponentSource::Registry("acme:binary-mod".to_string()),
+                    checksums: [("linux-x86_64".to_string(), "abc123".to_string())].into(),
+                },
+            ],
+        };
+
+        assert!(ModsLock::load(temp_dir.path()).unwrap().is_none());
+        lock.save(temp_dir.path()).await.unwrap();
+
+        let content = std::fs::read_to_string(ModsLock::path(temp_dir.path())).unwrap();
+        assert!(content.starts_with(MODS_LOCK_HEADER));
+        assert_eq!(ModsLock::load(temp_dir.path()).unwrap(), Some(lock));
+    }
+
+    #[test]
+    fn test_lock_mismatches() {
+        let lock = ModsLock {
+            mods: vec![
+                LockedMod {
+                    source: npx("pinned"),
+                    version: Some("1.0.0".to_string()),
+                    resolved: npx("pinned@1.0.0"),
+                    checksums: BTreeMap::new(),
+                },
+                LockedMod {
+                    source: npx("removed"),
+                    version: Some("1.0.0".to_string()),
+                    resolved: npx("removed@1.0.0"),
+                    checksums: BTreeMap::new(),
+                },
+            ],
+        };
+
+        let mods = vec![
+            mod_config(npx("pinned"), Some("2.0.0")),
+            mod_config(npx("new"), None),
+        ];
+
+        expect_test::expect![[r#"
+            [
+                "`pinned` is pinned to 2.0.0 but locked to 1.0.0",
+                "`new` is enabled but not locked",
+                "`removed` is locked but not enabled",
+            ]
+        "#]]
+        .assert_debug_eq(
+            &lock
+                .mismatches(&mods)
+                .iter()
+                .map(|m| m.to_string())
+                .collect::<Vec<_>>(),
+        );
+
+        // Unpinned mods follow the lock
+        let mods = vec![mod_config(npx("pinned"), None)];
+        let lock = ModsLock {
+            mods: lock.mods[..1].to_vec(),
+        };
+        assert!(lock.mismatches(&mods).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_locked_checksum_is_verified() {
+        // A cached binary distribution, as left behind by a download
+        let temp_dir = tempfile::tempdir().unwrap();
+        let executable = temp_dir.path().join("bin").join("my-mod");
+        std::fs::create_dir_all(executable.parent().unwrap()).unwrap();
+        std::fs::write(&executable, "#!/bin/sh").unwrap();
+        std::fs::write(
+            temp_dir.path().join(registry::CHECKSUM_FILE),
+            r#"{"archive": "abc123", "executable": "def456"}"#,
+        )
+        .unwrap();
+
+        let source = ComponentSource::Local(LocalDistribution {
+            name: Some("my-mod".to_string()),
+            command: executable.to_string_lossy().to_string(),
+            args: vec![],
+            env: Default::default(),
+        });
+        let locked = |sha256: &str| LockedMod {
+            source: source.clone(),
+            version: None,
+            resolved: source.clone(),
+            checksums: [(registry::get_platform_key(), sha256.to_string())].into(),
+        };
+
+        assert!(locked("abc123").resolve().await.is_ok());
+        let err = locked("000000").resolve().await.unwrap_err();
+        assert!(err.to_string().contains("does not match the lockfile"));
+    }
+}
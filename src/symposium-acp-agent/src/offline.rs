@@ -0,0 +1,97 @@
+//! Offline mode - never touch the network, only use what is already cached.
+//!
+//! Enabled with the `--offline` flag or by setting `SYMPOSIUM_OFFLINE=1`. The
+//! flag is forwarded to the components we spawn through the same environment
+//! variable, so mods (e.g. `symposium-ferris`) honor it too.
+//!
+//! In offline mode:
+//! - remote recommendations come from the cache in `<config_dir>/cache`
+//! - cargo and binary distributions come from `<config_dir>/bin` (cargo
+//!   installs use `cargo install --offline`, i.e. the cargo registry cache)
+//! - agent registries come from the registry cache in `<config_dir>/cache`;
+//!   built-in agents and registries read from a local path still work
+
+use anyhow::anyhow;
+use sacp::schema::{EnvVariable, McpServer};
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub use symposium_recommendations::offline::SYMPOSIUM_OFFLINE_ENV;
+
+/// Set by [`enable`]
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Check whether offline mode is enabled.
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed) || symposium_recommendations::offline::offline_from_env()
+}
+
+/// Enable offline mode for this process and any components it spawns
+/// (see [`forward_to`]).
+pub fn enable() {
+    OFFLINE.store(true, Ordering::Relaxed);
+}
+
+/// Pass offline mode on to a component that is about to be spawned.
+pub fn forward_to(server: &mut McpServer) {
+    if is_offline() {
+        set_offline_env(server);
+    }
+}
+
+fn set_offline_env(server: &mut McpServer) {
+    if let McpServer::Stdio(stdio) = server
+        && !stdio
+            .env
+            .iter()
+            .any(|var| var.name == SYMPOSIUM_OFFLINE_ENV)
+    {
+        stdio.env.push(EnvVariable::new(SYMPOSIUM_OFFLINE_ENV, "1"));
+    }
+}
+
+/// The error for something that would need the network while offline.
+///
+/// `what` names the missing artifact, e.g. "the binary for `sparkle-mcp`".
+pub fn not_cached(what: impl Display) -> anyhow::Error {
+    anyhow!(
+        "Offline mode is enabled (--offline or {SYMPOSIUM_OFFLINE_ENV}) and {what} is not cached. \
+         Run once with network access to populate the cache."
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_not_cached_message() {
+        let err = not_cached("the binary for `sparkle-mcp`");
+        expect_test::expect![[r#"
+            "Offline mode is enabled (--offline or SYMPOSIUM_OFFLINE) and the binary for `sparkle-mcp` is not cached. Run once with network access to populate the cache."
+        "#]]
+        .assert_debug_eq(&err.to_string());
+    }
+
+    #[test]
+    fn test_set_offline_env() {
+        let mut server = McpServer::Stdio(sacp::schema::McpServerStdio::new("ferris", "ferris"));
+        set_offline_env(&mut server);
+        set_offline_env(&mut server);
+        let McpServer::Stdio(stdio) = &server else {
+            unreachable!()
+        };
+        assert_eq!(
+            stdio.env,
+            vec![EnvVariable::new(SYMPOSIUM_OFFLINE_ENV, "1")]
+        );
+
+        // Only spawned components have an environment
+        let mut server = McpServer::Http(sacp::schema::McpServerHttp::new(
+            "db",
+            "https://example.com/mcp",
+        ));
+        set_offline_env(&mut server);
+        assert!(matches!(server, McpServer::Http(_)));
+    }
+}
@@ -4,14 +4,21 @@
 //! characteristics. Recommendations are loaded from a built-in TOML file that
 //! is embedded in the binary.
 
+use crate::dependency_index::WorkspaceDependencyIndex;
+use crate::mods_lock::{LockMismatch, ModsLock};
 use crate::user_config::{ModConfig, WorkspaceModsConfig};
 use anyhow::Result;
-use cargo_metadata::{Metadata, MetadataCommand, Package, PackageId};
+use globset::GlobBuilder;
+use ignore::WalkBuilder;
+use regex::Regex;
 use std::collections::HashSet;
 use std::path::Path;
+use std::sync::Arc;
 
 // Re-export types from symposium-recommendations
-pub use symposium_recommendations::{Recommendation, Recommendations, When};
+pub use symposium_recommendations::{
+    CrateRequirement, FileContains, Recommendation, Recommendations, When,
+};
 
 /// Built-in recommendations TOML, embedded at compile time
 const BUILTIN_RECOMMENDATIONS_TOML: &str = include_str!("builtin_recommendations.toml");
@@ -73,15 +80,42 @@ impl RecommendationExt for Recommendation {
 pub trait WhenExt {
     /// Check if this condition is met for the given workspace.
     fn is_met(&self, workspace_path: &Path) -> bool;
+
+    /// Check if this condition is met, answering crate conditions from
+    /// `dependencies` (if `None`, crate conditions are never met).
+    fn is_met_with(
+        &self,
+        workspace_path: &Path,
+        dependencies: Option<&WorkspaceDependencyIndex>,
+    ) -> bool;
+
+    /// True if this condition (or a nested one) depends on the workspace's crates
+    fn depends_on_crates(&self) -> bool;
 }
 
 impl WhenExt for When {
     fn is_met(&self, workspace_path: &Path) -> bool {
+        let dependencies = if self.depends_on_crates() {
+            load_dependency_index(workspace_path)
+        } else {
+            None
+        };
+        self.is_met_with(workspace_path, dependencies.as_deref())
+    }
+
+    fn is_met_with(
+        &self,
+        workspace_path: &Path,
+        dependencies: Option<&WorkspaceDependencyIndex>,
+    ) -> bool {
+        let is_using_crate =
+            |krate: &CrateRequirement| dependencies.is_some_and(|d| d.is_using_crate(krate));
+
         // file-exists
-        if let Some(path) = &self.file_exists {
-            if !workspace_path.join(path).exists() {
-                return false;
-            }
+        if let Some(path) = &self.file_exists
+            && !workspace_path.join(path).exists()
+        {
+            return false;
         }
 
         // files-exist (all must exist)
@@ -93,103 +127,139 @@ impl WhenExt for When {
             }
         }
 
+        // glob (at least one file must match)
+        if let Some(pattern) = &self.glob
+            && !glob_matches(workspace_path, pattern)
+        {
+            return false;
+        }
+
+        // file-contains
+        if let Some(file_contains) = &self.file_contains
+            && !file_contains_matches(workspace_path, file_contains)
+        {
+            return false;
+        }
+
         // using-crate
-        if let Some(crate_name) = &self.using_crate {
-            if !is_using_crate(workspace_path, crate_name) {
-                return false;
-            }
+        if let Some(krate) = &self.using_crate
+            && !is_using_crate(krate)
+        {
+            return false;
         }
 
         // using-crates (all must be dependencies)
-        if let Some(crate_names) = &self.using_crates {
-            for crate_name in crate_names {
-                if !is_using_crate(workspace_path, crate_name) {
+        if let Some(krates) = &self.using_crates {
+            for krate in krates {
+                if !is_using_crate(krate) {
                     return false;
                 }
             }
         }
 
+        // env-var-set
+        if let Some(name) = &self.env_var_set
+            && std::env::var_os(name).is_none()
+        {
+            return false;
+        }
+
+        // not
+        if let Some(condition) = &self.not
+            && condition.is_met_with(workspace_path, dependencies)
+        {
+            return false;
+        }
+
         // any (OR - at least one must match)
-        if let Some(conditions) = &self.any {
-            if !conditions.iter().any(|c| c.is_met(workspace_path)) {
-                return false;
-            }
+        if let Some(conditions) = &self.any
+            && !conditions
+                .iter()
+                .any(|c| c.is_met_with(workspace_path, dependencies))
+        {
+            return false;
         }
 
         // all (explicit AND - all must match)
-        if let Some(conditions) = &self.all {
-            if !conditions.iter().all(|c| c.is_met(workspace_path)) {
-                return false;
-            }
+        if let Some(conditions) = &self.all
+            && !conditions
+                .iter()
+                .all(|c| c.is_met_with(workspace_path, dependencies))
+        {
+            return false;
         }
 
         true
     }
-}
-
-/// Check if a crate is a direct dependency of the workspace.
-///
-/// Battery packs (crates ending in `-battery-pack`) are "transparent" - we also
-/// check their dependencies recursively. This means if your workspace depends on
-/// `cli-battery-pack` which depends on `clap`, then `using-crate = "clap"` will match.
-fn is_using_crate(workspace_path: &Path, crate_name: &str) -> bool {
-    let metadata = match MetadataCommand::new()
-        .current_dir(workspace_path)
-        .no_deps() // We only need workspace members initially
-        .exec()
-    {
-        Ok(m) => m,
-        Err(_) => return false,
-    };
-
-    // We need the full metadata to resolve battery pack dependencies
-    let full_metadata = match MetadataCommand::new().current_dir(workspace_path).exec() {
-        Ok(m) => m,
-        Err(_) => return false,
-    };
 
-    let mut visited = HashSet::new();
+    fn depends_on_crates(&self) -> bool {
+        self.using_crate.is_some()
+            || self.using_crates.is_some()
+            || self.not.as_ref().is_some_and(|c| c.depends_on_crates())
+            || self
+                .any
+                .iter()
+                .chain(&self.all)
+                .flatten()
+                .any(|c| c.depends_on_crates())
+    }
+}
 
-    // Check direct dependencies of all workspace members
-    for member_id in &metadata.workspace_members {
-        if let Some(package) = full_metadata.packages.iter().find(|p| &p.id == member_id) {
-            if has_dependency_recursive(&full_metadata, package, crate_name, &mut visited) {
-                return true;
-            }
+/// Load the dependency index for a workspace, logging (and returning `None`) on failure.
+fn load_dependency_index(workspace_path: &Path) -> Option<Arc<WorkspaceDependencyIndex>> {
+    match WorkspaceDependencyIndex::load(workspace_path) {
+        Ok(index) => Some(index),
+        Err(e) => {
+            tracing::warn!(?workspace_path, error = %e, "Failed to resolve workspace dependencies");
+            None
         }
     }
-
-    false
 }
 
-/// Recursively check if a package has a dependency on the given crate.
-/// Battery packs are transparent - we recurse into their dependencies.
-fn has_dependency_recursive(
-    metadata: &Metadata,
-    package: &Package,
-    crate_name: &str,
-    visited: &mut HashSet<PackageId>,
-) -> bool {
-    for dep in &package.dependencies {
-        // Check if this dependency matches
-        if dep.name == crate_name {
-            return true;
+/// Check if any file in the workspace matches a glob pattern.
+///
+/// The pattern is matched against paths relative to the workspace root.
+/// Files ignored by `.gitignore` (e.g., `target/`) and hidden directories
+/// are skipped.
+fn glob_matches(workspace_path: &Path, pattern: &str) -> bool {
+    let matcher = match GlobBuilder::new(pattern).literal_separator(true).build() {
+        Ok(glob) => glob.compile_matcher(),
+        Err(e) => {
+            tracing::warn!(pattern, error = %e, "Invalid glob in recommendation condition");
+            return false;
         }
+    };
 
-        // If it's a battery pack, recurse into its dependencies
-        if dep.name.ends_with("-battery-pack") {
-            // Find the resolved package for this dependency
-            if let Some(dep_package) = metadata.packages.iter().find(|p| p.name == dep.name) {
-                if visited.insert(dep_package.id.clone()) {
-                    if has_dependency_recursive(metadata, dep_package, crate_name, visited) {
-                        return true;
-                    }
-                }
-            }
+    WalkBuilder::new(workspace_path)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .any(|entry| {
+            entry
+                .path()
+                .strip_prefix(workspace_path)
+                .is_ok_and(|relative| matcher.is_match(relative))
+        })
+}
+
+/// Check if a workspace file exists and its contents match a regex.
+fn file_contains_matches(workspace_path: &Path, file_contains: &FileContains) -> bool {
+    let regex = match Regex::new(&file_contains.regex) {
+        Ok(regex) => regex,
+        Err(e) => {
+            tracing::warn!(
+                regex = %file_contains.regex,
+                error = %e,
+                "Invalid regex in recommendation condition"
+            );
+            return false;
         }
-    }
+    };
 
-    false
+    match std::fs::read_to_string(workspace_path.join(&file_contains.path)) {
+        Ok(contents) => regex.is_match(&contents),
+        Err(_) => false,
+    }
 }
 
 // ============================================================================
@@ -213,33 +283,24 @@ impl RecommendationsExt for Recommendations {
     fn for_workspace(&self, workspace_path: &Path) -> WorkspaceRecommendations {
         use crate::remote_recommendations::load_workspace_recommendations;
 
+        // Resolve the workspace's crates once for all conditions
+        let dependencies = load_dependency_index(workspace_path);
+        let is_met = |r: &Recommendation| {
+            r.when
+                .as_ref()
+                .map(|w| w.is_met_with(workspace_path, dependencies.as_deref()))
+                .unwrap_or(true)
+        };
+
         // Filter global recommendations by workspace conditions
-        let mut mods: Vec<Recommendation> = self
-            .mods
-            .iter()
-            .filter(|r| {
-                r.when
-                    .as_ref()
-                    .map(|w| w.is_met(workspace_path))
-                    .unwrap_or(true)
-            })
-            .cloned()
-            .collect();
+        let mut mods: Vec<Recommendation> =
+            self.mods.iter().filter(|r| is_met(r)).cloned().collect();
 
         // Merge workspace-specific recommendations if present
         match load_workspace_recommendations(workspace_path) {
             Ok(Some(workspace_recs)) => {
                 // Filter workspace recommendations by their conditions too
-                for rec in workspace_recs.mods {
-                    let meets_condition = rec
-                        .when
-                        .as_ref()
-                        .map(|w| w.is_met(workspace_path))
-                        .unwrap_or(true);
-                    if meets_condition {
-                        mods.push(rec);
-                    }
-                }
+                mods.extend(workspace_recs.mods.into_iter().filter(|r| is_met(r)));
             }
             Ok(None) => {
                 // No workspace recommendations file - that's fine
@@ -249,7 +310,7 @@ impl RecommendationsExt for Recommendations {
             }
         }
 
-        WorkspaceRecommendations { mods }
+        WorkspaceRecommendations { mods, dependencies }
     }
 }
 
@@ -261,6 +322,11 @@ impl RecommendationsExt for Recommendations {
 #[derive(Debug, Clone, Default)]
 pub struct WorkspaceRecommendations {
     pub mods: Vec<Recommendation>,
+
+    /// The workspace's crates, or `None` if they could not be resolved
+    /// (e.g., `Cargo.toml` is mid-edit). In that case crate conditions were
+    /// not met, so mods relying on them are not reported as stale.
+    pub dependencies: Option<Arc<WorkspaceDependencyIndex>>,
 }
 
 impl WorkspaceRecommendations {
@@ -286,40 +352,101 @@ impl WorkspaceRecommendations {
                     source: m.source.clone(),
                     when: m.when.clone().unwrap_or_default(),
                     enabled: true,
+                    order: 0,
+                    version: None,
+                    policy: Default::default(),
+                    overrides: Default::default(),
                 });
             }
         }
 
         let mut to_remove = vec![];
         for m in &config.mods {
-            if !recommended_sources.contains(&m.source) {
-                // Stale - remove it
-                to_remove.push(m.clone());
+            if recommended_sources.contains(&m.source) {
+                continue;
+            }
+
+            // Can't tell whether crate conditions still hold - keep it for now
+            if self.dependencies.is_none() && m.when.depends_on_crates() {
+                continue;
             }
+
+            // Stale - remove it
+            to_remove.push(m.clone());
         }
 
         if !to_add.is_empty() || !to_remove.is_empty() {
-            Some(RecommendationDiff { to_add, to_remove })
+            Some(RecommendationDiff {
+                to_add,
+                to_remove,
+                lock_mismatches: vec![],
+            })
         } else {
             None
         }
     }
 }
 
+/// The changes to suggest for a workspace's configuration: mods that are newly
+/// or no longer recommended, and differences from the workspace's `mods.lock`.
+///
+/// A lockfile that can't be read is logged and left out of the diff.
+pub fn workspace_diff(
+    recommendations: Option<&Recommendations>,
+    workspace_path: &Path,
+    config: &WorkspaceModsConfig,
+) -> RecommendationDiff {
+    let mut diff = recommendations
+        .and_then(|recs| recs.for_workspace(workspace_path).diff_against(config))
+        .unwrap_or_default();
+    match ModsLock::load(workspace_path) {
+        Ok(Some(lock)) => diff.lock_mismatches = lock.mismatches(&config.mods),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to load mods lock: {:#}", e),
+    }
+    diff
+}
+
 /// A new recommendation that isn't in the user's config yet
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct RecommendationDiff {
     /// Sources for mods that were newly recommended
     pub to_add: Vec<ModConfig>,
 
     /// Configuration for mods that were removed
     pub to_remove: Vec<ModConfig>,
+
+    /// Differences between the configuration and the workspace's `mods.lock`
+    pub lock_mismatches: Vec<LockMismatch>,
 }
 
 impl RecommendationDiff {
     /// True if this diff has no changes
     pub fn is_empty(&self) -> bool {
-        self.to_add.is_empty() && self.to_remove.is_empty()
+        self.to_add.is_empty() && self.to_remove.is_empty() && self.lock_mismatches.is_empty()
+    }
+
+    /// Describe the changes in one line, e.g.
+    /// "newly recommended: foo, bar; no longer recommended: old".
+    pub fn summary(&self) -> String {
+        let names = |mods: &[ModConfig]| {
+            mods.iter()
+                .map(|m| m.source.display_name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let mut parts = vec![];
+        if !self.to_add.is_empty() {
+            parts.push(format!("newly recommended: {}", names(&self.to_add)));
+        }
+        if !self.to_remove.is_empty() {
+            parts.push(format!("no longer recommended: {}", names(&self.to_remove)));
+        }
+        if !self.lock_mismatches.is_empty() {
+            parts.push("`mods.lock` is out of date".to_string());
+        }
+        parts.join("; ")
     }
 
     /// Apply this diff to the given workspace mods config
@@ -328,9 +455,9 @@ impl RecommendationDiff {
             return;
         }
 
-        // Add new recommendations
+        // Add new recommendations at the end of the chain
         for m in &self.to_add {
-            config.mods.push(m.clone());
+            config.push_mod(m.clone());
         }
 
         // Remove stale recommendations
@@ -459,6 +586,105 @@ when.files-exist = ["src/lib.rs"]
         assert_eq!(workspace_recs.mods.len(), 1);
     }
 
+    #[test]
+    fn test_when_glob_condition() {
+        let toml = r#"
+[[recommendation]]
+source.builtin = "proto-helper"
+when.glob = "**/*.proto"
+"#;
+
+        let recs = Recommendations::from_toml(toml).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        // No proto files
+        std::fs::write(temp_dir.path().join("README.md"), "").unwrap();
+        let workspace_recs = recs.for_workspace(temp_dir.path());
+        assert_eq!(workspace_recs.mods.len(), 0);
+
+        // Nested proto file
+        std::fs::create_dir_all(temp_dir.path().join("api/v1")).unwrap();
+        std::fs::write(temp_dir.path().join("api/v1/service.proto"), "").unwrap();
+        let workspace_recs = recs.for_workspace(temp_dir.path());
+        assert_eq!(workspace_recs.mods.len(), 1);
+    }
+
+    #[test]
+    fn test_when_file_contains_condition() {
+        let toml = r#"
+[[recommendation]]
+source.builtin = "edition-2024"
+when.file-contains = { path = "Cargo.toml", regex = 'edition\s*=\s*"2024"' }
+"#;
+
+        let recs = Recommendations::from_toml(toml).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        // Missing file
+        let workspace_recs = recs.for_workspace(temp_dir.path());
+        assert_eq!(workspace_recs.mods.len(), 0);
+
+        // File without a match
+        std::fs::write(temp_dir.path().join("Cargo.toml"), "edition = \"2021\"").unwrap();
+        let workspace_recs = recs.for_workspace(temp_dir.path());
+        assert_eq!(workspace_recs.mods.len(), 0);
+
+        // File with a match
+        std::fs::write(temp_dir.path().join("Cargo.toml"), "edition = \"2024\"").unwrap();
+        let workspace_recs = recs.for_workspace(temp_dir.path());
+        assert_eq!(workspace_recs.mods.len(), 1);
+    }
+
+    #[test]
+    fn test_when_not_condition() {
+        let toml = r#"
+[[recommendation]]
+source.builtin = "rust-only"
+when.file-exists = "Cargo.toml"
+when.not = { file-exists = "package.json" }
+"#;
+
+        let recs = Recommendations::from_toml(toml).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        std::fs::write(temp_dir.path().join("Cargo.toml"), "[package]").unwrap();
+        let workspace_recs = recs.for_workspace(temp_dir.path());
+        assert_eq!(workspace_recs.mods.len(), 1);
+
+        std::fs::write(temp_dir.path().join("package.json"), "{}").unwrap();
+        let workspace_recs = recs.for_workspace(temp_dir.path());
+        assert_eq!(workspace_recs.mods.len(), 0);
+    }
+
+    #[test]
+    #[serial]
+    fn test_when_env_var_set_condition() {
+        const VAR: &str = "SYMPOSIUM_TEST_WHEN_ENV_VAR_SET";
+
+        let when = When {
+            env_var_set: Some(VAR.to_string()),
+            ..Default::default()
+        };
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        // SAFETY: `#[serial]` keeps this from running alongside the other tests
+        // that change the environment
+        unsafe {
+            std::env::remove_var(VAR);
+        }
+        assert!(!when.is_met(temp_dir.path()));
+
+        unsafe {
+            std::env::set_var(VAR, "1");
+        }
+        assert!(when.is_met(temp_dir.path()));
+
+        // Clean up
+        unsafe {
+            std::env::remove_var(VAR);
+        }
+    }
+
     // ========================================================================
     // Diff tests
     // ========================================================================
@@ -473,6 +699,7 @@ when.files-exist = ["src/lib.rs"]
                     when,
                 })
                 .collect(),
+            dependencies: Some(Default::default()),
         }
     }
 
@@ -506,11 +733,23 @@ when.files-exist = ["src/lib.rs"]
                                 "Cargo.toml",
                             ),
                             files_exist: None,
+                            glob: None,
+                            file_contains: None,
                             using_crate: None,
                             using_crates: None,
+                            env_var_set: None,
+                            not: None,
                             any: None,
                             all: None,
                         },
+                        order: 0,
+                        version: None,
+                        policy: Optional,
+                        overrides: ModOverrides {
+                            args: [],
+                            env: {},
+                            cwd: None,
+                        },
                     },
                     ModConfig {
                         kind: Proxy,
@@ -521,14 +760,27 @@ when.files-exist = ["src/lib.rs"]
                         when: When {
                             file_exists: None,
                             files_exist: None,
+                            glob: None,
+                            file_contains: None,
                             using_crate: None,
                             using_crates: None,
+                            env_var_set: None,
+                            not: None,
                             any: None,
                             all: None,
                         },
+                        order: 0,
+                        version: None,
+                        policy: Optional,
+                        overrides: ModOverrides {
+                            args: [],
+                            env: {},
+                            cwd: None,
+                        },
                     },
                 ],
                 to_remove: [],
+                lock_mismatches: [],
             }
         "#]]
         .assert_debug_eq(&diff);
@@ -548,6 +800,10 @@ when.files-exist = ["src/lib.rs"]
                 file_exists: Some("old.txt".to_string()),
                 ..Default::default()
             },
+            order: 0,
+            version: None,
+            policy: Default::default(),
+            overrides: Default::default(),
         });
 
         let diff = recs.diff_against(&config).expect("should have changes");
@@ -567,18 +823,70 @@ when.files-exist = ["src/lib.rs"]
                                 "old.txt",
                             ),
                             files_exist: None,
+                            glob: None,
+                            file_contains: None,
                             using_crate: None,
                             using_crates: None,
+                            env_var_set: None,
+                            not: None,
                             any: None,
                             all: None,
                         },
+                        order: 0,
+                        version: None,
+                        policy: Optional,
+                        overrides: ModOverrides {
+                            args: [],
+                            env: {},
+                            cwd: None,
+                        },
                     },
                 ],
+                lock_mismatches: [],
             }
         "#]]
         .assert_debug_eq(&diff);
     }
 
+    #[test]
+    fn test_diff_keeps_crate_mods_when_dependencies_unresolved() {
+        let mut recs = make_workspace_recs(vec![]);
+        recs.dependencies = None;
+
+        let mut config = WorkspaceModsConfig::new(vec![]);
+        config.push_mod(ModConfig {
+            kind: ModKind::Proxy,
+            source: ComponentSource::Builtin("tokio-helper".to_string()),
+            enabled: true,
+            when: When {
+                using_crate: Some("tokio".into()),
+                ..Default::default()
+            },
+            order: 0,
+            version: None,
+            policy: Default::default(),
+            overrides: Default::default(),
+        });
+        config.push_mod(ModConfig {
+            kind: ModKind::Proxy,
+            source: ComponentSource::Builtin("old-mod".to_string()),
+            enabled: true,
+            when: When {
+                file_exists: Some("old.txt".to_string()),
+                ..Default::default()
+            },
+            order: 0,
+            version: None,
+            policy: Default::default(),
+            overrides: Default::default(),
+        });
+
+        // Only the mod that doesn't depend on crates is stale
+        let diff = recs.diff_against(&config).expect("should have changes");
+        assert_eq!(diff.to_remove.len(), 1);
+        assert_eq!(diff.to_remove[0].source.display_name(), "old-mod");
+    }
+
     #[test]
     fn test_diff_no_changes_when_in_sync() {
         let recs = make_workspace_recs(vec![("foo", None)]);
@@ -590,6 +898,10 @@ when.files-exist = ["src/lib.rs"]
             source: ComponentSource::Builtin("foo".to_string()),
             enabled: true,
             when: When::default(),
+            order: 0,
+            version: None,
+            policy: Default::default(),
+            overrides: Default::default(),
         });
 
         let diff = recs.diff_against(&config);
@@ -606,6 +918,10 @@ when.files-exist = ["src/lib.rs"]
             source: ComponentSource::Builtin("foo".to_string()),
             enabled: false, // Disabled
             when: When::default(),
+            order: 0,
+            version: None,
+            policy: Default::default(),
+            overrides: Default::default(),
         });
 
         let diff = recs.diff_against(&config);
@@ -624,6 +940,10 @@ when.files-exist = ["src/lib.rs"]
             source: ComponentSource::Builtin("old".to_string()),
             enabled: true,
             when: When::default(),
+            order: 0,
+            version: None,
+            policy: Default::default(),
+            overrides: Default::default(),
         });
 
         let diff = recs.diff_against(&config).expect("should have changes");
@@ -643,6 +963,35 @@ when.files-exist = ["src/lib.rs"]
         assert!(!config.mods.iter().any(|m| m.source == old_source));
     }
 
+    #[test]
+    fn test_diff_summary() {
+        let recs = make_workspace_recs(vec![("foo", None), ("bar", None)]);
+        let mut config = WorkspaceModsConfig::new(vec![]);
+        config.mods.push(ModConfig {
+            kind: ModKind::Proxy,
+            source: ComponentSource::Builtin("old".to_string()),
+            enabled: true,
+            when: When::default(),
+            order: 0,
+            version: None,
+            policy: Default::default(),
+            overrides: Default::default(),
+        });
+
+        let mut diff = recs.diff_against(&config).expect("should have changes");
+        assert_eq!(
+            diff.summary(),
+            "newly recommended: foo, bar; no longer recommended: old"
+        );
+
+        diff.to_add.clear();
+        diff.to_remove.clear();
+        diff.lock_mismatches.push(LockMismatch::Unlocked {
+            name: "foo".to_string(),
+        });
+        assert_eq!(diff.summary(), "`mods.lock` is out of date");
+    }
+
     #[test]
     fn test_when_explanations() {
         let when = When {
@@ -681,14 +1030,14 @@ serde = "1"
 
         // Test using-crate condition
         let when = When {
-            using_crate: Some("serde".to_string()),
+            using_crate: Some("serde".into()),
             ..Default::default()
         };
         assert!(when.is_met(temp_dir.path()));
 
         // Test crate that's not a dependency
         let when = When {
-            using_crate: Some("tokio".to_string()),
+            using_crate: Some("tokio".into()),
             ..Default::default()
         };
         assert!(!when.is_met(temp_dir.path()));
@@ -720,19 +1069,50 @@ anyhow = "1"
 
         // Both crates are dependencies
         let when = When {
-            using_crates: Some(vec!["serde".to_string(), "anyhow".to_string()]),
+            using_crates: Some(vec!["serde".into(), "anyhow".into()]),
             ..Default::default()
         };
         assert!(when.is_met(temp_dir.path()));
 
         // One crate is missing
         let when = When {
-            using_crates: Some(vec!["serde".to_string(), "tokio".to_string()]),
+            using_crates: Some(vec!["serde".into(), "tokio".into()]),
             ..Default::default()
         };
         assert!(!when.is_met(temp_dir.path()));
     }
 
+    #[test]
+    #[serial]
+    #[ignore = "https://github.com/symposium-dev/symposium/issues/112"]
+    fn test_using_crate_version_condition() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        write_synced(
+            &temp_dir.path().join("Cargo.toml"),
+            r#"
+[package]
+name = "test-project"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+serde = "1"
+"#,
+        );
+
+        std::fs::create_dir(temp_dir.path().join("src")).unwrap();
+        write_synced(&temp_dir.path().join("src/lib.rs"), "");
+
+        let when: When =
+            toml::from_str(r#"using-crate = { name = "serde", version = ">=1.0" }"#).unwrap();
+        assert!(when.is_met(temp_dir.path()));
+
+        let when: When =
+            toml::from_str(r#"using-crate = { name = "serde", version = "<1.0" }"#).unwrap();
+        assert!(!when.is_met(temp_dir.path()));
+    }
+
     #[test]
     #[serial]
     #[ignore = "https://github.com/symposium-dev/symposium/issues/112"]
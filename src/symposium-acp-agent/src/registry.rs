@@ -1,26 +1,41 @@
 //! Agent registry - fetching and resolving agents from the ACP registry.
 //!
 //! This module provides functionality to:
-//! - Fetch the agent registry from GitHub
+//! - Fetch the public agent registry from GitHub, plus any configured registries
 //! - Merge with built-in agents
 //! - Resolve agent distributions to executable commands
 //! - Download and cache binary distributions
 
-use crate::user_config::ConfigPaths;
+use crate::offline;
+use crate::user_config::{ConfigPaths, FileLocation, RegistryConfig, UserSettings};
+use crate::versions;
 use anyhow::{Context, Result, bail};
 use sacp::schema::{EnvVariable, McpServer, McpServerHttp, McpServerSse, McpServerStdio};
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use symposium_recommendations::{
     BinaryDistribution, CargoDistribution, ComponentSource, HttpDistribution, LocalDistribution,
     NpxDistribution, PipxDistribution,
 };
 
-/// Registry URL - same as VSCode extension uses
-const REGISTRY_URL: &str =
-    "https://github.com/agentclientprotocol/registry/releases/latest/download/registry.json";
+/// Separates the registry ID from the entry ID in namespaced IDs (`acme:our-agent`)
+const REGISTRY_ID_SEPARATOR: char = ':';
+
+/// File in a binary cache directory recording the checksums of its contents
+pub(crate) const CHECKSUM_FILE: &str = ".symposium-checksums.json";
+
+/// A downloaded archive did not match the `sha256` of its distribution
+#[derive(Debug, thiserror::Error)]
+#[error("Checksum mismatch for {archive}: expected sha256 {expected}, got {actual}")]
+pub struct ChecksumMismatch {
+    pub archive: String,
+    pub expected: String,
+    pub actual: String,
+}
 
 // ============================================================================
 // Registry Types (matching the registry JSON format)
@@ -70,22 +85,38 @@ pub struct Distribution {
 pub trait ComponentSourceExt {
     /// Resolve this source to an McpServer that can be spawned
     fn resolve(&self) -> impl std::future::Future<Output = Result<McpServer>> + Send;
+
+    /// Resolve this source at a pinned version (see [`crate::versions`]).
+    ///
+    /// Sources without a version (local commands, URLs, ...) ignore the pin.
+    fn resolve_version(
+        &self,
+        version: Option<&str>,
+    ) -> impl std::future::Future<Output = Result<McpServer>> + Send;
 }
 
 impl ComponentSourceExt for ComponentSource {
     async fn resolve(&self) -> Result<McpServer> {
-        match self {
-            ComponentSource::Builtin(name) => resolve_builtin(name).await,
-            ComponentSource::Registry(id) => resolve_from_registry(id).await,
-            ComponentSource::Url(url) => resolve_from_url(url).await,
-            ComponentSource::Local(local) => resolve_local(local),
-            ComponentSource::Npx(npx) => resolve_npx(npx),
-            ComponentSource::Pipx(pipx) => resolve_pipx(pipx),
-            ComponentSource::Cargo(cargo) => resolve_cargo(cargo).await,
-            ComponentSource::Binary(binary_map) => resolve_binary(binary_map).await,
-            ComponentSource::Http(dist) => Ok(resolve_http(dist)),
-            ComponentSource::Sse(dist) => Ok(resolve_sse(dist)),
-        }
+        self.resolve_version(None).await
+    }
+
+    async fn resolve_version(&self, version: Option<&str>) -> Result<McpServer> {
+        let pinned = version.and_then(|version| versions::pin_source(self, version));
+
+        let mut server = match pinned.as_ref().unwrap_or(self) {
+            ComponentSource::Builtin(name) => resolve_builtin(name).await?,
+            ComponentSource::Registry(id) => resolve_from_registry(id, version).await?,
+            ComponentSource::Url(url) => resolve_from_url(url).await?,
+            ComponentSource::Local(local) => resolve_local(local)?,
+            ComponentSource::Npx(npx) => resolve_npx(npx)?,
+            ComponentSource::Pipx(pipx) => resolve_pipx(pipx)?,
+            ComponentSource::Cargo(cargo) => resolve_cargo(cargo).await?,
+            ComponentSource::Binary(binary_map) => resolve_binary(binary_map).await?,
+            ComponentSource::Http(dist) => resolve_http(dist),
+            ComponentSource::Sse(dist) => resolve_sse(dist),
+        };
+        offline::forward_to(&mut server);
+        Ok(server)
     }
 }
 
@@ -96,8 +127,12 @@ impl ComponentSourceExt for ComponentSource {
 /// Agent listing entry - what `registry list` outputs
 #[derive(Debug, Clone, Serialize)]
 pub struct AgentListEntry {
+    /// Namespaced ID (see [`qualified_id`])
     pub id: String,
     pub name: String,
+    /// ID of the registry the agent came from (`None` for built-in agents)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -166,11 +201,242 @@ pub fn built_in_agents() -> Result<Vec<RegistryEntry>> {
 // Registry Fetching
 // ============================================================================
 
-/// Fetch the registry from GitHub
-pub async fn fetch_registry() -> Result<RegistryJson> {
-    let response = reqwest::get(REGISTRY_URL)
-        .await
-        .context("Failed to fetch registry")?;
+/// A fetched registry, with the ID of the registry it came from
+#[derive(Debug, Clone)]
+pub struct FetchedRegistry {
+    pub registry_id: String,
+    pub registry: RegistryJson,
+}
+
+impl FetchedRegistry {
+    /// The ID an entry of this registry is known by
+    fn qualified_id(&self, entry_id: &str) -> String {
+        qualified_id(&self.registry_id, entry_id)
+    }
+}
+
+/// The ID an entry of the given registry is known by.
+///
+/// Entries of the public registry keep their ID; entries of other registries
+/// are prefixed with the registry ID (e.g. `acme:our-agent`).
+pub fn qualified_id(registry_id: &str, entry_id: &str) -> String {
+    if registry_id == crate::user_config::PUBLIC_REGISTRY_ID {
+        entry_id.to_string()
+    } else {
+        format!("{registry_id}{REGISTRY_ID_SEPARATOR}{entry_id}")
+    }
+}
+
+/// Split a namespaced ID into its registry ID and entry ID.
+///
+/// IDs without a registry prefix belong to the public registry.
+fn split_qualified_id(id: &str) -> (&str, &str) {
+    id.split_once(REGISTRY_ID_SEPARATOR)
+        .unwrap_or((crate::user_config::PUBLIC_REGISTRY_ID, id))
+}
+
+/// The registries to search, and the on-disk cache they are fetched through.
+///
+/// Registries served from a URL are cached in `<config_dir>/cache/registries/`.
+/// A cached registry younger than the TTL is used as is; an older one is
+/// revalidated with a conditional request (`If-None-Match` /
+/// `If-Modified-Since`). If the request fails, the cached registry is used
+/// anyway, and offline only the cache is consulted.
+#[derive(Debug, Clone)]
+pub struct Registries {
+    /// The public registry, then the configured ones
+    pub configs: Vec<RegistryConfig>,
+    /// Directory holding one `<registry-id>.json` per cached registry
+    cache_dir: PathBuf,
+    /// How long a cached registry is used without revalidating it
+    ttl: Duration,
+}
+
+/// A registry as last fetched from its URL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct CachedRegistry {
+    /// `ETag` response header, for conditional requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    /// `Last-Modified` response header, for conditional requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+    /// When the registry was last fetched or revalidated (seconds since the Unix epoch)
+    fetched_at: u64,
+    /// The registry JSON as served
+    body: String,
+}
+
+/// Seconds since the Unix epoch
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn parse_registry(body: &str) -> Result<RegistryJson> {
+    serde_json::from_str(body).context("Failed to parse registry JSON")
+}
+
+impl Registries {
+    /// The public registry plus the registries configured in the user settings
+    pub fn configured() -> Result<Self> {
+        let config_paths = ConfigPaths::default_location()?;
+        let settings = UserSettings::load(&config_paths)?;
+        Ok(Self {
+            configs: settings.registries(),
+            cache_dir: config_paths.registry_cache_dir(),
+            ttl: Duration::from_secs(settings.registry_cache_ttl_secs),
+        })
+    }
+
+    /// Fetch a registry from its URL (through the cache) or path
+    pub async fn fetch(&self, config: &RegistryConfig) -> Result<RegistryJson> {
+        let url = match &config.location {
+            FileLocation::Url(url) => url,
+            FileLocation::Path(path) => {
+                let body = tokio::fs::read_to_string(path)
+                    .await
+                    .with_context(|| format!("Failed to read registry from {}", path.display()))?;
+                return parse_registry(&body);
+            }
+        };
+
+        let cache_path = self.cache_dir.join(format!("{}.json", config.id));
+        let cached = read_cached_registry(&cache_path);
+
+        if offline::is_offline() {
+            let Some(cached) = cached else {
+                return Err(offline::not_cached(format!(
+                    "the agent registry `{}`",
+                    config.id
+                )));
+            };
+            return parse_registry(&cached.body);
+        }
+
+        if let Some(cached) = &cached
+            && unix_now().saturating_sub(cached.fetched_at) < self.ttl.as_secs()
+        {
+            tracing::debug!(registry = %config.id, "Using cached registry");
+            return parse_registry(&cached.body);
+        }
+
+        match fetch_registry_url(config, url, cached.as_ref()).await {
+            Ok((fresh, registry)) => {
+                if let Err(e) = write_cached_registry(&cache_path, &fresh).await {
+                    tracing::warn!("Failed to cache registry `{}`: {:#}", config.id, e);
+                }
+                Ok(registry)
+            }
+            Err(fetch_error) => {
+                let Some(cached) = cached else {
+                    return Err(fetch_error);
+                };
+                tracing::warn!(
+                    "Failed to fetch registry `{}`, using cached version: {:#}",
+                    config.id,
+                    fetch_error
+                );
+                parse_registry(&cached.body)
+            }
+        }
+    }
+
+    /// Fetch all the registries.
+    ///
+    /// Registries that fail are skipped with a warning; fails only if none of
+    /// them could be fetched.
+    pub async fn fetch_all(&self) -> Result<Vec<FetchedRegistry>> {
+        let mut fetched = Vec::with_capacity(self.configs.len());
+        let mut errors = Vec::new();
+
+        for config in &self.configs {
+            match self.fetch(config).await {
+                Ok(registry) => fetched.push(FetchedRegistry {
+                    registry_id: config.id.clone(),
+                    registry,
+                }),
+                Err(e) => {
+                    tracing::warn!("Skipping registry `{}`: {:#}", config.id, e);
+                    errors.push(format!("`{}`: {:#}", config.id, e));
+                }
+            }
+        }
+
+        if fetched.is_empty() && !errors.is_empty() {
+            bail!("Failed to fetch any registry:\n{}", errors.join("\n"));
+        }
+
+        Ok(fetched)
+    }
+
+    /// Find an entry by its namespaced ID, fetching only the registry it belongs to.
+    async fn find_entry(&self, id: &str, kind: EntryKind) -> Result<RegistryEntry> {
+        let (registry_id, entry_id) = split_qualified_id(id);
+        let config = self
+            .configs
+            .iter()
+            .find(|r| r.id == registry_id)
+            .with_context(|| format!("Unknown registry `{}` in '{}'", registry_id, id))?;
+
+        let registry = self.fetch(config).await?;
+        let agents = registry
+            .agents
+            .into_iter()
+            .filter(|_| kind != EntryKind::Mod);
+        let mods = registry
+            .mods
+            .into_iter()
+            .filter(|_| kind != EntryKind::Agent);
+        agents
+            .chain(mods)
+            .find(|e| e.id == entry_id)
+            .with_context(|| format!("'{}' not found in registry `{}`", entry_id, registry_id))
+    }
+}
+
+/// Fetch a registry from its URL, revalidating `cached` if given.
+///
+/// Returns the new cache entry along with the parsed registry.
+async fn fetch_registry_url(
+    config: &RegistryConfig,
+    url: &str,
+    cached: Option<&CachedRegistry>,
+) -> Result<(CachedRegistry, RegistryJson)> {
+    use reqwest::header;
+
+    let mut request = reqwest::Client::new().get(url);
+    if let Some(var) = &config.auth_header_env {
+        let value = std::env::var(var).with_context(|| {
+            format!("Environment variable `{var}` for the auth header is not set")
+        })?;
+        request = request.header(header::AUTHORIZATION, value);
+    }
+    if let Some(cached) = cached {
+        if let Some(etag) = &cached.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = request.send().await.context("Failed to fetch registry")?;
+
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED
+        && let Some(cached) = cached
+    {
+        tracing::debug!(registry = %config.id, "Cached registry is still current");
+        let registry = parse_registry(&cached.body)?;
+        let fresh = CachedRegistry {
+            fetched_at: unix_now(),
+            ..cached.clone()
+        };
+        return Ok((fresh, registry));
+    }
 
     if !response.status().is_success() {
         bail!(
@@ -180,15 +446,65 @@ pub async fn fetch_registry() -> Result<RegistryJson> {
         );
     }
 
-    let registry: RegistryJson = response
-        .json()
+    let header_value = |name: header::HeaderName| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+    let etag = header_value(header::ETAG);
+    let last_modified = header_value(header::LAST_MODIFIED);
+
+    let body = response
+        .text()
         .await
-        .context("Failed to parse registry JSON")?;
+        .context("Failed to read registry response")?;
+    let registry = parse_registry(&body)?;
+
+    let fresh = CachedRegistry {
+        etag,
+        last_modified,
+        fetched_at: unix_now(),
+        body,
+    };
+    Ok((fresh, registry))
+}
 
-    Ok(registry)
+/// Read a cached registry, treating an unreadable cache as missing
+fn read_cached_registry(cache_path: &Path) -> Option<CachedRegistry> {
+    let content = std::fs::read_to_string(cache_path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(cached) => Some(cached),
+        Err(e) => {
+            tracing::warn!(
+                "Ignoring corrupt registry cache {}: {}",
+                cache_path.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+async fn write_cached_registry(cache_path: &Path, cached: &CachedRegistry) -> Result<()> {
+    if let Some(dir) = cache_path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    tokio::fs::write(cache_path, serde_json::to_string(cached)?)
+        .await
+        .with_context(|| format!("Failed to write registry cache {}", cache_path.display()))
 }
 
-/// List all available agents (built-ins + registry)
+/// Which part of a registry to search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntryKind {
+    Agent,
+    Mod,
+    Any,
+}
+
+/// List all available agents (built-ins + registries)
 pub async fn list_agents() -> Result<Vec<AgentListEntry>> {
     let agents_with_sources = list_agents_with_sources().await?;
     Ok(agents_with_sources
@@ -210,15 +526,23 @@ pub struct AgentSelectionEntry {
 /// Returns pairs of (AgentListEntry, ComponentSource) so the UI can display
 /// the agent info and store the appropriate ComponentSource when selected.
 pub async fn list_agents_with_sources() -> Result<Vec<(AgentListEntry, ComponentSource)>> {
+    list_agents_from(&Registries::configured()?).await
+}
+
+/// List the built-in agents and the agents of the given registries.
+async fn list_agents_from(
+    registries: &Registries,
+) -> Result<Vec<(AgentListEntry, ComponentSource)>> {
     let mut agents = Vec::new();
 
     // Built-in agents - use their specific distribution as the source
     for entry in built_in_agents()? {
-        let source = entry_to_component_source(&entry);
+        let source = entry_to_component_source(&entry, &entry.id);
         agents.push((
             AgentListEntry {
                 id: entry.id,
                 name: entry.name,
+                registry: None,
                 version: if entry.version.is_empty() {
                     None
                 } else {
@@ -231,26 +555,38 @@ pub async fn list_agents_with_sources() -> Result<Vec<(AgentListEntry, Component
     }
 
     // Fetch and merge registry agents
-    let registry = fetch_registry().await?;
-    for entry in registry.agents {
-        // Skip if we already have this agent (built-in takes precedence)
-        if agents.iter().any(|(a, _)| a.id == entry.id) {
-            continue;
+    let fetched = match registries.fetch_all().await {
+        Ok(fetched) => fetched,
+        // Offline, the built-in agents (and local registries) are still available
+        Err(e) if offline::is_offline() => {
+            tracing::debug!("Offline mode: listing built-in agents only: {:#}", e);
+            vec![]
         }
-        let source = entry_to_component_source(&entry);
-        agents.push((
-            AgentListEntry {
-                id: entry.id,
-                name: entry.name,
-                version: if entry.version.is_empty() {
-                    None
-                } else {
-                    Some(entry.version)
+        Err(e) => return Err(e),
+    };
+    for fetched in fetched {
+        for entry in &fetched.registry.agents {
+            let id = fetched.qualified_id(&entry.id);
+            // Skip if we already have this agent (built-in takes precedence)
+            if agents.iter().any(|(a, _)| a.id == id) {
+                continue;
+            }
+            let source = entry_to_component_source(entry, &id);
+            agents.push((
+                AgentListEntry {
+                    id,
+                    name: entry.name.clone(),
+                    registry: Some(fetched.registry_id.clone()),
+                    version: if entry.version.is_empty() {
+                        None
+                    } else {
+                        Some(entry.version.clone())
+                    },
+                    description: entry.description.clone(),
                 },
-                description: entry.description,
-            },
-            source,
-        ));
+                source,
+            ));
+        }
     }
 
     Ok(agents)
@@ -258,7 +594,7 @@ pub async fn list_agents_with_sources() -> Result<Vec<(AgentListEntry, Component
 
 /// Look up an agent by ID and return its ComponentSource.
 ///
-/// This checks built-in agents first, then fetches the registry.
+/// This checks built-in agents first, then fetches the registries.
 /// The agent ID is the same format used by `registry resolve-agent`.
 pub async fn lookup_agent_source(agent_id: &str) -> Result<ComponentSource> {
     let agents = list_agents_with_sources().await?;
@@ -269,10 +605,19 @@ pub async fn lookup_agent_source(agent_id: &str) -> Result<ComponentSource> {
         .with_context(|| format!("Agent '{}' not found", agent_id))
 }
 
+/// Look up an agent or mod by its (namespaced) ID in the registries.
+pub async fn lookup_registry_entry(id: &str) -> Result<RegistryEntry> {
+    Registries::configured()?
+        .find_entry(id, EntryKind::Any)
+        .await
+        .with_context(|| format!("Component '{}' not found", id))
+}
+
 /// Convert a RegistryEntry to the appropriate ComponentSource.
 ///
-/// Uses the entry's distribution to determine the most specific source type.
-fn entry_to_component_source(entry: &RegistryEntry) -> ComponentSource {
+/// Uses the entry's distribution to determine the most specific source type,
+/// falling back to the entry's namespaced `id`.
+fn entry_to_component_source(entry: &RegistryEntry, id: &str) -> ComponentSource {
     let dist = &entry.distribution;
 
     // Use the most specific source based on distribution type
@@ -291,39 +636,47 @@ fn entry_to_component_source(entry: &RegistryEntry) -> ComponentSource {
         ComponentSource::Binary(btree)
     } else {
         // Fallback to registry ID if no distribution specified
-        ComponentSource::Registry(entry.id.clone())
+        ComponentSource::Registry(id.to_string())
     }
 }
 
 /// Mod listing entry - what `registry list-mods` outputs
 #[derive(Debug, Clone, Serialize)]
 pub struct ModListEntry {
+    /// Namespaced ID (see [`qualified_id`])
     pub id: String,
     pub name: String,
+    /// ID of the registry the mod came from
+    pub registry: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
 }
 
-/// List all available mods from the registry
+/// List all available mods from the registries
 pub async fn list_mods() -> Result<Vec<ModListEntry>> {
-    let registry = fetch_registry().await?;
+    list_mods_from(&Registries::configured()?).await
+}
 
-    let mods: Vec<ModListEntry> = registry
-        .mods
-        .into_iter()
-        .map(|m| ModListEntry {
-            id: m.id,
-            name: m.name,
-            version: if m.version.is_empty() {
-                None
-            } else {
-                Some(m.version)
-            },
-            description: m.description,
-        })
-        .collect();
+/// List the mods of the given registries.
+async fn list_mods_from(registries: &Registries) -> Result<Vec<ModListEntry>> {
+    let mut mods = Vec::new();
+    for fetched in registries.fetch_all().await? {
+        for m in &fetched.registry.mods {
+            mods.push(ModListEntry {
+                id: fetched.qualified_id(&m.id),
+                name: m.name.clone(),
+                registry: fetched.registry_id.clone(),
+                version: if m.version.is_empty() {
+                    None
+                } else {
+                    Some(m.version.clone())
+                },
+                description: m.description.clone(),
+            });
+        }
+    }
 
     Ok(mods)
 }
@@ -356,40 +709,134 @@ struct CratesIoCrate {
     max_version: String,
 }
 
+/// Determine the version and binary names to use for a cargo distribution.
+///
+/// Online, this queries crates.io. Offline, it uses the versions already
+/// installed in the binary cache under `cache_id`.
+async fn crate_binaries(
+    cache_id: &str,
+    cargo: &CargoDistribution,
+) -> Result<(String, Vec<String>)> {
+    if offline::is_offline() {
+        let config_paths = ConfigPaths::default_location()?;
+        return cached_crate_binaries(&config_paths.binary_versions_dir(cache_id), cargo);
+    }
+
+    query_crate_binaries(&cargo.crate_name, cargo.version.as_deref()).await
+}
+
+/// Find the newest cached install of a cargo distribution in `versions_dir`
+/// (`<root>/bin/<id>/`), honoring its pinned version and binary name if any.
+///
+/// If nothing is cached but both the version and binary are pinned, returns
+/// them so the crate can still be installed from the cargo registry cache.
+fn cached_crate_binaries(
+    versions_dir: &Path,
+    cargo: &CargoDistribution,
+) -> Result<(String, Vec<String>)> {
+    let mut cached: Vec<(cargo_metadata::semver::Version, Vec<String>)> = vec![];
+
+    for entry in std::fs::read_dir(versions_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+    {
+        let version_name = entry.file_name().to_string_lossy().to_string();
+        if cargo.version.as_ref().is_some_and(|v| *v != version_name) {
+            continue;
+        }
+        let Ok(version) = cargo_metadata::semver::Version::parse(&version_name) else {
+            continue;
+        };
+
+        let bin_names: Vec<String> = std::fs::read_dir(entry.path().join("bin"))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect();
+        if bin_names.is_empty()
+            || cargo
+                .binary
+                .as_ref()
+                .is_some_and(|b| !bin_names.contains(b))
+        {
+            continue;
+        }
+
+        cached.push((version, bin_names));
+    }
+
+    if let Some((version, bin_names)) = cached.into_iter().max_by(|a, b| a.0.cmp(&b.0)) {
+        return Ok((version.to_string(), bin_names));
+    }
+
+    match (&cargo.version, &cargo.binary) {
+        (Some(version), Some(binary)) => Ok((version.clone(), vec![binary.clone()])),
+        _ => Err(offline::not_cached(format!(
+            "the binary for `{}` (looked in {})",
+            cargo.crate_name,
+            versions_dir.display()
+        ))),
+    }
+}
+
+/// HTTP client for the crates.io API (which requires a user agent)
+fn crates_io_client() -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .user_agent("symposium-acp-agent (https://github.com/symposium-dev/symposium)")
+        .build()?)
+}
+
+/// Query crates.io for the latest (stable, if any) version of a crate.
+pub async fn latest_crate_version(crate_name: &str) -> Result<String> {
+    if offline::is_offline() {
+        return Err(offline::not_cached(format!(
+            "crates.io information for `{}`",
+            crate_name
+        )));
+    }
+
+    let url = format!("https://crates.io/api/v1/crates/{}", crate_name);
+    let response = crates_io_client()?
+        .get(&url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to fetch crate info for {}", crate_name))?;
+
+    if !response.status().is_success() {
+        bail!("Crate '{}' not found on crates.io", crate_name);
+    }
+
+    let crate_info: CratesIoCrateResponse = response
+        .json()
+        .await
+        .context("Failed to parse crates.io response")?;
+
+    Ok(crate_info
+        .krate
+        .max_stable_version
+        .unwrap_or(crate_info.krate.max_version))
+}
+
 /// Query crates.io for binary names of a crate
 pub async fn query_crate_binaries(
     crate_name: &str,
     version: Option<&str>,
 ) -> Result<(String, Vec<String>)> {
-    let client = reqwest::Client::builder()
-        .user_agent("symposium-acp-agent (https://github.com/symposium-dev/symposium)")
-        .build()?;
+    if offline::is_offline() {
+        return Err(offline::not_cached(format!(
+            "crates.io information for `{}`",
+            crate_name
+        )));
+    }
+
+    let client = crates_io_client()?;
 
     // If no version specified, get the latest
     let version = match version {
         Some(v) => v.to_string(),
-        None => {
-            let url = format!("https://crates.io/api/v1/crates/{}", crate_name);
-            let response = client
-                .get(&url)
-                .send()
-                .await
-                .with_context(|| format!("Failed to fetch crate info for {}", crate_name))?;
-
-            if !response.status().is_success() {
-                bail!("Crate '{}' not found on crates.io", crate_name);
-            }
-
-            let crate_info: CratesIoCrateResponse = response
-                .json()
-                .await
-                .context("Failed to parse crates.io response")?;
-
-            crate_info
-                .krate
-                .max_stable_version
-                .unwrap_or(crate_info.krate.max_version)
-        }
+        None => latest_crate_version(crate_name).await?,
     };
 
     // Now get the version-specific info with bin_names
@@ -417,6 +864,28 @@ pub async fn query_crate_binaries(
     Ok((version, version_info.version.bin_names))
 }
 
+// ============================================================================
+// Progress Reporting
+// ============================================================================
+
+/// Receives messages about slow resolution steps (e.g. "Installing sparkle-mcp 0.5.0…")
+pub type ProgressSink = Arc<dyn Fn(String) + Send + Sync>;
+
+tokio::task_local! {
+    static PROGRESS: ProgressSink;
+}
+
+/// Run `future`, sending progress messages for any downloads or installs it does to `sink`.
+pub async fn with_progress<F: Future>(sink: ProgressSink, future: F) -> F::Output {
+    PROGRESS.scope(sink, future).await
+}
+
+/// Report a slow resolution step to the enclosing [`with_progress`], if any
+fn report_progress(message: String) {
+    tracing::info!("{}", message);
+    PROGRESS.try_with(|sink| sink(message)).ok();
+}
+
 // ============================================================================
 // Cargo Installation
 // ============================================================================
@@ -450,15 +919,18 @@ fn install_cargo_crate_sync(
     use std::fs;
     use std::process::Command;
 
-    // Clean up old versions first
-    if let Some(parent) = cache_dir.parent() {
-        if parent.exists() {
-            for entry in fs::read_dir(parent)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path != *cache_dir && path.is_dir() {
-                    fs::remove_dir_all(&path).ok();
-                }
+    let offline = offline::is_offline();
+
+    // Clean up old versions first (offline, they may be all we have)
+    if !offline
+        && let Some(parent) = cache_dir.parent()
+        && parent.exists()
+    {
+        for entry in fs::read_dir(parent)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path != *cache_dir && path.is_dir() {
+                fs::remove_dir_all(&path).ok();
             }
         }
     }
@@ -468,51 +940,67 @@ fn install_cargo_crate_sync(
 
     let crate_spec = format!("{}@{}", crate_name, version);
 
-    // Try cargo binstall first (faster, uses prebuilt binaries)
-    tracing::info!("Attempting cargo binstall for {}", crate_spec);
-    let binstall_result = Command::new("cargo")
-        .args([
-            "binstall",
-            "--no-confirm",
-            "--root",
-            cache_dir.to_str().unwrap(),
-            &crate_spec,
-        ])
-        .output();
-
     let binary_path = cache_dir.join("bin").join(binary_name);
 
-    match binstall_result {
-        Ok(output) if output.status.success() => {
-            tracing::info!("Successfully installed {} via cargo binstall", crate_spec);
-            if binary_path.exists() {
-                return Ok(binary_path);
+    // Try cargo binstall first (faster, uses prebuilt binaries - but needs the network)
+    if !offline {
+        tracing::info!("Attempting cargo binstall for {}", crate_spec);
+        let binstall_result = Command::new("cargo")
+            .args([
+                "binstall",
+                "--no-confirm",
+                "--root",
+                cache_dir.to_str().unwrap(),
+                &crate_spec,
+            ])
+            .output();
+
+        match binstall_result {
+            Ok(output) if output.status.success() => {
+                tracing::info!("Successfully installed {} via cargo binstall", crate_spec);
+                if binary_path.exists() {
+                    return Ok(binary_path);
+                }
+            }
+            Ok(output) => {
+                tracing::debug!(
+                    "cargo binstall failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+            Err(e) => {
+                tracing::debug!("cargo binstall not available: {}", e);
             }
-        }
-        Ok(output) => {
-            tracing::debug!(
-                "cargo binstall failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
-        }
-        Err(e) => {
-            tracing::debug!("cargo binstall not available: {}", e);
         }
     }
 
-    // Fall back to cargo install
+    // Fall back to cargo install (offline: from the cargo registry cache)
     tracing::info!("Falling back to cargo install for {}", crate_spec);
+    let mut install_args = vec![
+        "install",
+        "--root",
+        cache_dir.to_str().unwrap(),
+        &crate_spec,
+    ];
+    if offline {
+        install_args.push("--offline");
+    }
     let install_result = Command::new("cargo")
-        .args([
-            "install",
-            "--root",
-            cache_dir.to_str().unwrap(),
-            &crate_spec,
-        ])
+        .args(install_args)
         .output()
         .context("Failed to run cargo install")?;
 
     if !install_result.status.success() {
+        if offline {
+            return Err(offline::not_cached(format!(
+                "`{}` (in the cargo registry cache)",
+                crate_spec
+            ))
+            .context(format!(
+                "cargo install --offline failed: {}",
+                String::from_utf8_lossy(&install_result.stderr)
+            )));
+        }
         bail!(
             "cargo install failed for {}: {}",
             crate_spec,
@@ -584,15 +1072,13 @@ pub async fn resolve_agent(agent: &str) -> Result<McpServer> {
         }
     }
 
-    // Fetch registry and find the agent
-    let registry = fetch_registry().await?;
-    let entry = registry
-        .agents
-        .into_iter()
-        .find(|a| a.id == agent_id)
-        .with_context(|| format!("Agent '{}' not found in registry", agent_id))?;
+    // Fetch the agent's registry and find the agent
+    let entry = Registries::configured()?
+        .find_entry(&agent_id, EntryKind::Agent)
+        .await
+        .with_context(|| format!("Agent '{}' not found", agent_id))?;
 
-    if let Some(agent) = resolve_distribution(&entry).await? {
+    if let Some(agent) = resolve_distribution_as(&entry, &agent_id).await? {
         return Ok(agent);
     }
 
@@ -617,15 +1103,13 @@ pub async fn resolve_mod(mod_spec: &str) -> Result<McpServer> {
     } else {
         mod_spec.to_string()
     };
-    // Fetch registry and find the mod
-    let registry = fetch_registry().await?;
-    let entry = registry
-        .mods
-        .into_iter()
-        .find(|m| m.id == mod_id)
-        .with_context(|| format!("Mod '{}' not found in registry", mod_id))?;
+    // Fetch the mod's registry and find the mod
+    let entry = Registries::configured()?
+        .find_entry(&mod_id, EntryKind::Mod)
+        .await
+        .with_context(|| format!("Mod '{}' not found", mod_id))?;
 
-    if let Some(agent) = resolve_distribution(&entry).await? {
+    if let Some(agent) = resolve_distribution_as(&entry, &mod_id).await? {
         return Ok(agent);
     }
 
@@ -659,8 +1143,8 @@ async fn resolve_builtin(name: &str) -> Result<McpServer> {
     }
 }
 
-/// Resolve a component from the registry by ID
-async fn resolve_from_registry(id: &str) -> Result<McpServer> {
+/// Resolve a component from the registry by ID, optionally at a pinned version
+async fn resolve_from_registry(id: &str, version: Option<&str>) -> Result<McpServer> {
     // Check built-in agents first
     for entry in built_in_agents()? {
         if entry.id == id {
@@ -671,28 +1155,26 @@ async fn resolve_from_registry(id: &str) -> Result<McpServer> {
         }
     }
 
-    // Fetch registry
-    let registry = fetch_registry().await?;
+    // Fetch the component's registry (agents are checked before mods)
+    let entry = lookup_registry_entry(id).await?;
+    let entry = versions::resolve_pin(entry, version)?;
 
-    // Check agents
-    if let Some(entry) = registry.agents.iter().find(|a| a.id == id) {
-        if let Some(server) = resolve_distribution(entry).await? {
-            return Ok(server);
-        }
-    }
-
-    // Check mods
-    if let Some(entry) = registry.mods.iter().find(|m| m.id == id) {
-        if let Some(server) = resolve_distribution(entry).await? {
-            return Ok(server);
-        }
+    match resolve_distribution_as(&entry, id).await? {
+        Some(server) => Ok(server),
+        None => bail!(
+            "No compatible distribution found for '{}' on platform {}",
+            id,
+            get_platform_key()
+        ),
     }
-
-    bail!("Component '{}' not found in registry", id)
 }
 
 /// Resolve from a URL to extension.json
 async fn resolve_from_url(url: &str) -> Result<McpServer> {
+    if offline::is_offline() {
+        return Err(offline::not_cached(format!("the extension at {}", url)));
+    }
+
     // Fetch extension.json from URL
     let response = reqwest::get(url)
         .await
@@ -769,8 +1251,7 @@ fn resolve_pipx(pipx: &PipxDistribution) -> Result<McpServer> {
 /// Resolve a Cargo distribution
 async fn resolve_cargo(cargo: &CargoDistribution) -> Result<McpServer> {
     // Query crates.io for version and binary names
-    let (version, bin_names) =
-        query_crate_binaries(&cargo.crate_name, cargo.version.as_deref()).await?;
+    let (version, bin_names) = crate_binaries(&cargo.crate_name, cargo).await?;
 
     // Determine binary name
     let binary_name = match &cargo.binary {
@@ -795,6 +1276,7 @@ async fn resolve_cargo(cargo: &CargoDistribution) -> Result<McpServer> {
 
     // Check if we need to install
     if !binary_path.exists() {
+        report_progress(format!("Installing {} {}…", cargo.crate_name, version));
         install_cargo_crate(&cargo.crate_name, &version, &binary_name, &cache_dir).await?;
     }
 
@@ -823,7 +1305,8 @@ async fn resolve_binary(binary_map: &BTreeMap<String, BinaryDistribution>) -> Re
     let executable_path = cache_dir.join(executable);
 
     // Check if we need to download
-    if !executable_path.exists() {
+    if !is_cached_binary_valid(&cache_dir, binary) {
+        report_progress(format!("Downloading {}…", executable));
         download_and_cache_binary("binary", &version, binary, &cache_dir).await?;
     }
 
@@ -834,6 +1317,21 @@ async fn resolve_binary(binary_map: &BTreeMap<String, BinaryDistribution>) -> Re
 
 /// Resolve a registry entry's distribution to an McpServer
 pub async fn resolve_distribution(entry: &RegistryEntry) -> Result<Option<McpServer>> {
+    resolve_distribution_as(entry, &entry.id).await
+}
+
+/// Resolve a registry entry's distribution to an McpServer, caching binaries
+/// under `id`: the qualified ID of the entry (see [`qualified_id`]), so that
+/// entries of different registries with the same ID don't share a cache.
+async fn resolve_distribution_as(entry: &RegistryEntry, id: &str) -> Result<Option<McpServer>> {
+    let mut server = distribution_server(entry, id).await?;
+    if let Some(server) = &mut server {
+        offline::forward_to(server);
+    }
+    Ok(server)
+}
+
+async fn distribution_server(entry: &RegistryEntry, id: &str) -> Result<Option<McpServer>> {
     let dist = &entry.distribution;
 
     // Priority: local > npx > pipx > binary
@@ -878,8 +1376,7 @@ pub async fn resolve_distribution(entry: &RegistryEntry) -> Result<Option<McpSer
 
     if let Some(cargo) = &dist.cargo {
         // Query crates.io for version and binary names
-        let (version, bin_names) =
-            query_crate_binaries(&cargo.crate_name, cargo.version.as_deref()).await?;
+        let (version, bin_names) = crate_binaries(id, cargo).await?;
 
         // Determine binary name
         let binary_name = match &cargo.binary {
@@ -899,11 +1396,12 @@ pub async fn resolve_distribution(entry: &RegistryEntry) -> Result<Option<McpSer
             }
         };
 
-        let cache_dir = get_binary_cache_dir(&entry.id, &version)?;
+        let cache_dir = get_binary_cache_dir(id, &version)?;
         let binary_path = cache_dir.join("bin").join(&binary_name);
 
         // Check if we need to install
         if !binary_path.exists() {
+            report_progress(format!("Installing {} {}…", cargo.crate_name, version));
             install_cargo_crate(&cargo.crate_name, &version, &binary_name, &cache_dir).await?;
         }
 
@@ -920,13 +1418,14 @@ pub async fn resolve_distribution(entry: &RegistryEntry) -> Result<Option<McpSer
             } else {
                 &entry.version
             };
-            let cache_dir = get_binary_cache_dir(&entry.id, version)?;
+            let cache_dir = get_binary_cache_dir(id, version)?;
             let executable = binary.cmd.trim_start_matches("./");
             let executable_path = cache_dir.join(executable);
 
             // Check if we need to download
-            if !executable_path.exists() {
-                download_and_cache_binary(&entry.id, version, binary, &cache_dir).await?;
+            if !is_cached_binary_valid(&cache_dir, binary) {
+                report_progress(format!("Downloading {} {}…", entry.name, version));
+                download_and_cache_binary(id, version, binary, &cache_dir).await?;
             }
 
             return Ok(Some(McpServer::Stdio(
@@ -975,6 +1474,8 @@ async fn download_and_cache_binary(
 }
 
 /// Download and cache a binary distribution (blocking implementation)
+///
+/// If anything goes wrong, the partially populated cache directory is removed.
 fn download_and_cache_binary_sync(
     agent_id: &str,
     version: &str,
@@ -982,7 +1483,15 @@ fn download_and_cache_binary_sync(
     cache_dir: &PathBuf,
 ) -> Result<()> {
     use std::fs;
-    use std::io::Write;
+
+    if offline::is_offline() {
+        return Err(offline::not_cached(format!(
+            "the binary for `{}` {} (looked in {})",
+            agent_id,
+            version,
+            cache_dir.display()
+        )));
+    }
 
     // Clean up old versions first
     if let Some(parent) = cache_dir.parent() {
@@ -997,9 +1506,37 @@ fn download_and_cache_binary_sync(
         }
     }
 
-    // Create cache directory
+    // Start from an empty cache directory - anything already there failed verification
+    if cache_dir.exists() {
+        fs::remove_dir_all(cache_dir)?;
+    }
     fs::create_dir_all(cache_dir)?;
 
+    if let Err(e) = download_and_extract(agent_id, binary, cache_dir) {
+        fs::remove_dir_all(cache_dir).ok();
+        return Err(e);
+    }
+
+    tracing::info!(
+        "Downloaded and cached {} v{} to {}",
+        agent_id,
+        version,
+        cache_dir.display()
+    );
+
+    Ok(())
+}
+
+/// Download, verify and extract a binary distribution into `cache_dir`,
+/// recording checksums for later re-verification.
+fn download_and_extract(
+    agent_id: &str,
+    binary: &BinaryDistribution,
+    cache_dir: &PathBuf,
+) -> Result<()> {
+    use std::fs;
+    use std::io::Write;
+
     // Download the binary
     let response = reqwest::blocking::get(&binary.archive)
         .with_context(|| format!("Failed to download binary for {}", agent_id))?;
@@ -1015,6 +1552,9 @@ fn download_and_cache_binary_sync(
 
     let bytes = response.bytes()?;
 
+    // Verify before anything touches the disk
+    let archive_sha256 = verify_archive(binary, &bytes)?;
+
     // Determine filename from URL
     let url = url::Url::parse(&binary.archive)?;
     let filename = url
@@ -1036,12 +1576,13 @@ fn download_and_cache_binary_sync(
         fs::remove_file(&download_path).ok();
     }
 
+    let executable = binary.cmd.trim_start_matches("./");
+    let executable_path = cache_dir.join(executable);
+
     // Make executable on Unix
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        let executable = binary.cmd.trim_start_matches("./");
-        let executable_path = cache_dir.join(executable);
         if executable_path.exists() {
             let mut perms = fs::metadata(&executable_path)?.permissions();
             perms.set_mode(0o755);
@@ -1049,16 +1590,111 @@ fn download_and_cache_binary_sync(
         }
     }
 
-    tracing::info!(
-        "Downloaded and cached {} v{} to {}",
-        agent_id,
-        version,
-        cache_dir.display()
-    );
+    // Record checksums so the cache can be re-verified before reuse
+    if executable_path.exists() {
+        let checksums = CachedChecksums {
+            archive: archive_sha256,
+            executable: sha256_hex(&fs::read(&executable_path)?),
+        };
+        fs::write(
+            cache_dir.join(CHECKSUM_FILE),
+            serde_json::to_string_pretty(&checksums)?,
+        )?;
+    }
 
     Ok(())
 }
 
+/// Checksums recorded alongside a cached binary distribution
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedChecksums {
+    /// SHA-256 of the archive the cache was populated from
+    archive: String,
+    /// SHA-256 of the executable after extraction
+    executable: String,
+}
+
+/// Hex-encoded SHA-256 of some bytes
+fn sha256_hex(bytes: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Check a downloaded archive against the distribution's `sha256` (if any).
+///
+/// Returns the archive's actual SHA-256.
+fn verify_archive(binary: &BinaryDistribution, bytes: &[u8]) -> Result<String> {
+    let actual = sha256_hex(bytes);
+
+    if let Some(expected) = &binary.sha256
+        && !expected.eq_ignore_ascii_case(&actual)
+    {
+        return Err(ChecksumMismatch {
+            archive: binary.archive.clone(),
+            expected: expected.clone(),
+            actual,
+        }
+        .into());
+    }
+
+    Ok(actual)
+}
+
+/// Check that a cached binary exists and still matches the checksums recorded
+/// when it was downloaded (and the distribution's `sha256`, if any).
+fn is_cached_binary_valid(cache_dir: &Path, binary: &BinaryDistribution) -> bool {
+    let executable_path = cache_dir.join(binary.cmd.trim_start_matches("./"));
+    if !executable_path.exists() {
+        return false;
+    }
+
+    let recorded: Option<CachedChecksums> = std::fs::read_to_string(cache_dir.join(CHECKSUM_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok());
+
+    let Some(recorded) = recorded else {
+        // Caches from before checksums were recorded are only trusted if nothing is pinned
+        return binary.sha256.is_none();
+    };
+
+    if let Some(expected) = &binary.sha256
+        && !expected.eq_ignore_ascii_case(&recorded.archive)
+    {
+        tracing::warn!(
+            cache_dir = %cache_dir.display(),
+            "Cached binary was downloaded from a different archive, re-downloading"
+        );
+        return false;
+    }
+
+    match std::fs::read(&executable_path) {
+        Ok(bytes) if sha256_hex(&bytes) == recorded.executable => true,
+        _ => {
+            tracing::warn!(
+                executable = %executable_path.display(),
+                "Cached binary failed checksum verification, re-downloading"
+            );
+            false
+        }
+    }
+}
+
+/// The SHA-256 of the archive a cached binary distribution was extracted from.
+///
+/// Returns `None` if `executable` isn't part of a binary distribution in the cache.
+pub fn cached_archive_sha256(executable: &Path) -> Option<String> {
+    executable.ancestors().skip(1).find_map(|dir| {
+        let recorded = std::fs::read_to_string(dir.join(CHECKSUM_FILE)).ok()?;
+        serde_json::from_str::<CachedChecksums>(&recorded)
+            .ok()
+            .map(|checksums| checksums.archive)
+    })
+}
+
 /// Extract a tar.gz archive
 fn extract_tar_gz(archive_path: &PathBuf, dest_dir: &PathBuf) -> Result<()> {
     use flate2::read::GzDecoder;
@@ -1088,6 +1724,8 @@ fn extract_zip(archive_path: &PathBuf, dest_dir: &PathBuf) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[test]
     fn test_platform_key() {
@@ -1257,4 +1895,423 @@ mod tests {
         // Builtin comes before Cargo, Registry, etc. due to enum variant order
         assert!(matches!(sources[0], ComponentSource::Builtin(_)));
     }
+
+    /// Write a registry with one agent and one mod (both with local distributions)
+    fn write_registry(dir: &Path, file: &str, agent: &str, mod_id: &str) -> RegistryConfig {
+        let path = dir.join(file);
+        std::fs::write(&path, registry_json(agent, mod_id)).unwrap();
+        RegistryConfig {
+            id: file.trim_end_matches(".json").to_string(),
+            location: FileLocation::Path(path),
+            auth_header_env: None,
+        }
+    }
+
+    /// A registry with one agent and one mod (both with local distributions)
+    fn registry_json(agent: &str, mod_id: &str) -> String {
+        let entry = |id: &str| {
+            serde_json::json!({
+                "id": id,
+                "name": format!("{id} name"),
+                "distribution": { "local": { "command": format!("/usr/bin/{id}") } },
+            })
+        };
+        serde_json::json!({
+            "version": "1",
+            "agents": [entry(agent)],
+            "mods": [entry(mod_id)],
+        })
+        .to_string()
+    }
+
+    fn test_registries(
+        cache_dir: &Path,
+        configs: Vec<RegistryConfig>,
+        ttl_secs: u64,
+    ) -> Registries {
+        Registries {
+            configs,
+            cache_dir: cache_dir.to_path_buf(),
+            ttl: Duration::from_secs(ttl_secs),
+        }
+    }
+
+    /// Serve `body` over HTTP with an `ETag`, answering requests that carry
+    /// that ETag with `304 Not Modified`.
+    ///
+    /// Returns the URL, the server task, and the number of full (200) and
+    /// conditional (304) responses sent.
+    async fn serve_registry(
+        body: String,
+    ) -> (
+        String,
+        tokio::task::JoinHandle<()>,
+        Arc<AtomicUsize>,
+        Arc<AtomicUsize>,
+    ) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/registry.json", listener.local_addr().unwrap());
+        let full = Arc::new(AtomicUsize::new(0));
+        let not_modified = Arc::new(AtomicUsize::new(0));
+
+        let (full_count, not_modified_count) = (full.clone(), not_modified.clone());
+        let server = tokio::spawn(async move {
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+
+                let request = String::from_utf8_lossy(&request).to_lowercase();
+                let response = if request.contains("if-none-match: \"v1\"") {
+                    not_modified_count.fetch_add(1, Ordering::SeqCst);
+                    "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n"
+                        .to_string()
+                } else {
+                    full_count.fetch_add(1, Ordering::SeqCst);
+                    format!(
+                        "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                };
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        (url, server, full, not_modified)
+    }
+
+    #[tokio::test]
+    async fn test_registry_cache_revalidates_with_etag() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (url, server, full, not_modified) =
+            serve_registry(registry_json("our-agent", "our-mod")).await;
+        let config = RegistryConfig {
+            id: "acme".to_string(),
+            location: FileLocation::Url(url),
+            auth_header_env: None,
+        };
+        let counts = || {
+            (
+                full.load(Ordering::SeqCst),
+                not_modified.load(Ordering::SeqCst),
+            )
+        };
+
+        // With a TTL of 0, every fetch revalidates the cache
+        let registries = test_registries(temp_dir.path(), vec![config.clone()], 0);
+        let registry = registries.fetch(&config).await.unwrap();
+        assert_eq!(registry.agents[0].id, "our-agent");
+        assert_eq!(counts(), (1, 0));
+        assert!(temp_dir.path().join("acme.json").exists());
+
+        let registry = registries.fetch(&config).await.unwrap();
+        assert_eq!(registry.agents[0].id, "our-agent");
+        assert_eq!(counts(), (1, 1));
+
+        // Within the TTL, the cache is used without any request
+        let cached = test_registries(temp_dir.path(), vec![config.clone()], 3600);
+        cached.fetch(&config).await.unwrap();
+        assert_eq!(counts(), (1, 1));
+
+        // When the server is gone, the cache is used even though it is stale
+        server.abort();
+        let _ = server.await;
+        let registry = registries.fetch(&config).await.unwrap();
+        assert_eq!(registry.mods[0].id, "our-mod");
+
+        // ...but without a cache, the failure is reported
+        std::fs::remove_file(temp_dir.path().join("acme.json")).unwrap();
+        assert!(registries.fetch(&config).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_progress_reported_within_scope() {
+        let messages = Arc::new(std::sync::Mutex::new(vec![]));
+        let sink: ProgressSink = {
+            let messages = messages.clone();
+            Arc::new(move |message| messages.lock().unwrap().push(message))
+        };
+
+        with_progress(sink, async {
+            report_progress("Installing sparkle-mcp 0.5.0…".to_string());
+        })
+        .await;
+        report_progress("Installing elsewhere…".to_string());
+
+        assert_eq!(*messages.lock().unwrap(), ["Installing sparkle-mcp 0.5.0…"]);
+    }
+
+    #[test]
+    fn test_qualified_id() {
+        assert_eq!(qualified_id("public", "claude-code"), "claude-code");
+        assert_eq!(qualified_id("acme", "our-agent"), "acme:our-agent");
+        assert_eq!(split_qualified_id("claude-code"), ("public", "claude-code"));
+        assert_eq!(split_qualified_id("acme:our-agent"), ("acme", "our-agent"));
+    }
+
+    #[tokio::test]
+    async fn test_multiple_registries() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let configs = vec![
+            write_registry(temp_dir.path(), "acme.json", "our-agent", "our-mod"),
+            write_registry(temp_dir.path(), "team.json", "our-agent", "team-mod"),
+            RegistryConfig {
+                id: "broken".to_string(),
+                location: FileLocation::Path(temp_dir.path().join("missing.json")),
+                auth_header_env: None,
+            },
+        ];
+        let registries = test_registries(&temp_dir.path().join("cache"), configs, 0);
+
+        // Agents are merged (after the built-ins) and namespaced per registry
+        let agents: Vec<_> = list_agents_from(&registries)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|(entry, source)| Some((entry.registry?, entry.id, source)))
+            .collect();
+        expect_test::expect![[r#"
+            [
+                (
+                    "acme",
+                    "acme:our-agent",
+                    Local(
+                        LocalDistribution {
+                            command: "/usr/bin/our-agent",
+                            args: [],
+                            name: None,
+                            env: {},
+                        },
+                    ),
+                ),
+                (
+                    "team",
+                    "team:our-agent",
+                    Local(
+                        LocalDistribution {
+                            command: "/usr/bin/our-agent",
+                            args: [],
+                            name: None,
+                            env: {},
+                        },
+                    ),
+                ),
+            ]
+        "#]]
+        .assert_debug_eq(&agents);
+
+        let mods = list_mods_from(&registries).await.unwrap();
+        let mods: Vec<_> = mods.iter().map(|m| (&m.registry, &m.id)).collect();
+        expect_test::expect![[r#"
+            [
+                (
+                    "acme",
+                    "acme:our-mod",
+                ),
+                (
+                    "team",
+                    "team:team-mod",
+                ),
+            ]
+        "#]]
+        .assert_debug_eq(&mods);
+
+        // Lookups only search the registry named by the prefix
+        let entry = registries
+            .find_entry("team:team-mod", EntryKind::Mod)
+            .await
+            .unwrap();
+        assert_eq!(entry.id, "team-mod");
+        assert!(
+            registries
+                .find_entry("acme:team-mod", EntryKind::Any)
+                .await
+                .is_err()
+        );
+        assert!(
+            registries
+                .find_entry("acme:our-mod", EntryKind::Agent)
+                .await
+                .is_err()
+        );
+        assert!(
+            registries
+                .find_entry("other:our-mod", EntryKind::Any)
+                .await
+                .is_err()
+        );
+    }
+
+    fn binary_distribution(archive: &str, sha256: Option<&str>) -> BinaryDistribution {
+        BinaryDistribution {
+            archive: archive.to_string(),
+            cmd: "./my-mod".to_string(),
+            args: vec![],
+            sha256: sha256.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_binary_distribution_sha256_deserialize() {
+        let json = r#"{"archive": "https://example.com/my-mod.tar.gz", "cmd": "./my-mod", "sha256": "abc123"}"#;
+        let binary: BinaryDistribution = serde_json::from_str(json).unwrap();
+        assert_eq!(binary.sha256.as_deref(), Some("abc123"));
+
+        // Optional, and omitted when absent
+        let binary = binary_distribution("https://example.com/my-mod.tar.gz", None);
+        assert!(!serde_json::to_string(&binary).unwrap().contains("sha256"));
+    }
+
+    #[test]
+    fn test_verify_archive() {
+        let bytes = b"archive contents";
+        let actual = sha256_hex(bytes);
+
+        // Unpinned and matching (case-insensitive) archives pass
+        let unpinned = binary_distribution("https://example.com/my-mod.tar.gz", None);
+        assert_eq!(verify_archive(&unpinned, bytes).unwrap(), actual);
+        let pinned = binary_distribution(
+            "https://example.com/my-mod.tar.gz",
+            Some(&actual.to_uppercase()),
+        );
+        assert_eq!(verify_archive(&pinned, bytes).unwrap(), actual);
+
+        // A mismatch is reported as a distinct error
+        let wrong = binary_distribution("https://example.com/my-mod.tar.gz", Some("deadbeef"));
+        let err = verify_archive(&wrong, bytes).unwrap_err();
+        let mismatch = err
+            .downcast_ref::<ChecksumMismatch>()
+            .expect("should be a checksum mismatch");
+        assert_eq!(mismatch.expected, "deadbeef");
+        assert_eq!(mismatch.actual, actual);
+    }
+
+    #[test]
+    fn test_cached_binary_reverified() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_dir = temp_dir.path();
+        let archive_sha256 = sha256_hex(b"archive contents");
+        let unpinned = binary_distribution("https://example.com/my-mod.tar.gz", None);
+        let pinned =
+            binary_distribution("https://example.com/my-mod.tar.gz", Some(&archive_sha256));
+
+        // Nothing cached
+        assert!(!is_cached_binary_valid(cache_dir, &unpinned));
+
+        // Cached without recorded checksums: only trusted when unpinned
+        std::fs::write(cache_dir.join("my-mod"), "#!/bin/sh").unwrap();
+        assert!(is_cached_binary_valid(cache_dir, &unpinned));
+        assert!(!is_cached_binary_valid(cache_dir, &pinned));
+
+        // Cached with recorded checksums
+        let checksums = CachedChecksums {
+            archive: archive_sha256.clone(),
+            executable: sha256_hex(b"#!/bin/sh"),
+        };
+        std::fs::write(
+            cache_dir.join(CHECKSUM_FILE),
+            serde_json::to_string(&checksums).unwrap(),
+        )
+        .unwrap();
+        assert!(is_cached_binary_valid(cache_dir, &unpinned));
+        assert!(is_cached_binary_valid(cache_dir, &pinned));
+
+        // Pin changed since download
+        let repinned = binary_distribution("https://example.com/my-mod.tar.gz", Some("deadbeef"));
+        assert!(!is_cached_binary_valid(cache_dir, &repinned));
+
+        // Executable modified since download
+        std::fs::write(cache_dir.join("my-mod"), "#!/bin/sh\necho tampered").unwrap();
+        assert!(!is_cached_binary_valid(cache_dir, &unpinned));
+    }
+
+    #[test]
+    fn test_failed_download_cleans_up_cache_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_dir = temp_dir.path().join("my-mod").join("1.0.0");
+
+        // Nothing listens on port 1, so the download fails
+        let binary = binary_distribution("http://127.0.0.1:1/my-mod.tar.gz", None);
+        let result = download_and_cache_binary_sync("my-mod", "1.0.0", &binary, &cache_dir);
+
+        assert!(result.is_err());
+        assert!(!cache_dir.exists(), "partial cache dir should be removed");
+    }
+
+    #[test]
+    fn test_binary_cache_per_registry() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_paths = ConfigPaths::with_root(temp_dir.path());
+
+        // The public `our-agent` is cached...
+        let public_dir = config_paths.binary_cache_dir("our-agent", "1.0.0");
+        std::fs::create_dir_all(&public_dir).unwrap();
+
+        // ...apart from `acme:our-agent`
+        let acme_dir = config_paths.binary_cache_dir(&qualified_id("acme", "our-agent"), "2.0.0");
+        assert_ne!(public_dir.parent(), acme_dir.parent());
+
+        // so downloading one doesn't clean up the other's versions
+        let binary = binary_distribution("http://127.0.0.1:1/our-agent.tar.gz", None);
+        let result = download_and_cache_binary_sync("acme:our-agent", "2.0.0", &binary, &acme_dir);
+        assert!(result.is_err());
+        assert!(public_dir.exists());
+    }
+
+    #[test]
+    fn test_cached_crate_binaries() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let versions_dir = temp_dir.path();
+        for (version, binary) in [
+            ("0.4.0", "sparkle-mcp"),
+            ("0.5.0", "sparkle-mcp"),
+            ("0.6.0", ""),
+        ] {
+            let bin_dir = versions_dir.join(version).join("bin");
+            std::fs::create_dir_all(&bin_dir).unwrap();
+            if !binary.is_empty() {
+                std::fs::write(bin_dir.join(binary), "").unwrap();
+            }
+        }
+
+        let cargo = |version: Option<&str>, binary: Option<&str>| CargoDistribution {
+            crate_name: "sparkle-mcp".to_string(),
+            version: version.map(str::to_string),
+            binary: binary.map(str::to_string),
+            args: vec![],
+        };
+
+        // Newest version that actually has a binary installed
+        let (version, bin_names) = cached_crate_binaries(versions_dir, &cargo(None, None)).unwrap();
+        assert_eq!(version, "0.5.0");
+        assert_eq!(bin_names, vec!["sparkle-mcp"]);
+
+        // Pinned version
+        let (version, _) =
+            cached_crate_binaries(versions_dir, &cargo(Some("0.4.0"), None)).unwrap();
+        assert_eq!(version, "0.4.0");
+
+        // Not cached, but fully pinned: can still try the cargo registry cache
+        let (version, bin_names) =
+            cached_crate_binaries(versions_dir, &cargo(Some("0.7.0"), Some("sparkle-mcp")))
+                .unwrap();
+        assert_eq!(version, "0.7.0");
+        assert_eq!(bin_names, vec!["sparkle-mcp"]);
+
+        // Not cached and not pinned
+        let err = cached_crate_binaries(versions_dir, &cargo(None, Some("other"))).unwrap_err();
+        assert!(err.to_string().contains("not cached"), "{err}");
+    }
 }
@@ -1,30 +1,26 @@
 //! Remote recommendations fetching and caching.
 //!
 //! This module handles:
-//! - Fetching recommendations from the remote URL
-//! - Caching the result locally
+//! - Fetching recommendations from the configured recommendation sources
+//!   (see [`UserSettings::recommendation_sources`])
+//! - Caching each source locally
 //! - Loading local user recommendations
 //! - Loading workspace-specific recommendations
 //! - Merging all recommendation sources
 
-use crate::user_config::ConfigPaths;
+use crate::offline;
+use crate::user_config::{ConfigPaths, FileLocation, RecommendationSourceConfig, UserSettings};
 use anyhow::{Context, Result, bail};
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use symposium_recommendations::{Recommendation, Recommendations};
 
-/// URL for the remote recommendations file.
-const REMOTE_RECOMMENDATIONS_URL: &str =
-    "http://recommendations.symposium.dev/recommendations.toml";
-
-/// Filename for cached remote recommendations.
-const CACHED_RECOMMENDATIONS_FILENAME: &str = "recommendations.toml";
-
 /// Filename for user's local recommendations.
 const LOCAL_RECOMMENDATIONS_FILENAME: &str = "recommendations.toml";
 
 /// Directory for workspace-specific symposium config.
-const WORKSPACE_SYMPOSIUM_DIR: &str = ".symposium";
+pub(crate) const WORKSPACE_SYMPOSIUM_DIR: &str = ".symposium";
 
 /// Filename for workspace-specific recommendations.
 const WORKSPACE_RECOMMENDATIONS_FILENAME: &str = "recommendations.toml";
@@ -34,60 +30,146 @@ const HTTP_TIMEOUT_SECS: u64 = 30;
 
 /// Load all recommendations from all sources, merging them together.
 ///
-/// Sources (in order, all are merged):
-/// 1. Remote recommendations (downloaded and cached)
+/// Sources (all are merged):
+/// 1. The recommendation sources from the user settings (downloaded and
+///    cached), highest priority first
 /// 2. User's local recommendations file
 ///
-/// If the remote cannot be fetched, falls back to cached version.
-/// If no recommendations can be loaded at all, returns an error.
+/// See [`merge_recommendations`] for how duplicates are resolved.
+///
+/// A source that cannot be loaded falls back to its cached version, and is
+/// skipped with a warning if there is none. If no source can be loaded at
+/// all, returns an error.
 pub async fn load_recommendations(config_paths: &ConfigPaths) -> Result<Recommendations> {
-    // 1. Try to load remote recommendations (with caching fallback)
-    let mut combined = load_remote_with_cache(config_paths).await?;
-
-    tracing::debug!(?combined);
+    // 1. Load each configured source (with caching fallback)
+    let remote = load_remote_recommendations(config_paths).await?;
 
     // 2. Load user's local recommendations if present and merge
-    if let Some(local_recs) = load_local_recommendations(config_paths).await? {
-        tracing::debug!(?local_recs);
-        combined.mods.extend(local_recs.mods);
-    }
+    let local = load_local_recommendations(config_paths).await?;
+    tracing::debug!(?local);
+
+    let combined = merge_recommendations(remote, local);
 
     tracing::debug!(?combined);
 
     Ok(combined)
 }
 
-/// Fetch remote recommendations and cache them, or fall back to cache.
+/// Load the recommendation sources from the user settings, highest priority
+/// first, without the user's local recommendations.
+///
+/// See [`load_recommendations`] for how failing sources are handled.
+pub async fn load_remote_recommendations(
+    config_paths: &ConfigPaths,
+) -> Result<Vec<Recommendations>> {
+    let settings = UserSettings::load(config_paths)?;
+
+    let mut sources = settings.recommendation_sources;
+    sources.sort_by_key(|source| std::cmp::Reverse(source.priority));
+
+    let mut remote = Vec::with_capacity(sources.len());
+    let mut errors = Vec::new();
+    for source in &sources {
+        match load_source_with_cache(config_paths, source).await {
+            Ok(recs) => {
+                tracing::debug!(source = %source.name, ?recs);
+                remote.push(recs);
+            }
+            Err(e) => {
+                tracing::warn!("Skipping recommendation source `{}`: {:#}", source.name, e);
+                errors.push(format!("`{}`: {:#}", source.name, e));
+            }
+        }
+    }
+
+    if remote.is_empty() && !errors.is_empty() {
+        bail!(
+            "Cannot load recommendations from any source:\n{}",
+            errors.join("\n")
+        );
+    }
+
+    Ok(remote)
+}
+
+/// Merge recommendation sets, de-duplicating by `ComponentSource`.
+///
+/// `remote` is in priority order: when two remote sets recommend the same
+/// mod, the first one wins. A local recommendation replaces a remote one for
+/// the same mod (keeping its position), so users can override the conditions
+/// under which a published mod is recommended.
+pub fn merge_recommendations(
+    remote: Vec<Recommendations>,
+    local: Option<Recommendations>,
+) -> Recommendations {
+    let mut mods: Vec<Recommendation> = Vec::new();
+    let mut positions = HashMap::new();
+
+    for recommendation in remote.into_iter().flat_map(|recs| recs.mods) {
+        if !positions.contains_key(&recommendation.source) {
+            positions.insert(recommendation.source.clone(), mods.len());
+            mods.push(recommendation);
+        }
+    }
+
+    for recommendation in local.into_iter().flat_map(|recs| recs.mods) {
+        match positions.get(&recommendation.source) {
+            Some(&index) => mods[index] = recommendation,
+            None => {
+                positions.insert(recommendation.source.clone(), mods.len());
+                mods.push(recommendation);
+            }
+        }
+    }
+
+    Recommendations { mods }
+}
+
+/// Load one recommendation source and cache it, or fall back to its cache.
+///
+/// In offline mode, URL sources only consult the cache.
 ///
 /// Returns an error if:
-/// - Remote fetch fails AND no cached version exists
-async fn load_remote_with_cache(config_paths: &ConfigPaths) -> Result<Recommendations> {
-    // Try to fetch from remote
-    match fetch_remote_recommendations().await {
+/// - Loading the source fails AND no cached version exists
+async fn load_source_with_cache(
+    config_paths: &ConfigPaths,
+    source: &RecommendationSourceConfig,
+) -> Result<Recommendations> {
+    let cache_path = config_paths.recommendations_cache_path(&source.name);
+    let cached = config_paths.cached_recommendations_path(&source.name);
+
+    if offline::is_offline() && matches!(source.location, FileLocation::Url(_)) {
+        let Some(cached) = cached else {
+            return Err(offline::not_cached(format!(
+                "the recommendations from `{}` (expected at {})",
+                source.name,
+                cache_path.display()
+            )));
+        };
+        return load_cached_recommendations(&cached);
+    }
+
+    match fetch_source(source).await {
         Ok((toml_content, recommendations)) => {
             // Successfully fetched - cache the raw TOML string
-            if let Err(e) = cache_recommendations(config_paths, &toml_content).await {
+            if let Err(e) = cache_recommendations(config_paths, &source.name, &toml_content).await {
                 tracing::warn!("Failed to cache recommendations: {}", e);
             }
             Ok(recommendations)
         }
         Err(fetch_error) => {
-            tracing::warn!("Failed to fetch remote recommendations: {}", fetch_error);
-
-            let cache_path = config_paths
-                .cache_dir()
-                .join(CACHED_RECOMMENDATIONS_FILENAME);
+            tracing::warn!(
+                "Failed to load recommendations from `{}`: {:#}",
+                source.name,
+                fetch_error
+            );
 
             // Try to load from cache
-            if cache_path.exists() {
-                tracing::info!("Using cached recommendations from {}", cache_path.display());
-                let cached_toml = std::fs::read_to_string(&cache_path)
-                    .context("Failed to read cached recommendations")?;
-                Recommendations::from_toml(&cached_toml)
-                    .context("Failed to parse cached recommendations")
+            if let Some(cached) = cached {
+                load_cached_recommendations(&cached)
             } else {
                 bail!(
-                    "Cannot load recommendations: remote fetch failed ({}) and no cache exists at {}",
+                    "Cannot load recommendations: fetch failed ({:#}) and no cache exists at {}",
                     fetch_error,
                     cache_path.display()
                 )
@@ -96,22 +178,53 @@ async fn load_remote_with_cache(config_paths: &ConfigPaths) -> Result<Recommenda
     }
 }
 
-/// Fetch recommendations from the remote URL.
+/// Load previously cached recommendations.
+fn load_cached_recommendations(cache_path: &Path) -> Result<Recommendations> {
+    tracing::info!("Using cached recommendations from {}", cache_path.display());
+    let cached_toml =
+        std::fs::read_to_string(cache_path).context("Failed to read cached recommendations")?;
+    Recommendations::from_toml(&cached_toml).context("Failed to parse cached recommendations")
+}
+
+/// Load recommendations from a source's URL or path.
 ///
 /// Returns both the raw TOML string (for caching) and the parsed recommendations.
-async fn fetch_remote_recommendations() -> Result<(String, Recommendations)> {
-    tracing::debug!(
-        "Fetching recommendations from {}",
-        REMOTE_RECOMMENDATIONS_URL
-    );
+async fn fetch_source(source: &RecommendationSourceConfig) -> Result<(String, Recommendations)> {
+    let content = match &source.location {
+        FileLocation::Url(url) => fetch_url(url, source.auth_header_env.as_deref()).await?,
+        FileLocation::Path(path) => tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read recommendations from {}", path.display()))?,
+    };
+
+    // Parse and validate
+    let recommendations =
+        Recommendations::from_toml(&content).context("Recommendations failed to parse")?;
+
+    Ok((content, recommendations))
+}
+
+/// Fetch the contents of a recommendations file over HTTP.
+///
+/// If `auth_header_env` is given, the value of that environment variable is
+/// sent as the `Authorization` header.
+async fn fetch_url(url: &str, auth_header_env: Option<&str>) -> Result<String> {
+    tracing::debug!("Fetching recommendations from {}", url);
 
     let client = reqwest::Client::builder()
         .timeout(Duration::from_secs(HTTP_TIMEOUT_SECS))
         .build()
         .context("Failed to create HTTP client")?;
 
-    let response = client
-        .get(REMOTE_RECOMMENDATIONS_URL)
+    let mut request = client.get(url);
+    if let Some(var) = auth_header_env {
+        let value = std::env::var(var).with_context(|| {
+            format!("Environment variable `{var}` for the auth header is not set")
+        })?;
+        request = request.header(reqwest::header::AUTHORIZATION, value);
+    }
+
+    let response = request
         .send()
         .await
         .context("Failed to connect to recommendations server")?;
@@ -124,24 +237,21 @@ async fn fetch_remote_recommendations() -> Result<(String, Recommendations)> {
         );
     }
 
-    let content = response
+    response
         .text()
         .await
-        .context("Failed to read recommendations response")?;
-
-    // Parse and validate
-    let recommendations =
-        Recommendations::from_toml(&content).context("Remote recommendations failed to parse")?;
-
-    Ok((content, recommendations))
+        .context("Failed to read recommendations response")
 }
 
-/// Cache recommendations to disk.
-async fn cache_recommendations(config_paths: &ConfigPaths, content: &str) -> Result<()> {
-    config_paths.ensure_cache_dir().await?;
+/// Cache a source's recommendations to disk.
+async fn cache_recommendations(
+    config_paths: &ConfigPaths,
+    source_name: &str,
+    content: &str,
+) -> Result<()> {
     let cache_path = config_paths
-        .cache_dir()
-        .join(CACHED_RECOMMENDATIONS_FILENAME);
+        .ensure_recommendations_cache_path(source_name)
+        .await?;
 
     tokio::fs::write(&cache_path, content)
         .await
@@ -216,6 +326,13 @@ pub async fn save_local_recommendations(
     Ok(())
 }
 
+/// Path of a workspace's own recommendations file.
+pub fn workspace_recommendations_path(workspace_path: &Path) -> PathBuf {
+    workspace_path
+        .join(WORKSPACE_SYMPOSIUM_DIR)
+        .join(WORKSPACE_RECOMMENDATIONS_FILENAME)
+}
+
 /// Load workspace-specific recommendations if they exist.
 ///
 /// Location: `<workspace>/.symposium/recommendations.toml`
@@ -223,9 +340,7 @@ pub async fn save_local_recommendations(
 /// This allows projects to declare their own recommended mods that should
 /// be suggested when working in that workspace.
 pub fn load_workspace_recommendations(workspace_path: &Path) -> Result<Option<Recommendations>> {
-    let workspace_recs_path = workspace_path
-        .join(WORKSPACE_SYMPOSIUM_DIR)
-        .join(WORKSPACE_RECOMMENDATIONS_FILENAME);
+    let workspace_recs_path = workspace_recommendations_path(workspace_path);
 
     if !workspace_recs_path.exists() {
         return Ok(None);
@@ -256,6 +371,7 @@ pub fn load_workspace_recommendations(workspace_path: &Path) -> Result<Option<Re
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::user_config::DEFAULT_RECOMMENDATION_SOURCE_NAME;
 
     fn init_tracing() {
         let _ = tracing_subscriber::fmt()
@@ -338,9 +454,8 @@ source.builtin = "test-local-mod"
         assert!(!recs.mods.is_empty(), "Should have loaded recommendations");
 
         // Verify cache file was created
-        let cache_path = config_paths
-            .cache_dir()
-            .join(CACHED_RECOMMENDATIONS_FILENAME);
+        let cache_path =
+            config_paths.recommendations_cache_path(DEFAULT_RECOMMENDATION_SOURCE_NAME);
         assert!(cache_path.exists(), "Cache file should exist");
 
         // Cache should be valid TOML
@@ -348,16 +463,47 @@ source.builtin = "test-local-mod"
         Recommendations::from_toml(&cache_content).expect("Cache should be valid TOML");
     }
 
+    /// Write `recommendation-sources` to the user settings
+    fn write_sources(config_paths: &ConfigPaths, sources: serde_json::Value) {
+        let path = config_paths.user_settings_path();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let settings = serde_json::json!({ "recommendation-sources": sources });
+        std::fs::write(path, settings.to_string()).unwrap();
+    }
+
+    fn recommendations(toml: &str) -> Recommendations {
+        Recommendations::from_toml(toml).unwrap()
+    }
+
+    fn describe(recs: &Recommendations) -> Vec<String> {
+        recs.mods
+            .iter()
+            .map(|r| match &r.when {
+                Some(when) => format!("{} when {:?}", r.display_name(), when.file_exists),
+                None => r.display_name(),
+            })
+            .collect()
+    }
+
     #[tokio::test]
     async fn test_cache_fallback_with_invalid_url() {
         let temp_dir = tempfile::tempdir().unwrap();
         let config_paths = ConfigPaths::with_root(temp_dir.path());
 
+        // Nothing listens on the discard port, so fetching fails immediately
+        write_sources(
+            &config_paths,
+            serde_json::json!([{ "name": "unreachable", "url": "http://127.0.0.1:9/recs.toml" }]),
+        );
+
+        // Without a cache, there is nothing to load
+        assert!(load_recommendations(&config_paths).await.is_err());
+
         // Pre-populate cache
-        config_paths.ensure_cache_dir().await.unwrap();
         let cache_path = config_paths
-            .cache_dir()
-            .join(CACHED_RECOMMENDATIONS_FILENAME);
+            .ensure_recommendations_cache_path("unreachable")
+            .await
+            .unwrap();
         std::fs::write(
             &cache_path,
             r#"
@@ -367,12 +513,148 @@ source.builtin = "cached-mod"
         )
         .unwrap();
 
-        // The load_remote_with_cache function should use cache if remote fails
-        // We can't easily test this without mocking, but at least verify cache is read
-        let cache_content = std::fs::read_to_string(&cache_path).unwrap();
-        let recs = Recommendations::from_toml(&cache_content).unwrap();
-        assert_eq!(recs.mods.len(), 1);
-        assert_eq!(recs.mods[0].display_name(), "cached-mod");
+        let recs = load_recommendations(&config_paths).await.unwrap();
+        assert_eq!(describe(&recs), ["cached-mod"]);
+    }
+
+    #[tokio::test]
+    async fn test_cache_fallback_to_legacy_cache() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_paths = ConfigPaths::with_root(temp_dir.path());
+
+        write_sources(
+            &config_paths,
+            serde_json::json!([
+                { "name": DEFAULT_RECOMMENDATION_SOURCE_NAME, "url": "http://127.0.0.1:9/recs.toml" },
+                { "name": "other", "url": "http://127.0.0.1:9/other.toml" },
+            ]),
+        );
+
+        // The cache written before there were several sources
+        std::fs::create_dir_all(config_paths.cache_dir()).unwrap();
+        std::fs::write(
+            config_paths.cache_dir().join("recommendations.toml"),
+            r#"
+[[recommendation]]
+source.builtin = "legacy-mod"
+"#,
+        )
+        .unwrap();
+
+        // It belongs to the default source only
+        assert!(
+            config_paths
+                .cached_recommendations_path(DEFAULT_RECOMMENDATION_SOURCE_NAME)
+                .is_some()
+        );
+        assert!(config_paths.cached_recommendations_path("other").is_none());
+
+        let recs = load_recommendations(&config_paths).await.unwrap();
+        assert_eq!(describe(&recs), ["legacy-mod"]);
+    }
+
+    #[tokio::test]
+    async fn test_load_recommendations_from_multiple_sources() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_paths = ConfigPaths::with_root(temp_dir.path());
+
+        let low = temp_dir.path().join("low.toml");
+        std::fs::write(
+            &low,
+            r#"
+[[recommendation]]
+source.builtin = "shared-mod"
+when.file-exists = "low.txt"
+
+[[recommendation]]
+source.builtin = "low-mod"
+"#,
+        )
+        .unwrap();
+        let high = temp_dir.path().join("high.toml");
+        std::fs::write(
+            &high,
+            r#"
+[[recommendation]]
+source.builtin = "shared-mod"
+when.file-exists = "high.txt"
+"#,
+        )
+        .unwrap();
+
+        write_sources(
+            &config_paths,
+            serde_json::json!([
+                { "name": "low", "path": low, "priority": -1 },
+                { "name": "broken", "path": temp_dir.path().join("missing.toml") },
+                { "name": "high", "path": high, "priority": 5 },
+            ]),
+        );
+
+        // The broken source is skipped, and the higher priority source wins
+        let recs = load_recommendations(&config_paths).await.unwrap();
+        assert_eq!(
+            describe(&recs),
+            ["shared-mod when Some(\"high.txt\")", "low-mod"]
+        );
+
+        // Each loaded source got its own cache file
+        assert!(config_paths.recommendations_cache_path("low").exists());
+        assert!(config_paths.recommendations_cache_path("high").exists());
+        assert!(!config_paths.recommendations_cache_path("broken").exists());
+
+        // Loading falls back to the cache once the file is gone
+        std::fs::remove_file(&high).unwrap();
+        let recs = load_recommendations(&config_paths).await.unwrap();
+        assert_eq!(
+            describe(&recs),
+            ["shared-mod when Some(\"high.txt\")", "low-mod"]
+        );
+    }
+
+    #[test]
+    fn test_merge_recommendations() {
+        let first = recommendations(
+            r#"
+[[recommendation]]
+source.builtin = "a"
+when.file-exists = "first"
+
+[[recommendation]]
+source.builtin = "b"
+"#,
+        );
+        let second = recommendations(
+            r#"
+[[recommendation]]
+source.builtin = "c"
+
+[[recommendation]]
+source.builtin = "a"
+when.file-exists = "second"
+"#,
+        );
+        let local = recommendations(
+            r#"
+[[recommendation]]
+source.builtin = "d"
+
+[[recommendation]]
+source.builtin = "b"
+when.file-exists = "local"
+"#,
+        );
+
+        let merged = merge_recommendations(vec![first, second], Some(local));
+        expect_test::expect![[r#"
+            [
+                "a when Some(\"first\")",
+                "b when Some(\"local\")",
+                "c",
+                "d",
+            ]
+        "#]]
+        .assert_debug_eq(&describe(&merged));
     }
 
     #[test]
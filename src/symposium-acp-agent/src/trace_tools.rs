@@ -0,0 +1,811 @@
+//! Inspecting and replaying conductor traces.
+//!
+//! Conductors started with `--trace-dir` record every message they route as a
+//! `.jsons` file, one [`TraceEvent`] per line. The `trace` subcommand uses this
+//! module to print a trace's message flow, to narrow it down to a session,
+//! method or component, and to [`Replay`] the client's side of it against an
+//! agent chain, so a problem seen in someone else's trace can be reproduced
+//! locally.
+
+use anyhow::{Context, Result, bail};
+use futures::StreamExt;
+use futures::future::Either;
+use fxhash::{FxHashMap, FxHashSet};
+use sacp::jsonrpcmsg::{self, Id, Message, Params, Request, Response};
+use sacp::link::AgentToClient;
+use sacp::schema::{InitializeRequest, ProtocolVersion};
+use sacp::{Channel, Component};
+use sacp_conductor::trace::{NotificationEvent, Protocol, RequestEvent, ResponseEvent, TraceEvent};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// The component that starts the chain, as named in traces
+const CLIENT: &str = "client";
+
+/// The component a replay talks to, as named in its output
+const AGENT: &str = "agent";
+
+/// Longest params or payload summary `show` prints on one line
+const SUMMARY_LEN: usize = 100;
+
+/// JSON-RPC "internal error" code, for agent requests the trace can't answer
+const INTERNAL_ERROR_CODE: i32 = -32603;
+
+/// Read the events of a `.jsons` trace file.
+pub fn read_trace(path: &Path) -> Result<Vec<TraceEvent>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    parse_trace(&text).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+fn parse_trace(text: &str) -> Result<Vec<TraceEvent>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(n, line)| serde_json::from_str(line).with_context(|| format!("line {}", n + 1)))
+        .collect()
+}
+
+/// Write events in the `.jsons` format.
+pub fn write_trace<'a>(
+    events: impl IntoIterator<Item = &'a ResolvedEvent>,
+    out: &mut dyn Write,
+) -> Result<()> {
+    for event in events {
+        serde_json::to_writer(&mut *out, &event.event)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Identifies a request by its sender, receiver and ID.
+///
+/// A response goes back the way its request came, so it has the same key with
+/// `from` and `to` swapped.
+pub(crate) fn request_key(from: &str, to: &str, id: &serde_json::Value) -> String {
+    format!("{from}\n{to}\n{id}")
+}
+
+/// A trace event with the method and session it belongs to.
+///
+/// Responses carry neither, so they take them from the request they answer.
+/// A `session/new` request takes its session from its response.
+#[derive(Debug, Clone)]
+pub struct ResolvedEvent {
+    pub event: TraceEvent,
+    pub method: Option<String>,
+    pub session: Option<String>,
+}
+
+/// Attach methods and sessions to the events of a trace.
+pub fn resolve(events: Vec<TraceEvent>) -> Vec<ResolvedEvent> {
+    let mut resolved: Vec<ResolvedEvent> = events
+        .into_iter()
+        .map(|event| {
+            let (method, session) = match &event {
+                TraceEvent::Request(request) => {
+                    (Some(request.method.clone()), request.session.clone())
+                }
+                TraceEvent::Notification(notification) => (
+                    Some(notification.method.clone()),
+                    notification.session.clone(),
+                ),
+                TraceEvent::Response(_) | TraceEvent::Trace(_) => (None, None),
+            };
+            ResolvedEvent {
+                event,
+                method,
+                session,
+            }
+        })
+        .collect();
+
+    // Index of each request awaiting its response, by `request_key`
+    let mut requests: FxHashMap<String, usize> = FxHashMap::default();
+    for index in 0..resolved.len() {
+        match &resolved[index].event {
+            TraceEvent::Request(request) => {
+                let key = request_key(&request.from, &request.to, &request.id);
+                requests.insert(key, index);
+            }
+            TraceEvent::Response(response) => {
+                let key = request_key(&response.to, &response.from, &response.id);
+                let Some(request) = requests.remove(&key) else {
+                    continue;
+                };
+                let created = response
+                    .payload
+                    .get("sessionId")
+                    .and_then(|id| id.as_str())
+                    .map(str::to_string);
+                let request = &mut resolved[request];
+                if request.session.is_none() && request.method.as_deref() == Some("session/new") {
+                    request.session = created;
+                }
+                let (method, session) = (request.method.clone(), request.session.clone());
+                resolved[index].method = method;
+                resolved[index].session = session;
+            }
+            TraceEvent::Notification(_) | TraceEvent::Trace(_) => {}
+        }
+    }
+
+    resolved
+}
+
+/// Selects the events of a trace; every criterion that is set must match.
+#[derive(Debug, Default, Clone)]
+pub struct TraceFilter {
+    /// ACP session ID
+    pub session: Option<String>,
+    /// Method name; a trailing `*` matches any method with that prefix
+    pub method: Option<String>,
+    /// Component the message comes from or goes to (`client`, `proxy:0`, `agent`, ...)
+    pub component: Option<String>,
+}
+
+impl TraceFilter {
+    pub fn matches(&self, event: &ResolvedEvent) -> bool {
+        if let Some(session) = &self.session
+            && event.session.as_ref() != Some(session)
+        {
+            return false;
+        }
+
+        if let Some(pattern) = &self.method {
+            let Some(method) = &event.method else {
+                return false;
+            };
+            let matched = match pattern.strip_suffix('*') {
+                Some(prefix) => method.starts_with(prefix),
+                None => method == pattern,
+            };
+            if !matched {
+                return false;
+            }
+        }
+
+        if let Some(component) = &self.component {
+            let involved = match &event.event {
+                TraceEvent::Request(RequestEvent { from, to, .. })
+                | TraceEvent::Response(ResponseEvent { from, to, .. })
+                | TraceEvent::Notification(NotificationEvent { from, to, .. }) => {
+                    from == component || to == component
+                }
+                TraceEvent::Trace(log) => log.component == *component,
+            };
+            if !involved {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Print the message flow of a trace, grouped by session.
+///
+/// Each message is shown on the hop it was recorded on, so a prompt that
+/// passes through two proxies appears three times: `client → proxy:0`,
+/// `proxy:0 → proxy:1` and `proxy:1 → agent`. With `params`, the full params
+/// and payloads are printed below each message instead of a summary.
+pub fn show(events: &[ResolvedEvent], params: bool, out: &mut dyn Write) -> Result<()> {
+    // Sessions in order of their first event
+    let mut sessions: Vec<(Option<&str>, Vec<&ResolvedEvent>)> = vec![];
+    for event in events {
+        let session = event.session.as_deref();
+        match sessions.iter_mut().find(|(s, _)| *s == session) {
+            Some((_, group)) => group.push(event),
+            None => sessions.push((session, vec![event])),
+        }
+    }
+
+    for (i, (session, group)) in sessions.iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        match session {
+            Some(session) => writeln!(out, "Session {} ({} events)", session, group.len())?,
+            None => writeln!(out, "Outside sessions ({} events)", group.len())?,
+        }
+        for event in group {
+            write_event_line(event, params, out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Print one event as a line of `show` output.
+fn write_event_line(event: &ResolvedEvent, params: bool, out: &mut dyn Write) -> Result<()> {
+    let method = event.method.as_deref().unwrap_or("?");
+    let (ts, hop, label, body) = match &event.event {
+        TraceEvent::Request(request) => {
+            let mcp = if request.protocol == Protocol::Mcp {
+                "mcp "
+            } else {
+                ""
+            };
+            (
+                request.ts,
+                format!("{} → {}", request.from, request.to),
+                format!("{mcp}{method} #{}", request.id),
+                &request.params,
+            )
+        }
+        TraceEvent::Response(response) => {
+            let outcome = if response.is_error { "error" } else { "ok" };
+            (
+                response.ts,
+                format!("{} → {}", response.from, response.to),
+                format!("{outcome} {method} #{}", response.id),
+                &response.payload,
+            )
+        }
+        TraceEvent::Notification(notification) => {
+            let mcp = if notification.protocol == Protocol::Mcp {
+                "mcp "
+            } else {
+                ""
+            };
+            (
+                notification.ts,
+                format!("{} → {}", notification.from, notification.to),
+                format!("{mcp}{method}"),
+                &notification.params,
+            )
+        }
+        TraceEvent::Trace(log) => {
+            writeln!(
+                out,
+                "{:>9.3}  [{}] {:?}: {}",
+                log.ts, log.component, log.level, log.message
+            )?;
+            return Ok(());
+        }
+    };
+
+    if params {
+        writeln!(out, "{ts:>9.3}  {hop:<20}  {label}")?;
+        for line in serde_json::to_string_pretty(body)?.lines() {
+            writeln!(out, "{:>11}{line}", "")?;
+        }
+    } else {
+        writeln!(out, "{ts:>9.3}  {hop:<20}  {label}  {}", summarize(body))?;
+    }
+    Ok(())
+}
+
+/// Compact JSON, cut off after [`SUMMARY_LEN`] characters.
+fn summarize(value: &serde_json::Value) -> String {
+    let json = value.to_string();
+    match json.char_indices().nth(SUMMARY_LEN) {
+        Some((end, _)) => format!("{}…", &json[..end]),
+        None => json,
+    }
+}
+
+/// Replays the client side of a trace.
+///
+/// The requests and notifications the client sent are sent again, in order.
+/// A message is only sent once the responses the client had received before
+/// it (in the trace) have arrived, so prompts wait for the previous turn to
+/// finish but a `session/cancel` still arrives during its prompt.
+///
+/// Requests from the agent to the client (permissions, file reads, ...) get
+/// the response the client gave in the trace, matched by method in order.
+/// Sessions created during the replay have new IDs; later messages are
+/// rewritten to use them.
+pub struct Replay {
+    steps: Vec<Step>,
+    /// The client's recorded responses to agent requests, by method
+    answers: FxHashMap<String, VecDeque<RecordedResponse>>,
+    /// Replaces the `cwd` of `session/new` and `session/load` requests
+    cwd: Option<PathBuf>,
+}
+
+/// A message from the client in the trace.
+struct Step {
+    /// Request ID, `None` for notifications
+    id: Option<serde_json::Value>,
+    method: String,
+    params: serde_json::Value,
+    /// Session the message belongs to in the trace
+    session: Option<String>,
+    /// IDs of the requests whose responses must arrive before this is sent
+    after: Vec<serde_json::Value>,
+}
+
+struct RecordedResponse {
+    is_error: bool,
+    payload: serde_json::Value,
+}
+
+impl Replay {
+    pub fn new(events: &[ResolvedEvent]) -> Result<Self> {
+        let mut steps: Vec<Step> = vec![];
+        let mut answers: FxHashMap<String, VecDeque<RecordedResponse>> = FxHashMap::default();
+        // Responses the client received since its last message
+        let mut received = vec![];
+
+        for event in events {
+            match &event.event {
+                TraceEvent::Request(request)
+                    if request.from == CLIENT && request.protocol == Protocol::Acp =>
+                {
+                    steps.push(Step {
+                        id: Some(request.id.clone()),
+                        method: request.method.clone(),
+                        params: request.params.clone(),
+                        session: event.session.clone(),
+                        after: std::mem::take(&mut received),
+                    });
+                }
+                TraceEvent::Notification(notification)
+                    if notification.from == CLIENT && notification.protocol == Protocol::Acp =>
+                {
+                    steps.push(Step {
+                        id: None,
+                        method: notification.method.clone(),
+                        params: notification.params.clone(),
+                        session: event.session.clone(),
+                        after: std::mem::take(&mut received),
+                    });
+                }
+                TraceEvent::Response(response) if response.to == CLIENT => {
+                    received.push(response.id.clone());
+                }
+                TraceEvent::Response(response) if response.from == CLIENT => {
+                    if let Some(method) = &event.method {
+                        answers
+                            .entry(method.clone())
+                            .or_default()
+                            .push_back(RecordedResponse {
+                                is_error: response.is_error,
+                                payload: response.payload.clone(),
+                            });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if steps.is_empty() {
+            bail!("the trace contains no messages from the client");
+        }
+
+        // Per-session traces have no `initialize`, but the agent still needs one
+        if steps[0].method != "initialize" {
+            steps.insert(
+                0,
+                Step {
+                    id: Some(serde_json::json!("replay-initialize")),
+                    method: "initialize".to_string(),
+                    params: serde_json::to_value(InitializeRequest::new(ProtocolVersion::LATEST))?,
+                    session: None,
+                    after: vec![],
+                },
+            );
+        }
+
+        Ok(Self {
+            steps,
+            answers,
+            cwd: None,
+        })
+    }
+
+    /// Create and load sessions in `cwd` rather than the recorded directory.
+    pub fn with_cwd(mut self, cwd: Option<PathBuf>) -> Self {
+        self.cwd = cwd;
+        self
+    }
+
+    /// Replay against `agent`, printing the messages exchanged to `out`.
+    pub async fn run(
+        self,
+        agent: impl Component<AgentToClient>,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        let (client, agent_side) = Channel::duplex();
+        let client = Replayer {
+            channel: client,
+            answers: self.answers,
+            cwd: self.cwd,
+            start: Instant::now(),
+            pending: FxHashMap::default(),
+            answered: FxHashSet::default(),
+            sessions: FxHashMap::default(),
+            out,
+        };
+        match futures::future::select(
+            Box::pin(agent.serve(agent_side)),
+            Box::pin(client.run(self.steps)),
+        )
+        .await
+        {
+            Either::Left((result, _)) => {
+                result?;
+                bail!("the agent exited before the replay finished")
+            }
+            Either::Right((result, _)) => result,
+        }
+    }
+}
+
+/// The client end of a running [`Replay`].
+struct Replayer<'a> {
+    channel: Channel,
+    answers: FxHashMap<String, VecDeque<RecordedResponse>>,
+    cwd: Option<PathBuf>,
+    start: Instant,
+    /// Requests sent and not yet answered, by [`id_key`]
+    pending: FxHashMap<String, Step>,
+    /// Requests answered so far, by [`id_key`]
+    answered: FxHashSet<String>,
+    /// Recorded session IDs mapped to the IDs of the replayed sessions
+    sessions: FxHashMap<String, String>,
+    out: &'a mut dyn Write,
+}
+
+impl Replayer<'_> {
+    async fn run(mut self, steps: Vec<Step>) -> Result<()> {
+        for step in steps {
+            while !step
+                .after
+                .iter()
+                .all(|id| self.answered.contains(&id_key(id)))
+            {
+                self.receive().await?;
+            }
+            self.send(step)?;
+        }
+        while !self.pending.is_empty() {
+            self.receive().await?;
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, mut step: Step) -> Result<()> {
+        if let Some(session) = step.params.get_mut("sessionId")
+            && let Some(replayed) = session.as_str().and_then(|s| self.sessions.get(s))
+        {
+            *session = serde_json::Value::String(replayed.clone());
+        }
+        if let Some(cwd) = &self.cwd
+            && matches!(step.method.as_str(), "session/new" | "session/load")
+        {
+            step.params["cwd"] = serde_json::json!(cwd);
+        }
+
+        let params = match &step.params {
+            serde_json::Value::Null => None,
+            params => Some(serde_json::from_value::<Params>(params.clone())?),
+        };
+        let id = step
+            .id
+            .clone()
+            .map(serde_json::from_value::<Id>)
+            .transpose()?;
+        let request = match &id {
+            Some(_) => Request::new(step.method.clone(), params, id),
+            None => Request::notification(step.method.clone(), params),
+        };
+        self.channel
+            .tx
+            .unbounded_send(Ok(Message::Request(request)))
+            .context("the agent stopped receiving messages")?;
+
+        let event = match &step.id {
+            Some(id) => TraceEvent::Request(RequestEvent {
+                ts: self.ts(),
+                protocol: Protocol::Acp,
+                from: CLIENT.to_string(),
+                to: AGENT.to_string(),
+                id: id.clone(),
+                method: step.method.clone(),
+                session: None,
+                params: step.params.clone(),
+            }),
+            None => TraceEvent::Notification(NotificationEvent {
+                ts: self.ts(),
+                protocol: Protocol::Acp,
+                from: CLIENT.to_string(),
+                to: AGENT.to_string(),
+                method: step.method.clone(),
+                session: None,
+                params: step.params.clone(),
+            }),
+        };
+        self.print(event, Some(step.method.clone()))?;
+
+        if let Some(id) = &step.id {
+            self.pending.insert(id_key(id), step);
+        }
+        Ok(())
+    }
+
+    /// Handle the next message from the agent.
+    async fn receive(&mut self) -> Result<()> {
+        let Some(message) = self.channel.rx.next().await else {
+            bail!("the agent closed the connection before the replay finished");
+        };
+        match message? {
+            Message::Response(response) => {
+                let id = serde_json::to_value(&response.id)?;
+                let key = id_key(&id);
+                let step = self.pending.remove(&key);
+                self.answered.insert(key);
+
+                if let Some(step) = &step
+                    && step.method == "session/new"
+                    && let Some(recorded) = &step.session
+                    && let Some(replayed) = response
+                        .result
+                        .as_ref()
+                        .and_then(|result| result.get("sessionId"))
+                        .and_then(|id| id.as_str())
+                {
+                    self.sessions.insert(recorded.clone(), replayed.to_string());
+                }
+
+                let (is_error, payload) = match (&response.error, response.result) {
+                    (Some(error), _) => (true, serde_json::to_value(error)?),
+                    (None, result) => (false, result.unwrap_or_default()),
+                };
+                let event = TraceEvent::Response(ResponseEvent {
+                    ts: self.ts(),
+                    from: AGENT.to_string(),
+                    to: CLIENT.to_string(),
+                    id,
+                    is_error,
+                    payload,
+                });
+                self.print(event, step.map(|step| step.method))?;
+            }
+
+            Message::Request(request) => {
+                let params = serde_json::to_value(&request.params)?;
+                let session = params
+                    .get("sessionId")
+                    .and_then(|id| id.as_str())
+                    .map(str::to_string);
+                let Some(id) = request.id.clone() else {
+                    let event = TraceEvent::Notification(NotificationEvent {
+                        ts: self.ts(),
+                        protocol: Protocol::Acp,
+                        from: AGENT.to_string(),
+                        to: CLIENT.to_string(),
+                        method: request.method.clone(),
+                        session,
+                        params,
+                    });
+                    return self.print(event, Some(request.method));
+                };
+
+                let event = TraceEvent::Request(RequestEvent {
+                    ts: self.ts(),
+                    protocol: Protocol::Acp,
+                    from: AGENT.to_string(),
+                    to: CLIENT.to_string(),
+                    id: serde_json::to_value(&id)?,
+                    method: request.method.clone(),
+                    session,
+                    params,
+                });
+                self.print(event, Some(request.method.clone()))?;
+
+                let recorded = self
+                    .answers
+                    .get_mut(&request.method)
+                    .and_then(|answers| answers.pop_front());
+                let (response, is_error, payload) = match recorded {
+                    Some(RecordedResponse {
+                        is_error: false,
+                        payload,
+                    }) => (
+                        Response::success(payload.clone(), Some(id.clone())),
+                        false,
+                        payload,
+                    ),
+                    Some(RecordedResponse {
+                        is_error: true,
+                        payload,
+                    }) => {
+                        let error: jsonrpcmsg::Error = serde_json::from_value(payload.clone())?;
+                        (Response::error(error, Some(id.clone())), true, payload)
+                    }
+                    None => {
+                        let error = jsonrpcmsg::Error::new(
+                            INTERNAL_ERROR_CODE,
+                            format!("The trace has no response to `{}`", request.method),
+                        );
+                        let payload = serde_json::to_value(&error)?;
+                        (Response::error(error, Some(id.clone())), true, payload)
+                    }
+                };
+                self.channel
+                    .tx
+                    .unbounded_send(Ok(Message::Response(response)))
+                    .context("the agent stopped receiving messages")?;
+
+                let event = TraceEvent::Response(ResponseEvent {
+                    ts: self.ts(),
+                    from: CLIENT.to_string(),
+                    to: AGENT.to_string(),
+                    id: serde_json::to_value(&id)?,
+                    is_error,
+                    payload,
+                });
+                self.print(event, Some(request.method))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn ts(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+
+    fn print(&mut self, event: TraceEvent, method: Option<String>) -> Result<()> {
+        let event = ResolvedEvent {
+            event,
+            method,
+            session: None,
+        };
+        write_event_line(&event, false, self.out)
+    }
+}
+
+/// Key of a request ID, which may be a number or a string.
+fn id_key(id: &serde_json::Value) -> String {
+    id.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expect_test::expect;
+
+    const TRACE: &str = r#"
+{"type":"request","ts":0.001,"protocol":"acp","from":"client","to":"proxy:0","id":0,"method":"initialize","params":{"protocolVersion":1}}
+{"type":"response","ts":0.010,"from":"proxy:0","to":"client","id":0,"is_error":false,"payload":{"protocolVersion":1}}
+{"type":"request","ts":0.020,"protocol":"acp","from":"client","to":"proxy:0","id":1,"method":"session/new","params":{"cwd":"/work","mcpServers":[]}}
+{"type":"request","ts":0.021,"protocol":"acp","from":"proxy:0","to":"agent","id":5,"method":"session/new","params":{"cwd":"/work","mcpServers":[]}}
+{"type":"response","ts":0.030,"from":"agent","to":"proxy:0","id":5,"is_error":false,"payload":{"sessionId":"recorded"}}
+{"type":"response","ts":0.031,"from":"proxy:0","to":"client","id":1,"is_error":false,"payload":{"sessionId":"recorded"}}
+{"type":"request","ts":0.040,"protocol":"acp","from":"client","to":"proxy:0","id":2,"method":"session/prompt","session":"recorded","params":{"sessionId":"recorded","prompt":[{"type":"text","text":"Hello"}]}}
+{"type":"notification","ts":0.050,"protocol":"acp","from":"agent","to":"client","method":"session/update","session":"recorded","params":{"sessionId":"recorded"}}
+{"type":"trace","ts":0.055,"component":"proxy:0","level":"info","message":"prompt seen"}
+{"type":"response","ts":0.060,"from":"proxy:0","to":"client","id":2,"is_error":false,"payload":{"stopReason":"end_turn"}}
+"#;
+
+    fn events() -> Vec<ResolvedEvent> {
+        resolve(parse_trace(TRACE).unwrap())
+    }
+
+    #[test]
+    fn test_resolve_sessions_and_methods() {
+        let resolved: Vec<_> = events()
+            .iter()
+            .map(|e| format!("{:?} {:?}", e.method, e.session))
+            .collect();
+        expect![[r#"
+            [
+                "Some(\"initialize\") None",
+                "Some(\"initialize\") None",
+                "Some(\"session/new\") Some(\"recorded\")",
+                "Some(\"session/new\") Some(\"recorded\")",
+                "Some(\"session/new\") Some(\"recorded\")",
+                "Some(\"session/new\") Some(\"recorded\")",
+                "Some(\"session/prompt\") Some(\"recorded\")",
+                "Some(\"session/update\") Some(\"recorded\")",
+                "None None",
+                "Some(\"session/prompt\") Some(\"recorded\")",
+            ]
+        "#]]
+        .assert_debug_eq(&resolved);
+    }
+
+    #[test]
+    fn test_filter() {
+        let count = |filter: TraceFilter| events().iter().filter(|e| filter.matches(e)).count();
+
+        let session = TraceFilter {
+            session: Some("recorded".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(count(session), 7);
+
+        let method = TraceFilter {
+            method: Some("session/*".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(count(method), 7);
+
+        let component = TraceFilter {
+            component: Some("agent".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(count(component), 3);
+
+        let both = TraceFilter {
+            method: Some("session/new".to_string()),
+            component: Some("agent".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(count(both), 2);
+    }
+
+    #[test]
+    fn test_show() {
+        let mut out = vec![];
+        show(&events(), false, &mut out).unwrap();
+        expect![[r#"
+            Outside sessions (3 events)
+                0.001  client → proxy:0      initialize #0  {"protocolVersion":1}
+                0.010  proxy:0 → client      ok initialize #0  {"protocolVersion":1}
+                0.055  [proxy:0] Info: prompt seen
+
+            Session recorded (7 events)
+                0.020  client → proxy:0      session/new #1  {"cwd":"/work","mcpServers":[]}
+                0.021  proxy:0 → agent       session/new #5  {"cwd":"/work","mcpServers":[]}
+                0.030  agent → proxy:0       ok session/new #5  {"sessionId":"recorded"}
+                0.031  proxy:0 → client      ok session/new #1  {"sessionId":"recorded"}
+                0.040  client → proxy:0      session/prompt #2  {"prompt":[{"text":"Hello","type":"text"}],"sessionId":"recorded"}
+                0.050  agent → client        session/update  {"sessionId":"recorded"}
+                0.060  proxy:0 → client      ok session/prompt #2  {"stopReason":"end_turn"}
+        "#]]
+        .assert_eq(&String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_replay_steps() {
+        let session = TraceFilter {
+            session: Some("recorded".to_string()),
+            ..Default::default()
+        };
+        let events: Vec<_> = events()
+            .into_iter()
+            .filter(|e| session.matches(e))
+            .collect();
+        let replay = Replay::new(&events).unwrap();
+
+        // The filtered trace lost `initialize`, so the replay adds one
+        let steps: Vec<_> = replay
+            .steps
+            .iter()
+            .map(|step| format!("{} after {:?}", step.method, step.after))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                "initialize after []",
+                "session/new after []",
+                "session/prompt after [Number(1)]",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_against_eliza() {
+        let replay = Replay::new(&events())
+            .unwrap()
+            .with_cwd(Some(PathBuf::from("/elsewhere")));
+        let mut out = vec![];
+        replay
+            .run(elizacp::ElizaAgent::new(true), &mut out)
+            .await
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(
+            out.contains(r#"session/new #1  {"cwd":"/elsewhere""#),
+            "{out}"
+        );
+        assert!(out.contains("ok session/prompt #2"), "{out}");
+        // The prompt went to the replayed session, not the recorded one
+        assert!(!out.contains(r#""sessionId":"recorded""#), "{out}");
+    }
+}
@@ -6,16 +6,21 @@
 //! - Windows: `%APPDATA%\symposium\`
 //!
 //! Within this directory:
-//! - Global agent config: `config/agent.json` - the selected agent for all workspaces
-//! - Per-workspace mods: `config/<encoded-workspace-path>/config.json`
+//! - Global agent config: `config/agent.json` - the agent for workspaces without their own
+//! - User settings: `config/settings.json` - recommendation sources and registries
+//! - Per-workspace mods (and agent, if overridden): `config/<encoded-workspace-path>/config.json`
+//! - Per-workspace sessions: `config/<encoded-workspace-path>/sessions.json`
+//! - Per-workspace allowed tools: `config/<encoded-workspace-path>/allowed-tools.json`
 //! - Cache: `cache/` - for downloaded recommendations and other cached data
 //!
 //! The configuration uses `ComponentSource` as the identity for mods,
 //! enabling easy diffing with recommendations.
 
+use crate::mod_overrides::ModOverrides;
 use crate::recommendations::When;
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use symposium_recommendations::{ComponentSource, ModKind, Recommendation};
 
@@ -105,6 +110,17 @@ impl ConfigPaths {
         Ok(path)
     }
 
+    // ------------------------------------------------------------------------
+    // User settings
+    // ------------------------------------------------------------------------
+
+    /// Get the path to the user settings file.
+    ///
+    /// Location: `<root>/config/settings.json`
+    pub fn user_settings_path(&self) -> PathBuf {
+        self.root.join("config").join("settings.json")
+    }
+
     // ------------------------------------------------------------------------
     // Workspace config
     // ------------------------------------------------------------------------
@@ -136,15 +152,39 @@ impl ConfigPaths {
         Ok(path)
     }
 
+    /// Get the file listing a workspace's saved sessions.
+    ///
+    /// Location: `<root>/config/<encoded-workspace-path>/sessions.json`
+    pub fn workspace_sessions_path(&self, workspace_path: &Path) -> PathBuf {
+        self.workspace_config_dir(workspace_path)
+            .join("sessions.json")
+    }
+
+    /// Get the file listing the tools the user always allows in a workspace.
+    ///
+    /// Location: `<root>/config/<encoded-workspace-path>/allowed-tools.json`
+    pub fn workspace_allowed_tools_path(&self, workspace_path: &Path) -> PathBuf {
+        self.workspace_config_dir(workspace_path)
+            .join("allowed-tools.json")
+    }
+
     // ------------------------------------------------------------------------
     // Binary cache (for downloaded agents)
     // ------------------------------------------------------------------------
 
+    /// Get the directory holding all cached versions of a binary agent.
+    ///
+    /// Location: `<root>/bin/<agent_id>/`, where the `:` of a qualified ID
+    /// (e.g. `acme:our-agent`) becomes `@`, which is valid on all platforms.
+    pub fn binary_versions_dir(&self, agent_id: &str) -> PathBuf {
+        self.root.join("bin").join(agent_id.replace(':', "@"))
+    }
+
     /// Get the cache directory for a binary agent.
     ///
     /// Location: `<root>/bin/<agent_id>/<version>/`
     pub fn binary_cache_dir(&self, agent_id: &str, version: &str) -> PathBuf {
-        self.root.join("bin").join(agent_id).join(version)
+        self.binary_versions_dir(agent_id).join(version)
     }
 
     /// Ensure the binary cache directory exists and return the path.
@@ -167,20 +207,53 @@ impl ConfigPaths {
         self.root.join("cache")
     }
 
-    /// Get the path to the cached recommendations file.
+    /// Get the path to the cached recommendations of one recommendation source.
+    ///
+    /// Location: `<root>/cache/recommendations/<source_name>.toml`
+    pub fn recommendations_cache_path(&self, source_name: &str) -> PathBuf {
+        self.cache_dir()
+            .join("recommendations")
+            .join(format!("{source_name}.toml"))
+    }
+
+    /// Get the cached recommendations of one recommendation source, if there are any.
+    ///
+    /// Before recommendations came from several sources, the default source was
+    /// cached at `<root>/cache/recommendations.toml`. That file is still used
+    /// for the default source until it is cached at its new location.
+    pub fn cached_recommendations_path(&self, source_name: &str) -> Option<PathBuf> {
+        let path = self.recommendations_cache_path(source_name);
+        if path.exists() {
+            return Some(path);
+        }
+        let legacy = self.cache_dir().join("recommendations.toml");
+        (source_name == DEFAULT_RECOMMENDATION_SOURCE_NAME && legacy.is_file()).then_some(legacy)
+    }
+
+    /// Get the file recording which agents support `session/load`.
+    ///
+    /// Location: `<root>/cache/load-session.json`
+    pub fn load_session_support_path(&self) -> PathBuf {
+        self.cache_dir().join("load-session.json")
+    }
+
+    /// Get the directory holding cached agent registries.
     ///
-    /// Location: `<root>/cache/recommendations.toml`
-    pub fn recommendations_cache_path(&self) -> PathBuf {
-        self.cache_dir().join("recommendations.toml")
+    /// Location: `<root>/cache/registries/`
+    pub fn registry_cache_dir(&self) -> PathBuf {
+        self.cache_dir().join("registries")
     }
 
-    /// Ensure the cache directory exists and return the recommendations cache path.
+    /// Ensure the recommendations cache directory exists and return the
+    /// cache path for the given recommendation source.
     ///
     /// Use this before writing to the recommendations cache.
-    pub async fn ensure_cache_dir(&self) -> Result<PathBuf> {
-        let cache_dir = self.cache_dir();
-        self.ensure_dir(&cache_dir).await?;
-        Ok(self.recommendations_cache_path())
+    pub async fn ensure_recommendations_cache_path(&self, source_name: &str) -> Result<PathBuf> {
+        let path = self.recommendations_cache_path(source_name);
+        if let Some(dir) = path.parent() {
+            self.ensure_dir(dir).await?;
+        }
+        Ok(path)
     }
 
     pub fn local_reccomendations_path(&self) -> PathBuf {
@@ -203,6 +276,57 @@ pub struct ModConfig {
     /// The conditions that caused this mod to be recommended.
     /// Used to explain why a mod is stale when the conditions no longer apply.
     pub when: When,
+
+    /// Position of this mod in the proxy chain.
+    ///
+    /// Mods with lower values are chained first (closest to the editor).
+    /// Configs written before ordering existed have no `order` field; every
+    /// mod then defaults to `0` and the order in the file is kept.
+    #[serde(default)]
+    pub order: u32,
+
+    /// Version the mod is pinned to (see [`crate::versions`]).
+    /// Unpinned mods resolve to the newest version at spawn time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    /// What happens when this mod fails to resolve or keeps crashing.
+    #[serde(default, skip_serializing_if = "FailurePolicy::is_optional")]
+    pub policy: FailurePolicy,
+
+    /// Extra arguments, environment variables and working directory for the
+    /// mod's process (see [`crate::mod_overrides`]).
+    #[serde(default, skip_serializing_if = "ModOverrides::is_empty")]
+    pub overrides: ModOverrides,
+}
+
+/// What happens when a mod fails to resolve, or crashes more often than it
+/// can be restarted.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailurePolicy {
+    /// The session can't start without this mod.
+    Required,
+    /// The session starts without this mod, with a warning.
+    #[default]
+    Optional,
+}
+
+impl FailurePolicy {
+    pub fn is_optional(&self) -> bool {
+        matches!(self, FailurePolicy::Optional)
+    }
+}
+
+/// Where to move a mod when reordering (see [`WorkspaceModsConfig::move_mod`]).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ModPosition {
+    /// Move to the front of the chain.
+    Start,
+    /// Move to the back of the chain.
+    End,
+    /// Move to the given 0-based index.
+    Index(usize),
 }
 
 /// Per-workspace mod configuration for Symposium.
@@ -210,12 +334,20 @@ pub struct ModConfig {
 /// Uses `ComponentSource` as identity for mods.
 /// This makes it easy to compare with recommendations and detect changes.
 ///
-/// Note: The agent is stored globally in `GlobalAgentConfig`, not per-workspace.
+/// Mods are kept sorted by [`ModConfig::order`], so the order of `mods` is
+/// the order in which proxies are chained.
+///
+/// The agent is normally the global one from [`GlobalAgentConfig`]; a
+/// workspace can override it with its own (see [`Self::agent_or`]).
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct WorkspaceModsConfig {
     /// Mods with their enabled state
     #[serde(default)]
     pub mods: Vec<ModConfig>,
+
+    /// Agent used in this workspace instead of the global agent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<Box<GlobalAgentConfig>>,
 }
 
 // ============================================================================
@@ -224,19 +356,29 @@ pub struct WorkspaceModsConfig {
 
 /// Global agent configuration.
 ///
-/// Stores the user's selected agent. This agent is used for all workspaces.
+/// Stores the user's selected agent. This agent is used for all workspaces
+/// that don't have their own ([`WorkspaceModsConfig::agent`]), which is
+/// stored in the same form.
 ///
 /// Stored at `<config_dir>/config/agent.json`
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct GlobalAgentConfig {
-    /// The selected agent for all workspaces
+    /// The selected agent
     pub agent: ComponentSource,
+
+    /// Version the agent is pinned to (see [`crate::versions`]).
+    /// An unpinned agent resolves to the newest version at spawn time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
 }
 
 impl GlobalAgentConfig {
-    /// Create a new global agent config
+    /// Create a new global agent config (unpinned)
     pub fn new(agent: ComponentSource) -> Self {
-        Self { agent }
+        Self {
+            agent,
+            version: None,
+        }
     }
 
     /// Load the global agent config.
@@ -270,6 +412,215 @@ impl GlobalAgentConfig {
     }
 }
 
+// ============================================================================
+// User Settings
+// ============================================================================
+
+/// Name of the public Symposium recommendations source.
+pub const DEFAULT_RECOMMENDATION_SOURCE_NAME: &str = "symposium";
+
+/// URL of the public Symposium recommendations.
+pub const DEFAULT_RECOMMENDATIONS_URL: &str =
+    "http://recommendations.symposium.dev/recommendations.toml";
+
+/// Global settings edited by hand.
+///
+/// Stored at `<config_dir>/config/settings.json`. A missing file, or a
+/// missing field, means the default.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct UserSettings {
+    /// Where remote recommendations are loaded from.
+    ///
+    /// Defaults to the public Symposium recommendations. Setting this replaces
+    /// the default, so list the public source too to keep using it.
+    pub recommendation_sources: Vec<RecommendationSourceConfig>,
+
+    /// Additional agent registries, searched alongside the public registry.
+    /// IDs of their entries are namespaced by registry (e.g. `acme:our-agent`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub registries: Vec<RegistryConfig>,
+
+    /// How long (in seconds) a cached registry is used before it is
+    /// revalidated with the server. `0` revalidates on every use.
+    pub registry_cache_ttl_secs: u64,
+
+    /// Vocabulary files (in the tiktoken format) used to count tokens for the
+    /// VS Code language model provider, by model ID.
+    /// Relative paths are relative to the directory of the settings file.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tokenizers: BTreeMap<String, PathBuf>,
+}
+
+/// Default for [`UserSettings::registry_cache_ttl_secs`]: one hour.
+pub const DEFAULT_REGISTRY_CACHE_TTL_SECS: u64 = 60 * 60;
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            recommendation_sources: vec![RecommendationSourceConfig {
+                name: DEFAULT_RECOMMENDATION_SOURCE_NAME.to_string(),
+                location: FileLocation::Url(DEFAULT_RECOMMENDATIONS_URL.to_string()),
+                auth_header_env: None,
+                priority: 0,
+            }],
+            registries: vec![],
+            registry_cache_ttl_secs: DEFAULT_REGISTRY_CACHE_TTL_SECS,
+            tokenizers: BTreeMap::new(),
+        }
+    }
+}
+
+/// A place to load remote recommendations from.
+///
+/// ```json
+/// { "name": "acme", "url": "https://acme.example/recs.toml", "auth-header-env": "ACME_RECS_AUTH", "priority": 10 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RecommendationSourceConfig {
+    /// Unique name of the source; also names its cache file.
+    /// May only contain ASCII letters, digits, `-` and `_`.
+    pub name: String,
+
+    /// Where the recommendations file lives
+    #[serde(flatten)]
+    pub location: FileLocation,
+
+    /// Environment variable holding the value of the `Authorization` header
+    /// sent when fetching from a URL (e.g. `Bearer <token>`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_header_env: Option<String>,
+
+    /// When several sources recommend the same mod, the recommendation from
+    /// the source with the highest priority is used.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// ID of the public ACP registry. Its entries are not namespaced.
+pub const PUBLIC_REGISTRY_ID: &str = "public";
+
+/// URL of the public ACP registry - same as VSCode extension uses
+pub const PUBLIC_REGISTRY_URL: &str =
+    "https://github.com/agentclientprotocol/registry/releases/latest/download/registry.json";
+
+/// An agent registry (a `registry.json` in the ACP registry format).
+///
+/// ```json
+/// { "id": "acme", "url": "https://acme.example/registry.json", "auth-header-env": "ACME_REGISTRY_AUTH" }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RegistryConfig {
+    /// Unique ID of the registry, used to namespace the IDs of its entries.
+    /// May only contain ASCII letters, digits, `-` and `_`.
+    pub id: String,
+
+    /// Where the `registry.json` lives
+    #[serde(flatten)]
+    pub location: FileLocation,
+
+    /// Environment variable holding the value of the `Authorization` header
+    /// sent when fetching from a URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_header_env: Option<String>,
+}
+
+impl RegistryConfig {
+    /// The public ACP registry
+    pub fn public() -> Self {
+        Self {
+            id: PUBLIC_REGISTRY_ID.to_string(),
+            location: FileLocation::Url(PUBLIC_REGISTRY_URL.to_string()),
+            auth_header_env: None,
+        }
+    }
+
+    /// Whether this is the public ACP registry
+    pub fn is_public(&self) -> bool {
+        self.id == PUBLIC_REGISTRY_ID
+    }
+}
+
+/// Location of a recommendations or registry file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileLocation {
+    /// Fetched over HTTP(S)
+    Url(String),
+    /// Read from the local filesystem
+    Path(PathBuf),
+}
+
+impl UserSettings {
+    /// Load the user settings, or the defaults if the file doesn't exist.
+    pub fn load(config_paths: &ConfigPaths) -> Result<Self> {
+        let path = config_paths.user_settings_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read user settings from {}", path.display()))?;
+        let settings: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse user settings from {}", path.display()))?;
+        settings
+            .validate()
+            .with_context(|| format!("Invalid user settings in {}", path.display()))?;
+        Ok(settings)
+    }
+
+    /// All registries to search: the public registry, then the configured ones.
+    pub fn registries(&self) -> Vec<RegistryConfig> {
+        std::iter::once(RegistryConfig::public())
+            .chain(self.registries.iter().cloned())
+            .collect()
+    }
+
+    /// The [`Self::tokenizers`] vocabulary files, with relative paths resolved.
+    pub fn tokenizer_paths(&self, config_paths: &ConfigPaths) -> BTreeMap<String, PathBuf> {
+        let settings_path = config_paths.user_settings_path();
+        let settings_dir = settings_path.parent().unwrap_or(config_paths.root());
+        self.tokenizers
+            .iter()
+            .map(|(model_id, path)| (model_id.clone(), settings_dir.join(path)))
+            .collect()
+    }
+
+    /// Check that source names and registry IDs are unique and usable as
+    /// file names.
+    fn validate(&self) -> Result<()> {
+        let mut names = std::collections::HashSet::new();
+        for source in &self.recommendation_sources {
+            check_settings_name("recommendation source name", &source.name)?;
+            if !names.insert(&source.name) {
+                bail!("duplicate recommendation source name `{}`", source.name);
+            }
+        }
+
+        let mut ids = std::collections::HashSet::new();
+        for registry in &self.registries {
+            check_settings_name("registry ID", &registry.id)?;
+            if registry.is_public() || !ids.insert(&registry.id) {
+                bail!("duplicate registry ID `{}`", registry.id);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Names in the user settings may only contain ASCII letters, digits, `-` and `_`.
+fn check_settings_name(what: &str, name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        bail!("{what} `{name}` may only contain ASCII letters, digits, `-` and `_`");
+    }
+    Ok(())
+}
+
 // ============================================================================
 // Workspace Mods Config
 // ============================================================================
@@ -277,23 +628,79 @@ impl GlobalAgentConfig {
 impl WorkspaceModsConfig {
     /// Create a new workspace mods config
     pub fn new(mods: Vec<ModConfig>) -> Self {
-        Self { mods }
+        let mut config = Self { mods, agent: None };
+        config.normalize_order();
+        config
     }
 
     /// Create a workspace mods config from a list of mod sources.
-    /// All mods are enabled by default.
+    /// All mods are enabled by default, in the order they were recommended.
     pub fn from_recommendations(sources: Vec<Recommendation>) -> Self {
         let mods = sources
             .into_iter()
-            .map(|rec| ModConfig {
+            .zip(0..)
+            .map(|(rec, order)| ModConfig {
                 kind: rec.kind,
                 source: rec.source,
                 when: rec.when.unwrap_or(When::default()),
                 enabled: true,
+                order,
+                version: None,
+                policy: Default::default(),
+                overrides: Default::default(),
             })
             .collect();
 
-        Self { mods }
+        Self { mods, agent: None }
+    }
+
+    /// The agent to run in this workspace: its own agent if it has one,
+    /// otherwise the global agent.
+    pub fn agent_or(&self, global: &GlobalAgentConfig) -> GlobalAgentConfig {
+        self.agent.as_deref().unwrap_or(global).clone()
+    }
+
+    /// Append a mod to the end of the chain.
+    pub fn push_mod(&mut self, mut m: ModConfig) {
+        m.order = self.mods.iter().map(|m| m.order + 1).max().unwrap_or(0);
+        self.mods.push(m);
+    }
+
+    /// Move the mod at 0-based index `from` to `to`.
+    ///
+    /// An index past the end is treated as [`ModPosition::End`].
+    /// Returns false (and changes nothing) if `from` is out of range.
+    pub fn move_mod(&mut self, from: usize, to: ModPosition) -> bool {
+        if from >= self.mods.len() {
+            return false;
+        }
+
+        let m = self.mods.remove(from);
+        let to = match to {
+            ModPosition::Start => 0,
+            ModPosition::End => self.mods.len(),
+            ModPosition::Index(index) => index.min(self.mods.len()),
+        };
+        self.mods.insert(to, m);
+
+        self.renumber();
+        true
+    }
+
+    /// Sort mods by `order` and renumber them sequentially.
+    ///
+    /// The sort is stable, so mods with equal `order` (e.g., configs written
+    /// before ordering existed) keep their relative position.
+    fn normalize_order(&mut self) {
+        self.mods.sort_by_key(|m| m.order);
+        self.renumber();
+    }
+
+    /// Assign sequential `order` values matching the current position in `mods`.
+    fn renumber(&mut self) {
+        for (m, order) in self.mods.iter_mut().zip(0..) {
+            m.order = order;
+        }
     }
 
     /// Load the workspace mods config for the given workspace.
@@ -309,8 +716,9 @@ impl WorkspaceModsConfig {
             .with_context(|| format!("Failed to read workspace config from {}", path.display()))?;
 
         // Try to parse - serde will ignore unknown fields like `agent` from old format
-        let config: Self = serde_json::from_str(&content)
+        let mut config: Self = serde_json::from_str(&content)
             .with_context(|| format!("Failed to parse workspace config from {}", path.display()))?;
+        config.normalize_order();
         Ok(Some(config))
     }
 
@@ -332,7 +740,7 @@ impl WorkspaceModsConfig {
 ///
 /// Format: `{last_component}-{truncated_sha256_hash}`
 /// Example: `symposium-e3b0c44298fc1c14`
-fn encode_path(path: &Path) -> String {
+pub(crate) fn encode_path(path: &Path) -> String {
     use sha2::{Digest, Sha256};
 
     let path_str = path.to_string_lossy();
@@ -391,11 +799,23 @@ mod tests {
                         when: When {
                             file_exists: None,
                             files_exist: None,
+                            glob: None,
+                            file_contains: None,
                             using_crate: None,
                             using_crates: None,
+                            env_var_set: None,
+                            not: None,
                             any: None,
                             all: None,
                         },
+                        order: 0,
+                        version: None,
+                        policy: Optional,
+                        overrides: ModOverrides {
+                            args: [],
+                            env: {},
+                            cwd: None,
+                        },
                     },
                     ModConfig {
                         kind: Proxy,
@@ -413,13 +833,26 @@ mod tests {
                         when: When {
                             file_exists: None,
                             files_exist: None,
+                            glob: None,
+                            file_contains: None,
                             using_crate: None,
                             using_crates: None,
+                            env_var_set: None,
+                            not: None,
                             any: None,
                             all: None,
                         },
+                        order: 1,
+                        version: None,
+                        policy: Optional,
+                        overrides: ModOverrides {
+                            args: [],
+                            env: {},
+                            cwd: None,
+                        },
                     },
                 ],
+                agent: None,
             }
         "#]]
         .assert_debug_eq(&config);
@@ -464,6 +897,10 @@ mod tests {
                     }),
                     enabled: true,
                     when: When::default(),
+                    order: 0,
+                    version: None,
+                    policy: Default::default(),
+                    overrides: Default::default(),
                 },
                 ModConfig {
                     kind: ModKind::MCP,
@@ -477,8 +914,13 @@ mod tests {
                     }),
                     enabled: true,
                     when: When::default(),
+                    order: 1,
+                    version: None,
+                    policy: Default::default(),
+                    overrides: Default::default(),
                 },
             ],
+            agent: None,
         };
 
         let json = serde_json::to_string(&config).unwrap();
@@ -486,6 +928,134 @@ mod tests {
         assert_eq!(config, parsed);
     }
 
+    #[test]
+    fn test_mod_config_policy_json() {
+        // Optional is the default and isn't written out
+        let json = r#"{"source":{"builtin":"ferris"},"enabled":true,"when":{}}"#;
+        let mut config: ModConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.policy, FailurePolicy::Optional);
+        assert!(!serde_json::to_string(&config).unwrap().contains("policy"));
+
+        config.policy = FailurePolicy::Required;
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(json.contains(r#""policy":"required""#));
+        let parsed: ModConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.policy, FailurePolicy::Required);
+    }
+
+    fn builtin_mods(names: &[&str]) -> WorkspaceModsConfig {
+        WorkspaceModsConfig::from_recommendations(
+            names
+                .iter()
+                .map(|name| Recommendation {
+                    kind: ModKind::Proxy,
+                    source: ComponentSource::Builtin(name.to_string()),
+                    when: None,
+                })
+                .collect(),
+        )
+    }
+
+    fn mod_names(config: &WorkspaceModsConfig) -> Vec<String> {
+        config
+            .mods
+            .iter()
+            .map(|m| format!("{}={}", m.source.display_name(), m.order))
+            .collect()
+    }
+
+    #[test]
+    fn test_move_mod() {
+        let mut config = builtin_mods(&["a", "b", "c", "d"]);
+
+        assert!(config.move_mod(3, ModPosition::Start));
+        assert_eq!(mod_names(&config), ["d=0", "a=1", "b=2", "c=3"]);
+
+        assert!(config.move_mod(0, ModPosition::End));
+        assert_eq!(mod_names(&config), ["a=0", "b=1", "c=2", "d=3"]);
+
+        assert!(config.move_mod(0, ModPosition::Index(2)));
+        assert_eq!(mod_names(&config), ["b=0", "c=1", "a=2", "d=3"]);
+
+        // Out of range target clamps to the end
+        assert!(config.move_mod(0, ModPosition::Index(10)));
+        assert_eq!(mod_names(&config), ["c=0", "a=1", "d=2", "b=3"]);
+
+        // Out of range source is rejected
+        assert!(!config.move_mod(4, ModPosition::Start));
+        assert_eq!(mod_names(&config), ["c=0", "a=1", "d=2", "b=3"]);
+    }
+
+    #[test]
+    fn test_push_mod_appends_to_end() {
+        let mut config = builtin_mods(&["a", "b"]);
+        config.move_mod(1, ModPosition::Start);
+
+        config.push_mod(ModConfig {
+            kind: ModKind::Proxy,
+            source: ComponentSource::Builtin("c".to_string()),
+            enabled: true,
+            when: When::default(),
+            order: 0,
+            version: None,
+            policy: Default::default(),
+            overrides: Default::default(),
+        });
+        assert_eq!(mod_names(&config), ["b=0", "a=1", "c=2"]);
+    }
+
+    #[tokio::test]
+    async fn test_workspace_mods_config_load_sorts_by_order() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_paths = ConfigPaths::with_root(temp_dir.path());
+        let workspace_path = PathBuf::from("/some/workspace");
+
+        // Written out of order on disk
+        let path = config_paths
+            .ensure_workspace_config_dir(&workspace_path)
+            .await
+            .unwrap();
+        std::fs::write(
+            &path,
+            r#"{"mods":[
+                {"source":{"builtin":"b"},"enabled":true,"when":{},"order":5},
+                {"source":{"builtin":"a"},"enabled":true,"when":{},"order":2}
+            ]}"#,
+        )
+        .unwrap();
+
+        let loaded = WorkspaceModsConfig::load(&config_paths, &workspace_path)
+            .unwrap()
+            .unwrap();
+        assert_eq!(mod_names(&loaded), ["a=0", "b=1"]);
+    }
+
+    #[tokio::test]
+    async fn test_workspace_mods_config_load_without_order_keeps_file_order() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_paths = ConfigPaths::with_root(temp_dir.path());
+        let workspace_path = PathBuf::from("/some/workspace");
+
+        // Configs written before ordering existed have no `order` field
+        let path = config_paths
+            .ensure_workspace_config_dir(&workspace_path)
+            .await
+            .unwrap();
+        std::fs::write(
+            &path,
+            r#"{"mods":[
+                {"source":{"builtin":"z"},"enabled":true,"when":{}},
+                {"source":{"builtin":"a"},"enabled":false,"when":{}}
+            ]}"#,
+        )
+        .unwrap();
+
+        let loaded = WorkspaceModsConfig::load(&config_paths, &workspace_path)
+            .unwrap()
+            .unwrap();
+        assert_eq!(mod_names(&loaded), ["z=0", "a=1"]);
+    }
+
     #[tokio::test]
     async fn test_global_agent_config_save_load_roundtrip() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -502,6 +1072,130 @@ mod tests {
         assert_eq!(config, loaded);
     }
 
+    #[test]
+    fn test_user_settings_defaults_to_public_recommendations() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_paths = ConfigPaths::with_root(temp_dir.path());
+
+        let settings = UserSettings::load(&config_paths).unwrap();
+        assert_eq!(settings, UserSettings::default());
+        assert_eq!(
+            settings.recommendation_sources[0].location,
+            FileLocation::Url(DEFAULT_RECOMMENDATIONS_URL.to_string())
+        );
+    }
+
+    #[test]
+    fn test_user_settings_recommendation_sources() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_paths = ConfigPaths::with_root(temp_dir.path());
+        let path = config_paths.user_settings_path();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        std::fs::write(
+            &path,
+            r#"{
+                "recommendation-sources": [
+                    { "name": "symposium", "url": "http://recommendations.symposium.dev/recommendations.toml" },
+                    { "name": "acme", "url": "https://acme.example/recs.toml", "auth-header-env": "ACME_AUTH", "priority": 10 },
+                    { "name": "team", "path": "/srv/team/recommendations.toml", "priority": -1 }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let settings = UserSettings::load(&config_paths).unwrap();
+        expect![[r#"
+            [
+                RecommendationSourceConfig {
+                    name: "symposium",
+                    location: Url(
+                        "http://recommendations.symposium.dev/recommendations.toml",
+                    ),
+                    auth_header_env: None,
+                    priority: 0,
+                },
+                RecommendationSourceConfig {
+                    name: "acme",
+                    location: Url(
+                        "https://acme.example/recs.toml",
+                    ),
+                    auth_header_env: Some(
+                        "ACME_AUTH",
+                    ),
+                    priority: 10,
+                },
+                RecommendationSourceConfig {
+                    name: "team",
+                    location: Path(
+                        "/srv/team/recommendations.toml",
+                    ),
+                    auth_header_env: None,
+                    priority: -1,
+                },
+            ]
+        "#]]
+        .assert_debug_eq(&settings.recommendation_sources);
+
+        // Names end up in file names, so they are restricted and must be unique
+        std::fs::write(
+            &path,
+            r#"{ "recommendation-sources": [{ "name": "../evil", "path": "recs.toml" }] }"#,
+        )
+        .unwrap();
+        assert!(UserSettings::load(&config_paths).is_err());
+
+        std::fs::write(
+            &path,
+            r#"{ "recommendation-sources": [
+                { "name": "a", "path": "one.toml" },
+                { "name": "a", "path": "two.toml" }
+            ] }"#,
+        )
+        .unwrap();
+        assert!(UserSettings::load(&config_paths).is_err());
+    }
+
+    #[test]
+    fn test_user_settings_registries() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_paths = ConfigPaths::with_root(temp_dir.path());
+        let path = config_paths.user_settings_path();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        std::fs::write(
+            &path,
+            r#"{ "registries": [
+                { "id": "acme", "url": "https://acme.example/registry.json", "auth-header-env": "ACME_AUTH" },
+                { "id": "local", "path": "/srv/registry.json" }
+            ] }"#,
+        )
+        .unwrap();
+        let settings = UserSettings::load(&config_paths).unwrap();
+
+        // The public registry always comes first
+        let ids: Vec<_> = settings.registries().into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, ["public", "acme", "local"]);
+        assert_eq!(
+            settings.registries[1].location,
+            FileLocation::Path(PathBuf::from("/srv/registry.json"))
+        );
+
+        // IDs end up in namespaced entry IDs, so `:` is not allowed
+        std::fs::write(
+            &path,
+            r#"{ "registries": [{ "id": "acme:x", "path": "registry.json" }] }"#,
+        )
+        .unwrap();
+        assert!(UserSettings::load(&config_paths).is_err());
+
+        std::fs::write(
+            &path,
+            r#"{ "registries": [{ "id": "public", "path": "registry.json" }] }"#,
+        )
+        .unwrap();
+        assert!(UserSettings::load(&config_paths).is_err());
+    }
+
     #[test]
     fn test_encode_path() {
         let path = PathBuf::from("/Users/test/my-project");
@@ -540,6 +1234,25 @@ mod tests {
         assert_eq!(serialized, json);
     }
 
+    #[test]
+    fn test_workspace_agent_override() {
+        let global = GlobalAgentConfig::new(ComponentSource::Builtin("eliza".to_string()));
+
+        // Workspaces without their own agent use the global one, and don't write the field
+        let json = r#"{"mods":[]}"#;
+        let mut config: WorkspaceModsConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.agent_or(&global), global);
+        assert_eq!(serde_json::to_string(&config).unwrap(), json);
+
+        let own = GlobalAgentConfig::new(ComponentSource::Builtin("ferris".to_string()));
+        config.agent = Some(Box::new(own.clone()));
+        assert_eq!(config.agent_or(&global), own);
+        assert_eq!(
+            serde_json::to_string(&config).unwrap(),
+            r#"{"mods":[],"agent":{"agent":{"builtin":"ferris"}}}"#
+        );
+    }
+
     #[test]
     fn test_config_paths_env_override() {
         let temp_dir = tempfile::tempdir().unwrap();
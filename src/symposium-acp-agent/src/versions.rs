@@ -0,0 +1,645 @@
+//! Version pins - keeping agents and mods at a known version.
+//!
+//! `Registry`, `Npx`, `Pipx` and `Cargo` sources resolve to whatever is newest
+//! at spawn time. A pin (the `version` of a [`ModConfig`] or
+//! [`GlobalAgentConfig`]) fixes the version that is resolved instead, so a
+//! team's agent doesn't change behavior mid-week.
+//!
+//! Pins are recorded when the configuration is saved ([`record_agent_version`],
+//! [`record_mod_versions`]),
+//! and newer versions are offered through the config menu ([`available_updates`]).
+
+use crate::offline;
+use crate::registry::{self, RegistryEntry};
+use crate::user_config::{GlobalAgentConfig, ModConfig, WorkspaceModsConfig};
+use anyhow::{Context, Result, bail};
+use cargo_metadata::semver::Version;
+use futures::StreamExt;
+use serde::Deserialize;
+use std::time::Duration;
+use symposium_recommendations::{ComponentSource, NpxDistribution, PipxDistribution};
+
+/// Display name of a source, with its version pin if any (e.g. `sparkle-mcp@0.4.1`)
+pub fn pinned_name(source: &ComponentSource, version: &Option<String>) -> String {
+    match version {
+        Some(version) => format!("{}@{}", source.display_name(), version),
+        None => source.display_name(),
+    }
+}
+
+/// Apply a version pin to a source that carries its own version.
+///
+/// Returns `None` for `Registry` sources (pinned through [`pin_entry`] once
+/// the entry is fetched) and for sources without a version.
+pub fn pin_source(source: &ComponentSource, version: &str) -> Option<ComponentSource> {
+    match source {
+        ComponentSource::Npx(npx) => Some(ComponentSource::Npx(pin_npx(npx, version))),
+        ComponentSource::Pipx(pipx) => Some(ComponentSource::Pipx(pin_pipx(pipx, version))),
+        ComponentSource::Cargo(cargo) => {
+            let mut cargo = cargo.clone();
+            cargo.version = Some(version.to_string());
+            Some(ComponentSource::Cargo(cargo))
+        }
+        _ => None,
+    }
+}
+
+/// Apply a version pin to a registry entry.
+///
+/// The registry only describes the current version of an entry, so an older
+/// version can only be pinned if the entry is distributed through a package
+/// manager (npx, pipx or cargo) that can install any version.
+pub fn pin_entry(mut entry: RegistryEntry, version: &str) -> Result<RegistryEntry> {
+    if entry.version == version || entry.distribution.local.is_some() {
+        return Ok(entry);
+    }
+
+    if !can_pin(&entry) {
+        bail!(
+            "'{}' is at version {} in the registry, and its distribution cannot be pinned to version {}",
+            entry.id,
+            entry.version,
+            version
+        );
+    }
+
+    let dist = &mut entry.distribution;
+    if let Some(npx) = &mut dist.npx {
+        *npx = pin_npx(npx, version);
+    }
+    if let Some(pipx) = &mut dist.pipx {
+        *pipx = pin_pipx(pipx, version);
+    }
+    if let Some(cargo) = &mut dist.cargo {
+        cargo.version = Some(version.to_string());
+    }
+    entry.version = version.to_string();
+
+    Ok(entry)
+}
+
+/// Whether a registry entry can be pinned to a version the registry has moved past
+pub fn can_pin(entry: &RegistryEntry) -> bool {
+    let dist = &entry.distribution;
+    dist.local.is_some() || dist.npx.is_some() || dist.pipx.is_some() || dist.cargo.is_some()
+}
+
+/// Apply an optional version pin to a freshly fetched registry entry
+pub fn resolve_pin(entry: RegistryEntry, version: Option<&str>) -> Result<RegistryEntry> {
+    match version {
+        Some(version) => pin_entry(entry, version),
+        None => Ok(entry),
+    }
+}
+
+/// The npm package name without a version or tag (`@scope/pkg@1.0` -> `@scope/pkg`)
+fn npm_package_name(package: &str) -> &str {
+    match package.rfind('@') {
+        Some(index) if index > 0 => &package[..index],
+        _ => package,
+    }
+}
+
+fn pin_npx(npx: &NpxDistribution, version: &str) -> NpxDistribution {
+    NpxDistribution {
+        package: format!("{}@{}", npm_package_name(&npx.package), version),
+        ..npx.clone()
+    }
+}
+
+/// The Python package name without a version specifier (`pkg==1.0` -> `pkg`)
+fn pypi_package_name(package: &str) -> &str {
+    let end = package
+        .find(['=', '<', '>', '!', '~', '['])
+        .unwrap_or(package.len());
+    package[..end].trim()
+}
+
+fn pin_pipx(pipx: &PipxDistribution, version: &str) -> PipxDistribution {
+    PipxDistribution {
+        package: format!("{}=={}", pypi_package_name(&pipx.package), version),
+        ..pipx.clone()
+    }
+}
+
+/// Look up the newest version of a source.
+///
+/// Returns `None` for sources without a version.
+pub async fn latest_version(source: &ComponentSource) -> Result<Option<String>> {
+    match source {
+        ComponentSource::Registry(id) => {
+            let entry = registry::lookup_registry_entry(id).await?;
+            Ok(Some(entry.version).filter(|v| !v.is_empty()))
+        }
+        ComponentSource::Npx(npx) => latest_npm_version(npm_package_name(&npx.package))
+            .await
+            .map(Some),
+        ComponentSource::Pipx(pipx) => latest_pypi_version(pypi_package_name(&pipx.package))
+            .await
+            .map(Some),
+        ComponentSource::Cargo(cargo) => registry::latest_crate_version(&cargo.crate_name)
+            .await
+            .map(Some),
+        _ => Ok(None),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct NpmVersion {
+    version: String,
+}
+
+async fn latest_npm_version(package: &str) -> Result<String> {
+    if offline::is_offline() {
+        return Err(offline::not_cached(format!(
+            "npm information for `{}`",
+            package
+        )));
+    }
+
+    // Scoped packages need their `/` escaped
+    let url = format!(
+        "https://registry.npmjs.org/{}/latest",
+        package.replace('/', "%2F")
+    );
+    let response = reqwest::get(&url)
+        .await
+        .with_context(|| format!("Failed to fetch package info for {}", package))?;
+    if !response.status().is_success() {
+        bail!("Package '{}' not found on npm", package);
+    }
+
+    let latest: NpmVersion = response
+        .json()
+        .await
+        .context("Failed to parse npm response")?;
+    Ok(latest.version)
+}
+
+#[derive(Debug, Deserialize)]
+struct PypiProject {
+    info: PypiInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct PypiInfo {
+    version: String,
+}
+
+async fn latest_pypi_version(package: &str) -> Result<String> {
+    if offline::is_offline() {
+        return Err(offline::not_cached(format!(
+            "PyPI information for `{}`",
+            package
+        )));
+    }
+
+    let url = format!("https://pypi.org/pypi/{}/json", package);
+    let response = reqwest::get(&url)
+        .await
+        .with_context(|| format!("Failed to fetch package info for {}", package))?;
+    if !response.status().is_success() {
+        bail!("Package '{}' not found on PyPI", package);
+    }
+
+    let project: PypiProject = response
+        .json()
+        .await
+        .context("Failed to parse PyPI response")?;
+    Ok(project.info.version)
+}
+
+/// How long finding the version of one agent or mod may take
+const VERSION_LOOKUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many mod versions are looked up at the same time
+const MAX_CONCURRENT_LOOKUPS: usize = 4;
+
+/// Pin an unpinned agent to the version it would resolve to right now.
+///
+/// Agents whose version can't be determined (e.g. offline) are left unpinned,
+/// as are registry agents that are only distributed as binaries or over HTTP:
+/// those can only run the registry's current version.
+pub async fn record_agent_version(agent: &mut GlobalAgentConfig) {
+    if agent.version.is_none() {
+        agent.version = latest_version_or_warn(&agent.agent).await;
+    }
+}
+
+/// Pin unpinned mods, and the workspace's own agent if it has one, to the
+/// versions they would resolve to right now.
+///
+/// Mods whose version can't be determined (e.g. offline) or that can't be
+/// pinned (see [`record_agent_version`]) are left unpinned.
+pub async fn record_mod_versions(mods: &mut WorkspaceModsConfig) {
+    if let Some(agent) = &mut mods.agent {
+        record_agent_version(agent).await;
+    }
+    let unpinned: Vec<Option<ComponentSource>> = mods
+        .mods
+        .iter()
+        .map(|m| m.version.is_none().then(|| m.source.clone()))
+        .collect();
+    let versions: Vec<Option<String>> = futures::stream::iter(unpinned)
+        .map(|source| async move {
+            match source {
+                Some(source) => latest_version_or_warn(&source).await,
+                None => None,
+            }
+        })
+        .buffered(MAX_CONCURRENT_LOOKUPS)
+        .collect()
+        .await;
+    for (m, version) in mods.mods.iter_mut().zip(versions) {
+        if m.version.is_none() {
+            m.version = version;
+        }
+    }
+}
+
+/// Copy the pin recorded for an agent (see [`record_agent_version`]) to
+/// `config`, if it is the same agent and still unpinned.
+///
+/// Returns true if `config` changed.
+pub fn copy_agent_pin(pinned: &GlobalAgentConfig, config: &mut GlobalAgentConfig) -> bool {
+    if config.version.is_some() || pinned.version.is_none() || config.agent != pinned.agent {
+        return false;
+    }
+    config.version = pinned.version.clone();
+    true
+}
+
+/// Copy the pins recorded for a workspace's mods (see [`record_mod_versions`])
+/// to the mods in `mods` that are still unpinned.
+///
+/// Returns true if `mods` changed.
+pub fn copy_mod_pins(pinned: &[ModConfig], mods: &mut [ModConfig]) -> bool {
+    let mut changed = false;
+    for m in mods.iter_mut().filter(|m| m.version.is_none()) {
+        if let Some(version) = pinned
+            .iter()
+            .find(|p| p.source == m.source)
+            .and_then(|p| p.version.clone())
+        {
+            m.version = Some(version);
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// The version to pin a source to, if it can be pinned
+async fn pinnable_version(source: &ComponentSource) -> Result<Option<String>> {
+    match source {
+        ComponentSource::Registry(id) => {
+            let entry = registry::lookup_registry_entry(id).await?;
+            Ok(entry_pin(&entry))
+        }
+        _ => latest_version(source).await,
+    }
+}
+
+/// The version to pin a registry entry to, if its distribution can be pinned
+fn entry_pin(entry: &RegistryEntry) -> Option<String> {
+    Some(entry.version.clone()).filter(|v| !v.is_empty() && can_pin(entry))
+}
+
+async fn latest_version_or_warn(source: &ComponentSource) -> Option<String> {
+    let version = tokio::time::timeout(VERSION_LOOKUP_TIMEOUT, pinnable_version(source))
+        .await
+        .unwrap_or_else(|_| {
+            Err(anyhow::anyhow!(
+                "timed out after {}s",
+                VERSION_LOOKUP_TIMEOUT.as_secs()
+            ))
+        });
+    match version {
+        Ok(version) => version,
+        Err(e) => {
+            tracing::warn!(
+                "Not pinning `{}`: failed to determine its version: {:#}",
+                source.display_name(),
+                e
+            );
+            None
+        }
+    }
+}
+
+/// What an [`AvailableUpdate`] updates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateTarget {
+    /// The global agent
+    Agent,
+    /// The workspace's own agent ([`WorkspaceModsConfig::agent`])
+    WorkspaceAgent,
+    /// The mod at this index of [`WorkspaceModsConfig::mods`]
+    Mod(usize),
+}
+
+/// A newer version of a pinned agent or mod
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvailableUpdate {
+    pub target: UpdateTarget,
+    /// Display name of the agent or mod
+    pub name: String,
+    /// The pinned version
+    pub current: String,
+    /// The newest version
+    pub latest: String,
+}
+
+impl AvailableUpdate {
+    /// Move the pin to the newest version.
+    pub fn apply(&self, agent: &mut GlobalAgentConfig, mods: &mut WorkspaceModsConfig) {
+        let version = match self.target {
+            UpdateTarget::Agent => &mut agent.version,
+            UpdateTarget::WorkspaceAgent => match mods.agent.as_deref_mut() {
+                Some(GlobalAgentConfig { version, .. }) => version,
+                None => return,
+            },
+            UpdateTarget::Mod(index) => match mods.mods.get_mut(index) {
+                Some(ModConfig { version, .. }) => version,
+                None => return,
+            },
+        };
+        *version = Some(self.latest.clone());
+    }
+}
+
+/// List the pinned agent and mods that have a newer version available.
+///
+/// Sources whose newest version can't be determined are skipped.
+pub async fn available_updates(
+    agent: &GlobalAgentConfig,
+    mods: &WorkspaceModsConfig,
+) -> Vec<AvailableUpdate> {
+    // A workspace with its own agent doesn't run the global agent
+    let agent = match &mods.agent {
+        Some(agent) => (UpdateTarget::WorkspaceAgent, &agent.agent, &agent.version),
+        None => (UpdateTarget::Agent, &agent.agent, &agent.version),
+    };
+    let pinned = std::iter::once(agent).chain(
+        mods.mods
+            .iter()
+            .enumerate()
+            .map(|(index, m)| (UpdateTarget::Mod(index), &m.source, &m.version)),
+    );
+
+    let mut updates = vec![];
+    for (target, source, version) in pinned {
+        let Some(current) = version else {
+            continue;
+        };
+        let latest = match latest_version(source).await {
+            Ok(Some(latest)) => latest,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to check `{}` for updates: {:#}",
+                    source.display_name(),
+                    e
+                );
+                continue;
+            }
+        };
+        if is_newer(&latest, current) {
+            updates.push(AvailableUpdate {
+                target,
+                name: source.display_name(),
+                current: current.clone(),
+                latest,
+            });
+        }
+    }
+    updates
+}
+
+/// Whether `latest` is newer than `current` (any difference, if either isn't semver)
+fn is_newer(latest: &str, current: &str) -> bool {
+    match (Version::parse(latest), Version::parse(current)) {
+        (Ok(latest), Ok(current)) => latest > current,
+        _ => latest != current,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::Distribution;
+    use symposium_recommendations::{BinaryDistribution, CargoDistribution};
+
+    fn npx(package: &str) -> ComponentSource {
+        ComponentSource::Npx(NpxDistribution {
+            package: package.to_string(),
+            args: vec!["--acp".to_string()],
+            env: Default::default(),
+        })
+    }
+
+    #[test]
+    fn test_pin_source() {
+        let pinned = |source: &ComponentSource| pin_source(source, "1.2.3");
+
+        assert_eq!(
+            pinned(&npx("@zed-industries/claude-code-acp@latest")),
+            Some(npx("@zed-industries/claude-code-acp@1.2.3"))
+        );
+        assert_eq!(pinned(&npx("cowsay")), Some(npx("cowsay@1.2.3")));
+
+        let Some(ComponentSource::Pipx(pipx)) = pinned(&ComponentSource::Pipx(PipxDistribution {
+            package: "some-agent>=1.0".to_string(),
+            args: vec![],
+        })) else {
+            panic!("pipx sources can be pinned");
+        };
+        assert_eq!(pipx.package, "some-agent==1.2.3");
+
+        let Some(ComponentSource::Cargo(cargo)) =
+            pinned(&ComponentSource::Cargo(CargoDistribution {
+                crate_name: "sparkle-mcp".to_string(),
+                version: None,
+                binary: None,
+                args: vec![],
+            }))
+        else {
+            panic!("cargo sources can be pinned");
+        };
+        assert_eq!(cargo.version.as_deref(), Some("1.2.3"));
+
+        // Registry entries are pinned once fetched; builtins have no version
+        assert_eq!(pinned(&ComponentSource::Registry("gemini".into())), None);
+        assert_eq!(pinned(&ComponentSource::Builtin("eliza".into())), None);
+    }
+
+    fn entry(version: &str, distribution: Distribution) -> RegistryEntry {
+        RegistryEntry {
+            id: "gemini".to_string(),
+            name: "Gemini CLI".to_string(),
+            version: version.to_string(),
+            description: None,
+            distribution,
+        }
+    }
+
+    #[test]
+    fn test_pin_entry() {
+        let npx_dist = Distribution {
+            local: None,
+            npx: Some(NpxDistribution {
+                package: "@google/gemini-cli@0.22.3".to_string(),
+                args: vec![],
+                env: Default::default(),
+            }),
+            pipx: None,
+            binary: None,
+            cargo: None,
+        };
+        let pinned = pin_entry(entry("0.22.3", npx_dist), "0.21.0").unwrap();
+        assert_eq!(pinned.version, "0.21.0");
+        assert_eq!(
+            pinned.distribution.npx.unwrap().package,
+            "@google/gemini-cli@0.21.0"
+        );
+
+        // Binary archives are specific to the registry's version
+        let binary_dist = Distribution {
+            local: None,
+            npx: None,
+            pipx: None,
+            binary: Some(
+                [(
+                    "linux-x86_64".to_string(),
+                    BinaryDistribution {
+                        archive: "https://example.com/gemini-0.22.3.tar.gz".to_string(),
+                        cmd: "./gemini".to_string(),
+                        args: vec![],
+                        sha256: None,
+                    },
+                )]
+                .into(),
+            ),
+            cargo: None,
+        };
+        assert!(pin_entry(entry("0.22.3", binary_dist.clone()), "0.22.3").is_ok());
+        assert!(pin_entry(entry("0.22.3", binary_dist), "0.21.0").is_err());
+    }
+
+    #[test]
+    fn test_binary_entry_not_auto_pinned() {
+        let binary_dist = Distribution {
+            local: None,
+            npx: None,
+            pipx: None,
+            binary: Some(
+                [(
+                    "linux-x86_64".to_string(),
+                    BinaryDistribution {
+                        archive: "https://example.com/gemini.tar.gz".to_string(),
+                        cmd: "./gemini".to_string(),
+                        args: vec![],
+                        sha256: None,
+                    },
+                )]
+                .into(),
+            ),
+            cargo: None,
+        };
+
+        // Recorded when the config is saved...
+        let pin = entry_pin(&entry("0.22.3", binary_dist.clone()));
+        assert_eq!(pin, None);
+
+        // ...and still resolvable once the registry moves on
+        let resolved = resolve_pin(entry("0.23.0", binary_dist), pin.as_deref()).unwrap();
+        assert_eq!(resolved.version, "0.23.0");
+
+        // Package manager distributions keep their pin
+        let npx_dist = Distribution {
+            local: None,
+            npx: Some(NpxDistribution {
+                package: "@google/gemini-cli".to_string(),
+                args: vec![],
+                env: Default::default(),
+            }),
+            pipx: None,
+            binary: None,
+            cargo: None,
+        };
+        let pin = entry_pin(&entry("0.22.3", npx_dist.clone()));
+        assert_eq!(pin.as_deref(), Some("0.22.3"));
+        let resolved = resolve_pin(entry("0.23.0", npx_dist), pin.as_deref()).unwrap();
+        assert_eq!(resolved.version, "0.22.3");
+    }
+
+    #[test]
+    fn test_apply_update() {
+        let mut agent = GlobalAgentConfig::new(npx("agent@latest"));
+        agent.version = Some("1.0.0".to_string());
+        let mut mods = WorkspaceModsConfig::new(vec![]);
+
+        AvailableUpdate {
+            target: UpdateTarget::Agent,
+            name: "agent".to_string(),
+            current: "1.0.0".to_string(),
+            latest: "1.1.0".to_string(),
+        }
+        .apply(&mut agent, &mut mods);
+        assert_eq!(agent.version.as_deref(), Some("1.1.0"));
+
+        let mut workspace_agent = GlobalAgentConfig::new(npx("other-agent@latest"));
+        workspace_agent.version = Some("2.0.0".to_string());
+        mods.agent = Some(Box::new(workspace_agent));
+        AvailableUpdate {
+            target: UpdateTarget::WorkspaceAgent,
+            name: "other-agent".to_string(),
+            current: "2.0.0".to_string(),
+            latest: "2.1.0".to_string(),
+        }
+        .apply(&mut agent, &mut mods);
+        assert_eq!(agent.version.as_deref(), Some("1.1.0"));
+        assert_eq!(
+            mods.agent.and_then(|agent| agent.version).as_deref(),
+            Some("2.1.0")
+        );
+    }
+
+    #[test]
+    fn test_copy_mod_pins() {
+        use crate::config_commands::add_mod;
+        use symposium_recommendations::ModKind;
+
+        let mut config = WorkspaceModsConfig::new(vec![]);
+        add_mod(&mut config, ModKind::Proxy, npx("a@latest")).unwrap();
+        add_mod(&mut config, ModKind::Proxy, npx("b@latest")).unwrap();
+        let mut pinned = config.clone();
+        pinned.mods[0].version = Some("1.0.0".to_string());
+        pinned.mods[1].version = Some("2.0.0".to_string());
+
+        // Pinned by hand while the versions were looked up, and a mod added
+        config.mods[1].version = Some("1.5.0".to_string());
+        add_mod(&mut config, ModKind::Proxy, npx("c@latest")).unwrap();
+
+        assert!(copy_mod_pins(&pinned.mods, &mut config.mods));
+        let versions: Vec<_> = config.mods.iter().map(|m| m.version.as_deref()).collect();
+        assert_eq!(versions, [Some("1.0.0"), Some("1.5.0"), None]);
+        assert!(!copy_mod_pins(&pinned.mods, &mut config.mods));
+
+        // An agent's pin only applies to the same agent
+        let mut agent = GlobalAgentConfig::new(npx("agent@latest"));
+        let mut pinned = GlobalAgentConfig::new(npx("other-agent@latest"));
+        pinned.version = Some("3.0.0".to_string());
+        assert!(!copy_agent_pin(&pinned, &mut agent));
+        pinned.agent = agent.agent.clone();
+        assert!(copy_agent_pin(&pinned, &mut agent));
+        assert_eq!(agent.version.as_deref(), Some("3.0.0"));
+    }
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("1.10.0", "1.9.0"));
+        assert!(!is_newer("1.9.0", "1.10.0"));
+        assert!(!is_newer("1.0.0", "1.0.0"));
+        assert!(is_newer("2024-06", "2024-05"));
+    }
+}
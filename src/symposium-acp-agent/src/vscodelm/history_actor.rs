@@ -7,8 +7,11 @@
 
 use futures::StreamExt;
 use futures::channel::{mpsc, oneshot};
+use sacp::schema::{RequestPermissionOutcome, RequestPermissionRequest};
+use std::path::PathBuf;
 use uuid::Uuid;
 
+use super::permissions::{self, AllowedTools};
 use super::session_actor::{AgentDefinition, SessionActor};
 use super::{
     ContentPart, Message, ProvideResponseRequest, ProvideResponseResponse, ROLE_ASSISTANT,
@@ -27,7 +30,7 @@ use super::LmBackendToVsCode;
 pub enum HistoryActorMessage {
     /// A request from VS Code
     FromVsCode {
-        request: ProvideResponseRequest,
+        request: Box<ProvideResponseRequest>,
         request_id: serde_json::Value,
         request_cx: sacp::JrRequestCx<ProvideResponseResponse>,
     },
@@ -48,6 +51,11 @@ pub enum SessionToHistoryMessage {
     Complete,
     /// The session encountered an error
     Error(String),
+    /// Ask the user whether the agent may run a tool (see [`permissions`])
+    RequestPermission {
+        request: Box<RequestPermissionRequest>,
+        respond_to: oneshot::Sender<RequestPermissionOutcome>,
+    },
 }
 
 // ============================================================================
@@ -85,7 +93,7 @@ impl HistoryActorHandle {
     ) -> Result<(), sacp::Error> {
         self.tx
             .unbounded_send(HistoryActorMessage::FromVsCode {
-                request,
+                request: Box::new(request),
                 request_id,
                 request_cx,
             })
@@ -122,6 +130,8 @@ struct SessionData {
     /// Whether the internal tool (symposium-agent-action) is available.
     /// If false, all permission requests should be auto-denied.
     has_internal_tool: bool,
+    /// The workspace folder of the latest request, if VS Code sent one
+    workspace: Option<PathBuf>,
 }
 
 /// State when actively streaming a response
@@ -159,70 +169,20 @@ impl SessionData {
             provisional_messages: Vec::new(),
             streaming: None,
             has_internal_tool,
+            workspace: None,
         }
     }
 
-    /// Check if incoming messages match our expected history and return match info.
-    fn match_history(&self, incoming: &[Message]) -> Option<HistoryMatch> {
-        let committed_len = self.committed.len();
-
+    /// Check if incoming messages match our expected history and return match info,
+    /// or where they diverge from it.
+    fn match_history(&self, incoming: &[Message]) -> Result<HistoryMatch, Divergence> {
         tracing::trace!(
             ?incoming,
             ?self.committed,
             ?self.provisional_messages,
             "match_history"
         );
-
-        // Incoming must at least start with committed
-        if incoming.len() < committed_len {
-            tracing::trace!(
-                incoming_len = incoming.len(),
-                committed_len,
-                "match_history: incoming shorter than committed"
-            );
-            return None;
-        }
-        if &incoming[..committed_len] != self.committed.as_slice() {
-            tracing::trace!(committed_len, "match_history: committed prefix mismatch");
-            return None;
-        }
-
-        let after_committed = &incoming[committed_len..];
-
-        // Check if the new messages have the provisional messages as a prefix
-        if !after_committed.starts_with(&self.provisional_messages) {
-            // They do not. This must be a cancellation of the provisional content.
-            tracing::debug!(
-                after_committed_len = after_committed.len(),
-                provisional_len = self.provisional_messages.len(),
-                "match_history: provisional mismatch, marking as canceled"
-            );
-            // Log the first differing message for debugging
-            for (i, (incoming_msg, provisional_msg)) in after_committed
-                .iter()
-                .zip(&self.provisional_messages)
-                .enumerate()
-            {
-                if incoming_msg != provisional_msg {
-                    tracing::debug!(
-                        index = i,
-                        ?incoming_msg,
-                        ?provisional_msg,
-                        "match_history: first mismatch"
-                    );
-                    break;
-                }
-            }
-            return Some(HistoryMatch {
-                new_messages: after_committed.to_vec(),
-                canceled: true,
-            });
-        }
-
-        Some(HistoryMatch {
-            new_messages: after_committed[self.provisional_messages.len()..].to_vec(),
-            canceled: false,
-        })
+        match_history(&self.committed, &self.provisional_messages, incoming)
     }
 
     /// Record that we're sending a response part.
@@ -257,6 +217,151 @@ impl SessionData {
     }
 }
 
+// ============================================================================
+// History matching
+// ============================================================================
+
+/// Check if incoming messages continue the `committed + provisional` history.
+///
+/// Messages are compared with [`compare_messages`], so differences VS Code is
+/// known to introduce don't end the session. If the committed history diverges,
+/// the session can't continue; if only the provisional exchange does, it was
+/// canceled and the messages after the committed history are replayed.
+fn match_history(
+    committed: &[Message],
+    provisional: &[Message],
+    incoming: &[Message],
+) -> Result<HistoryMatch, Divergence> {
+    let committed_len = common_prefix(committed, incoming)?;
+
+    let after_committed = &incoming[committed_len..];
+
+    // Check if the new messages have the provisional messages as a prefix
+    if let Err(divergence) = common_prefix(provisional, after_committed) {
+        // They do not. This must be a cancellation of the provisional content.
+        tracing::debug!(
+            after_committed_len = after_committed.len(),
+            provisional_len = provisional.len(),
+            index = committed_len + divergence.index,
+            reason = ?divergence.reason,
+            "match_history: provisional mismatch, marking as canceled"
+        );
+        return Ok(HistoryMatch {
+            new_messages: after_committed.to_vec(),
+            canceled: true,
+        });
+    }
+
+    Ok(HistoryMatch {
+        new_messages: after_committed[provisional.len()..].to_vec(),
+        canceled: false,
+    })
+}
+
+/// Where incoming messages stop matching our history
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Divergence {
+    /// Index of the first message that doesn't match
+    index: usize,
+    reason: DivergenceReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DivergenceReason {
+    /// The incoming history ends before ours
+    Missing,
+    /// The message has a different role
+    Role,
+    /// The text of the message is different
+    Text,
+    /// The tool calls, tool results or other parts of the message are different
+    Parts,
+}
+
+/// Check that `incoming` starts with `ours`, returning the length of `ours`.
+fn common_prefix(ours: &[Message], incoming: &[Message]) -> Result<usize, Divergence> {
+    for (index, ours) in ours.iter().enumerate() {
+        let Some(theirs) = incoming.get(index) else {
+            return Err(Divergence {
+                index,
+                reason: DivergenceReason::Missing,
+            });
+        };
+        if let Some(reason) = compare_messages(ours, theirs) {
+            tracing::trace!(index, ?reason, ?ours, ?theirs, "messages differ");
+            return Err(Divergence { index, reason });
+        }
+        if ours != theirs {
+            tracing::debug!(index, ?ours, ?theirs, "ignoring benign message difference");
+        }
+    }
+    Ok(ours.len())
+}
+
+/// Compare a message we have seen or sent with the message VS Code sent back.
+///
+/// Ignores differences that don't change the conversation:
+///
+/// * whitespace in the text, which VS Code may trim or re-wrap
+/// * the order of non-text parts, e.g. tool results moved before the text
+/// * the content of tool results, which VS Code may shorten in later requests
+fn compare_messages(ours: &Message, theirs: &Message) -> Option<DivergenceReason> {
+    if ours.role != theirs.role {
+        return Some(DivergenceReason::Role);
+    }
+
+    if !words(ours).eq(words(theirs)) {
+        return Some(DivergenceReason::Text);
+    }
+
+    let mut their_parts = non_text_parts(theirs);
+    for part in non_text_parts(ours) {
+        let Some(i) = their_parts.iter().position(|p| same_part(part, p)) else {
+            return Some(DivergenceReason::Parts);
+        };
+        their_parts.swap_remove(i);
+    }
+    if !their_parts.is_empty() {
+        return Some(DivergenceReason::Parts);
+    }
+
+    None
+}
+
+/// The words of a message's text
+fn words(message: &Message) -> impl Iterator<Item = &str> {
+    message
+        .content
+        .iter()
+        .filter_map(|part| match part {
+            ContentPart::Text { value } => Some(value.split_whitespace()),
+            _ => None,
+        })
+        .flatten()
+}
+
+fn non_text_parts(message: &Message) -> Vec<&ContentPart> {
+    message
+        .content
+        .iter()
+        .filter(|part| !matches!(part, ContentPart::Text { .. }))
+        .collect()
+}
+
+/// Whether two non-text parts are the same, comparing tool results by ID only
+fn same_part(ours: &ContentPart, theirs: &ContentPart) -> bool {
+    match (ours, theirs) {
+        (
+            ContentPart::ToolResult { tool_call_id, .. },
+            ContentPart::ToolResult {
+                tool_call_id: their_id,
+                ..
+            },
+        ) => tool_call_id == their_id,
+        _ => ours == theirs,
+    }
+}
+
 // ============================================================================
 // HistoryActor
 // ============================================================================
@@ -297,7 +402,7 @@ impl HistoryActor {
                     request_id,
                     request_cx,
                 } => {
-                    self.handle_vscode_request(request, request_id, request_cx)?;
+                    self.handle_vscode_request(*request, request_id, request_cx)?;
                 }
                 HistoryActorMessage::CancelFromVsCode { request_id } => {
                     self.handle_vscode_cancel(request_id);
@@ -329,13 +434,21 @@ impl HistoryActor {
         // This ensures consistent comparison with our provisional history.
         normalize_messages(&mut request.messages);
 
-        // Find session with best history match (must also match agent)
+        // Find session with best history match (must also match agent),
+        // remembering where the others diverged for diagnostics
+        let mut divergences = Vec::new();
         let best_match = self
             .sessions
             .iter()
             .enumerate()
             .filter(|(_, s)| s.agent_definition == request.agent)
-            .filter_map(|(i, s)| s.match_history(&request.messages).map(|m| (i, m)))
+            .filter_map(|(i, s)| match s.match_history(&request.messages) {
+                Ok(m) => Some((i, m)),
+                Err(divergence) => {
+                    divergences.push((s.actor.session_id(), divergence));
+                    None
+                }
+            })
             .max_by_key(|(_, m)| !m.canceled); // prefer non-canceled matches
 
         // Check if the internal tool is available in the request options
@@ -357,7 +470,15 @@ impl HistoryActor {
             self.sessions[idx].has_internal_tool = has_internal_tool;
             (idx, history_match)
         } else {
-            // No matching session - create a new one
+            // No matching session - create a new one, which is sent the whole history
+            for (session_id, divergence) in &divergences {
+                tracing::info!(
+                    %session_id,
+                    index = divergence.index,
+                    reason = ?divergence.reason,
+                    "history diverged from session"
+                );
+            }
             let actor = SessionActor::spawn(self.handle.clone(), request.agent.clone())?;
             tracing::debug!(
                 session_id = %actor.session_id(),
@@ -377,6 +498,7 @@ impl HistoryActor {
         };
 
         let session_data = &mut self.sessions[session_idx];
+        session_data.workspace = request.workspace.take();
 
         // Handle cancellation if needed
         if history_match.canceled {
@@ -413,6 +535,7 @@ impl HistoryActor {
             history_match.canceled,
             cancel_rx,
             session_data.has_internal_tool,
+            request.capabilities,
             vscode_tools,
         );
 
@@ -497,8 +620,166 @@ impl HistoryActor {
                         .respond_with_error(sacp::Error::new(-32000, err))?;
                 }
             }
+            SessionToHistoryMessage::RequestPermission {
+                request,
+                respond_to,
+            } => {
+                // Waiting for the user must not block other sessions
+                let cx = self.cx.clone();
+                let allowed_tools = AllowedTools::for_workspace(session_data.workspace.as_deref());
+                self.cx.spawn(async move {
+                    let outcome =
+                        permissions::prompt(cx, allowed_tools, request_id, *request).await;
+                    let _ = respond_to.send(outcome);
+                    Ok(())
+                })?;
+            }
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vscodelm::ROLE_USER;
+
+    fn text(role: &str, value: &str) -> Message {
+        Message {
+            role: role.to_string(),
+            content: vec![ContentPart::Text {
+                value: value.to_string(),
+            }],
+        }
+    }
+
+    fn tool_result(id: &str, result: &str) -> ContentPart {
+        ContentPart::ToolResult {
+            tool_call_id: id.to_string(),
+            result: serde_json::json!(result),
+        }
+    }
+
+    #[test]
+    fn test_compare_messages_ignores_benign_differences() {
+        // Whitespace
+        assert_eq!(
+            compare_messages(
+                &text(ROLE_ASSISTANT, "Hello,\nworld!\n"),
+                &text(ROLE_ASSISTANT, "Hello, world!")
+            ),
+            None
+        );
+
+        // Reordered parts and a shortened tool result
+        let ours = Message {
+            role: ROLE_USER.to_string(),
+            content: vec![
+                ContentPart::Text {
+                    value: "Done".to_string(),
+                },
+                tool_result("call-1", "a long output"),
+                tool_result("call-2", "ok"),
+            ],
+        };
+        let theirs = Message {
+            role: ROLE_USER.to_string(),
+            content: vec![
+                tool_result("call-2", "ok"),
+                tool_result("call-1", "a long"),
+                ContentPart::Text {
+                    value: "Done".to_string(),
+                },
+            ],
+        };
+        assert_eq!(compare_messages(&ours, &theirs), None);
+    }
+
+    #[test]
+    fn test_compare_messages_detects_divergence() {
+        assert_eq!(
+            compare_messages(&text(ROLE_USER, "Hi"), &text(ROLE_ASSISTANT, "Hi")),
+            Some(DivergenceReason::Role)
+        );
+        assert_eq!(
+            compare_messages(&text(ROLE_USER, "Hi"), &text(ROLE_USER, "Bye")),
+            Some(DivergenceReason::Text)
+        );
+
+        // A tool call VS Code doesn't have, e.g. because the user rejected it
+        let mut ours = text(ROLE_ASSISTANT, "Let me check");
+        ours.content.push(ContentPart::ToolCall {
+            tool_call_id: "call-1".to_string(),
+            tool_name: SYMPOSIUM_AGENT_ACTION.to_string(),
+            parameters: serde_json::json!({}),
+        });
+        assert_eq!(
+            compare_messages(&ours, &text(ROLE_ASSISTANT, "Let me check")),
+            Some(DivergenceReason::Parts)
+        );
+        assert_eq!(
+            compare_messages(&text(ROLE_ASSISTANT, "Let me check"), &ours),
+            Some(DivergenceReason::Parts)
+        );
+    }
+
+    #[test]
+    fn test_match_history() {
+        let committed = vec![text(ROLE_USER, "Hi"), text(ROLE_ASSISTANT, "Hello there")];
+        let provisional = vec![
+            text(ROLE_USER, "How are you?"),
+            text(ROLE_ASSISTANT, "Fine"),
+        ];
+
+        // The committed history comes back with different whitespace
+        let incoming = vec![
+            text(ROLE_USER, "Hi\n"),
+            text(ROLE_ASSISTANT, "Hello  there"),
+            text(ROLE_USER, "How are you?"),
+            text(ROLE_ASSISTANT, "Fine"),
+            text(ROLE_USER, "Good"),
+        ];
+        let history_match = match_history(&committed, &provisional, &incoming).unwrap();
+        assert!(!history_match.canceled);
+        assert_eq!(history_match.new_messages, vec![text(ROLE_USER, "Good")]);
+
+        // The provisional exchange was replaced: replay what follows the committed history
+        let incoming = vec![
+            text(ROLE_USER, "Hi"),
+            text(ROLE_ASSISTANT, "Hello there"),
+            text(ROLE_USER, "Something else"),
+        ];
+        let history_match = match_history(&committed, &provisional, &incoming).unwrap();
+        assert!(history_match.canceled);
+        assert_eq!(
+            history_match.new_messages,
+            vec![text(ROLE_USER, "Something else")]
+        );
+
+        // The committed history was edited
+        let incoming = vec![text(ROLE_USER, "Hey"), text(ROLE_ASSISTANT, "Hello there")];
+        let divergence = match_history(&committed, &provisional, &incoming)
+            .err()
+            .unwrap();
+        assert_eq!(
+            divergence,
+            Divergence {
+                index: 0,
+                reason: DivergenceReason::Text
+            }
+        );
+
+        // The history is shorter than ours
+        let divergence = match_history(&committed, &provisional, &[text(ROLE_USER, "Hi")])
+            .err()
+            .unwrap();
+        assert_eq!(
+            divergence,
+            Divergence {
+                index: 1,
+                reason: DivergenceReason::Missing
+            }
+        );
+    }
+}
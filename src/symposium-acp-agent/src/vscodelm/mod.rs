@@ -4,19 +4,27 @@
 //! It uses sacp's JSON-RPC infrastructure for communication with the TypeScript extension.
 
 mod history_actor;
+pub mod permissions;
 pub mod session_actor;
 #[cfg(test)]
 mod tests;
+pub mod tokenizer;
 mod vscode_tools_mcp;
 
 use anyhow::Result;
 use history_actor::{HistoryActor, HistoryActorHandle};
 use sacp::{
     Component, Handled, JrConnectionCx, JrLink, JrMessageHandler, JrNotification, JrPeer,
-    JrRequest, JrResponsePayload, MessageCx, link::RemoteStyle, util::MatchMessage,
+    JrRequest, JrResponsePayload, MessageCx,
+    link::RemoteStyle,
+    schema::{
+        AvailableCommand, PermissionOption, PermissionOptionId, PlanEntry, ToolCallStatus, ToolKind,
+    },
+    util::MatchMessage,
 };
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use tokenizer::Tokenizers;
 
 /// Name of the special tool we inject into vscode for requesting permission
 const SYMPOSIUM_AGENT_ACTION: &str = "symposium-agent-action";
@@ -106,6 +114,59 @@ pub enum ContentPart {
         tool_call_id: String,
         result: serde_json::Value,
     },
+    /// Binary data such as an image (VS Code's `LanguageModelDataPart`)
+    Data {
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+        /// The data, base64-encoded
+        data: String,
+    },
+    /// Progress of an agent's tool call. Sent for the call and each of its updates.
+    ToolProgress {
+        #[serde(rename = "toolCallId")]
+        tool_call_id: String,
+        title: String,
+        status: ToolCallStatus,
+        /// Text output of the tool call, e.g. the output of a command
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        output: String,
+    },
+    /// A file edit made by an agent's tool call
+    Diff {
+        #[serde(rename = "toolCallId")]
+        tool_call_id: String,
+        path: String,
+        /// The previous content, absent for new files
+        #[serde(rename = "oldText", default, skip_serializing_if = "Option::is_none")]
+        old_text: Option<String>,
+        #[serde(rename = "newText")]
+        new_text: String,
+    },
+    /// A file an agent's tool call reads or modifies
+    FileLocation {
+        #[serde(rename = "toolCallId")]
+        tool_call_id: String,
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        line: Option<u32>,
+    },
+    /// A thought of the agent, sent once the agent moves on from it
+    Thinking {
+        value: String,
+    },
+    /// The agent's plan. Sent in full each time it changes.
+    Plan {
+        entries: Vec<PlanEntry>,
+    },
+    /// The session mode the agent switched to
+    ModeChange {
+        #[serde(rename = "modeId")]
+        mode_id: String,
+    },
+    /// The slash commands the agent accepts
+    AvailableCommands {
+        commands: Vec<AvailableCommand>,
+    },
 }
 
 /// A chat message
@@ -122,7 +183,16 @@ impl Message {
             .iter()
             .filter_map(|part| match part {
                 ContentPart::Text { value } => Some(value.as_str()),
-                ContentPart::ToolCall { .. } | ContentPart::ToolResult { .. } => None,
+                ContentPart::ToolCall { .. }
+                | ContentPart::ToolResult { .. }
+                | ContentPart::Data { .. }
+                | ContentPart::ToolProgress { .. }
+                | ContentPart::Diff { .. }
+                | ContentPart::FileLocation { .. }
+                | ContentPart::Thinking { .. }
+                | ContentPart::Plan { .. }
+                | ContentPart::ModeChange { .. }
+                | ContentPart::AvailableCommands { .. } => None,
             })
             .collect::<Vec<_>>()
             .join("")
@@ -208,6 +278,39 @@ pub struct ModelInfo {
     pub capabilities: ModelCapabilities,
 }
 
+/// What the extension supports beyond the base protocol
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientCapabilities {
+    /// Whether agent tool calls may be sent as `tool_progress`, `diff` and
+    /// `file_location` parts. Otherwise they are rendered as markdown text.
+    #[serde(default)]
+    pub structured_tool_calls: bool,
+    /// Agent updates to send besides messages and tool calls, as chosen in
+    /// the user's settings. Other updates are not shown.
+    #[serde(default)]
+    pub agent_updates: Vec<AgentUpdateKind>,
+    /// Whether the extension answers `lm/requestPermission` requests.
+    /// Otherwise agent permission requests go through the
+    /// `symposium-agent-action` tool, or are denied if it is unavailable.
+    #[serde(default)]
+    pub permission_prompts: bool,
+}
+
+/// Agent updates the user can choose to see
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentUpdateKind {
+    /// The agent's thoughts, as `thinking` parts
+    Thoughts,
+    /// The agent's plan, as `plan` parts
+    Plan,
+    /// Session mode changes, as `mode_change` parts
+    Mode,
+    /// The agent's slash commands, as `available_commands` parts
+    Commands,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ModelCapabilities {
@@ -244,11 +347,46 @@ pub struct ProvideResponseRequest {
     pub agent: session_actor::AgentDefinition,
     #[serde(default)]
     pub options: ChatRequestOptions,
+    #[serde(default)]
+    pub capabilities: ClientCapabilities,
+    /// The workspace folder of the chat, where allow-always permission
+    /// decisions are stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JrResponsePayload)]
 pub struct ProvideResponseResponse {}
 
+// ----------------------------------------------------------------------------
+// lm/requestPermission (request: backend -> vscode)
+// ----------------------------------------------------------------------------
+
+/// Ask the user whether the agent may run a tool
+#[derive(Debug, Clone, Serialize, Deserialize, JrRequest)]
+#[request(method = "lm/requestPermission", response = PermissionPromptResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionPromptRequest {
+    /// The `lm/provideLanguageModelChatResponse` request the agent is working on
+    pub request_id: serde_json::Value,
+    pub tool_call_id: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ToolKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_input: Option<serde_json::Value>,
+    /// The choices offered by the agent, in the agent's order
+    pub options: Vec<PermissionOption>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JrResponsePayload)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionPromptResponse {
+    /// The chosen option, or none if the user dismissed the prompt
+    #[serde(default)]
+    pub option_id: Option<PermissionOptionId>,
+}
+
 // ----------------------------------------------------------------------------
 // lm/responsePart (notification: backend -> vscode)
 // ----------------------------------------------------------------------------
@@ -292,7 +430,15 @@ pub struct CancelNotification {
 #[serde(rename_all = "camelCase")]
 pub struct ProvideTokenCountRequest {
     pub model_id: String,
-    pub text: String,
+    pub text: TokenCountInput,
+}
+
+/// What to count: like VS Code's `provideTokenCount`, either a string or a message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TokenCountInput {
+    Text(String),
+    Message(Message),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JrResponsePayload)]
@@ -310,12 +456,15 @@ pub struct LmBackendHandler {
     /// Handle to send messages to the HistoryActor.
     /// Created lazily on first request that needs it.
     history_handle: Option<HistoryActorHandle>,
+    /// Tokenizer for each model, for `lm/provideTokenCount`
+    tokenizers: Tokenizers,
 }
 
 impl LmBackendHandler {
-    pub fn new() -> Self {
+    pub fn new(tokenizers: Tokenizers) -> Self {
         Self {
             history_handle: None,
+            tokenizers,
         }
     }
 
@@ -367,9 +516,16 @@ impl JrMessageHandler for LmBackendHandler {
             })
             .await
             .if_request(async |req: ProvideTokenCountRequest, request_cx| {
-                // Simple heuristic: 1 token ≈ 4 characters
-                let count = (req.text.len() / 4).max(1) as u32;
-                request_cx.respond(ProvideTokenCountResponse { count })
+                let tokenizer = self.tokenizers.for_model(&req.model_id).await;
+                let count = match &req.text {
+                    TokenCountInput::Text(text) => tokenizer.count(text),
+                    TokenCountInput::Message(message) => {
+                        tokenizer::count_message(tokenizer.as_ref(), message)
+                    }
+                };
+                request_cx.respond(ProvideTokenCountResponse {
+                    count: count.max(1) as u32,
+                })
             })
             .await
             .if_request(async |req: ProvideResponseRequest, request_cx| {
@@ -418,9 +574,14 @@ pub struct LmBackend {
 }
 
 impl LmBackend {
+    /// Create the backend, counting tokens with the tokenizers from the user settings.
     pub fn new() -> Self {
+        Self::with_tokenizers(Tokenizers::from_user_settings())
+    }
+
+    pub fn with_tokenizers(tokenizers: Tokenizers) -> Self {
         Self {
-            handler: LmBackendHandler::new(),
+            handler: LmBackendHandler::new(tokenizers),
         }
     }
 }
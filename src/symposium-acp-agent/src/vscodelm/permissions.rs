@@ -0,0 +1,380 @@
+//! Permission prompts for agent tool calls
+//!
+//! When the extension supports it, an agent's `session/request_permission`
+//! becomes an `lm/requestPermission` request, for which VS Code asks the user
+//! with all the options the agent offers. When the user picks an allow-always
+//! option, the tool is recorded for the workspace (see [`AllowedTools`]) and
+//! later requests for it are approved without asking.
+//!
+//! Tools are identified by the tool name the agent reports in the request's
+//! `_meta` or `rawInput`, together with the tool kind (see [`tool_identity`]).
+//! Titles are written for people: they can be generic or include the call's
+//! arguments, so a request with no tool name is never approved automatically.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use sacp::JrConnectionCx;
+use sacp::schema::{
+    PermissionOption, PermissionOptionKind, RequestPermissionOutcome, RequestPermissionRequest,
+    SelectedPermissionOutcome, ToolCallUpdate,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{LmBackendToVsCode, PermissionPromptRequest};
+use crate::user_config::ConfigPaths;
+
+/// Serializes the load-modify-save of allowed tools files, so chats that allow
+/// tools at the same time don't drop each other's decisions
+static ALLOWED_TOOLS_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Where the allow-always decisions of a chat are stored.
+///
+/// Stored at `<config_dir>/config/<encoded-workspace-path>/allowed-tools.json`,
+/// next to the workspace config, which config mode saves independently.
+#[derive(Debug, Clone)]
+pub struct AllowedTools {
+    config_paths: ConfigPaths,
+    workspace: PathBuf,
+}
+
+impl AllowedTools {
+    pub fn new(config_paths: ConfigPaths, workspace: PathBuf) -> Self {
+        Self {
+            config_paths,
+            workspace,
+        }
+    }
+
+    /// The decisions for a workspace in the default config location
+    pub fn for_workspace(workspace: Option<&Path>) -> Option<Self> {
+        let workspace = workspace?.to_path_buf();
+        let config_paths = ConfigPaths::default_location().ok()?;
+        Some(Self::new(config_paths, workspace))
+    }
+
+    /// The tools the user always allows in the workspace
+    pub fn list(&self) -> Result<Vec<String>> {
+        let path = self
+            .config_paths
+            .workspace_allowed_tools_path(&self.workspace);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read allowed tools from {}", path.display()))?;
+        let file: AllowedToolsFile = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse allowed tools from {}", path.display()))?;
+        Ok(file.tools)
+    }
+
+    /// Whether the user always allows this tool in the workspace
+    pub fn contains(&self, tool: &str) -> bool {
+        match self.list() {
+            Ok(tools) => tools.iter().any(|t| t == tool),
+            Err(err) => {
+                tracing::warn!(?err, "failed to read allowed tools");
+                false
+            }
+        }
+    }
+
+    /// Always allow this tool in the workspace.
+    ///
+    /// Returns false if the workspace has no config yet: it is created by the
+    /// first-time setup, which would otherwise run after the tool is allowed.
+    pub async fn insert(&self, tool: &str) -> Result<bool> {
+        if !self
+            .config_paths
+            .workspace_config_path(&self.workspace)
+            .exists()
+        {
+            return Ok(false);
+        }
+        let _lock = ALLOWED_TOOLS_LOCK.lock().await;
+        let mut tools = self.list()?;
+        if !tools.iter().any(|t| t == tool) {
+            tools.push(tool.to_string());
+            self.save(tools).await?;
+        }
+        Ok(true)
+    }
+
+    /// Ask about this tool again in the workspace.
+    ///
+    /// Returns false if the tool wasn't always allowed.
+    pub async fn remove(&self, tool: &str) -> Result<bool> {
+        let _lock = ALLOWED_TOOLS_LOCK.lock().await;
+        let mut tools = self.list()?;
+        let len = tools.len();
+        tools.retain(|t| t != tool);
+        if tools.len() == len {
+            return Ok(false);
+        }
+        self.save(tools).await?;
+        Ok(true)
+    }
+
+    async fn save(&self, tools: Vec<String>) -> Result<()> {
+        self.config_paths
+            .ensure_workspace_config_dir(&self.workspace)
+            .await?;
+        let path = self
+            .config_paths
+            .workspace_allowed_tools_path(&self.workspace);
+        let content = serde_json::to_string_pretty(&AllowedToolsFile { tools })?;
+        tokio::fs::write(&path, &content)
+            .await
+            .with_context(|| format!("Failed to write allowed tools to {}", path.display()))
+    }
+}
+
+/// The contents of an allowed tools file
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct AllowedToolsFile {
+    #[serde(default)]
+    tools: Vec<String>,
+}
+
+/// Answer an agent's permission request, asking the user through VS Code
+/// unless they always allow the tool.
+///
+/// If the user dismisses the prompt, the request is rejected once.
+pub async fn prompt(
+    cx: JrConnectionCx<LmBackendToVsCode>,
+    allowed_tools: Option<AllowedTools>,
+    request_id: serde_json::Value,
+    request: RequestPermissionRequest,
+) -> RequestPermissionOutcome {
+    let tool = tool_identity(&request.tool_call);
+    let allowed_tools = allowed_tools.zip(tool.clone());
+    let fields = request.tool_call.fields;
+    let title = fields.title.unwrap_or_default();
+
+    if let Some((allowed_tools, tool)) = &allowed_tools
+        && allowed_tools.contains(tool)
+        && let Some(option) = find_option(
+            &request.options,
+            &[
+                PermissionOptionKind::AllowOnce,
+                PermissionOptionKind::AllowAlways,
+            ],
+        )
+    {
+        tracing::info!(%tool, %title, "auto-approving tool the user always allows");
+        return selected(option);
+    }
+
+    let prompt = PermissionPromptRequest {
+        request_id,
+        tool_call_id: request.tool_call.tool_call_id.to_string(),
+        title: title.clone(),
+        kind: fields.kind,
+        raw_input: fields.raw_input,
+        options: request.options.clone(),
+    };
+    let option_id = match cx.send_request(prompt).block_task().await {
+        Ok(response) => response.option_id,
+        Err(err) => {
+            tracing::warn!(?err, "permission prompt failed");
+            None
+        }
+    };
+
+    let Some(option) = option_id.and_then(|id| request.options.iter().find(|o| o.option_id == id))
+    else {
+        tracing::debug!(%title, "permission prompt dismissed");
+        return find_option(&request.options, &[PermissionOptionKind::RejectOnce])
+            .map(selected)
+            .unwrap_or(RequestPermissionOutcome::Cancelled);
+    };
+
+    if option.kind == PermissionOptionKind::AllowAlways
+        && let Some((allowed_tools, tool)) = &allowed_tools
+    {
+        match allowed_tools.insert(tool).await {
+            Ok(true) => tracing::info!(%tool, "always allowing tool in this workspace"),
+            Ok(false) => tracing::info!(%tool, "no workspace config, not remembering tool"),
+            Err(err) => tracing::warn!(?err, %tool, "failed to remember allowed tool"),
+        }
+    }
+
+    selected(option)
+}
+
+/// The identity under which allow-always decisions for a tool call are stored:
+/// `<kind>:<tool name>`, or just the tool name if the agent gives no kind.
+///
+/// The tool name is the `toolName` in the request's `_meta`, directly or in an
+/// agent's own section (such as `_meta.claudeCode.toolName`), or in `rawInput`.
+fn tool_identity(tool_call: &ToolCallUpdate) -> Option<String> {
+    let name = tool_call
+        .meta
+        .as_ref()
+        .and_then(|meta| {
+            tool_name(meta).or_else(|| {
+                meta.values()
+                    .filter_map(|value| value.as_object())
+                    .find_map(tool_name)
+            })
+        })
+        .or_else(|| {
+            tool_call
+                .fields
+                .raw_input
+                .as_ref()
+                .and_then(|input| input.as_object())
+                .and_then(tool_name)
+        })?;
+    let kind = tool_call
+        .fields
+        .kind
+        .and_then(|kind| serde_json::to_value(kind).ok())
+        .and_then(|kind| kind.as_str().map(str::to_string));
+    Some(match kind {
+        Some(kind) => format!("{}:{}", kind, name),
+        None => name.to_string(),
+    })
+}
+
+fn tool_name(map: &serde_json::Map<String, serde_json::Value>) -> Option<&str> {
+    map.get("toolName")?
+        .as_str()
+        .filter(|name| !name.is_empty())
+}
+
+/// The first option of the first of `kinds` that the agent offers
+fn find_option<'a>(
+    options: &'a [PermissionOption],
+    kinds: &[PermissionOptionKind],
+) -> Option<&'a PermissionOption> {
+    kinds
+        .iter()
+        .find_map(|kind| options.iter().find(|o| o.kind == *kind))
+}
+
+fn selected(option: &PermissionOption) -> RequestPermissionOutcome {
+    RequestPermissionOutcome::Selected(SelectedPermissionOutcome::new(option.option_id.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user_config::WorkspaceModsConfig;
+
+    fn options() -> Vec<PermissionOption> {
+        vec![
+            PermissionOption::new("always", "Always Allow", PermissionOptionKind::AllowAlways),
+            PermissionOption::new("once", "Allow", PermissionOptionKind::AllowOnce),
+            PermissionOption::new("reject", "Reject", PermissionOptionKind::RejectOnce),
+        ]
+    }
+
+    #[test]
+    fn test_find_option_prefers_earlier_kinds() {
+        let options = options();
+        let option = find_option(
+            &options,
+            &[
+                PermissionOptionKind::AllowOnce,
+                PermissionOptionKind::AllowAlways,
+            ],
+        )
+        .unwrap();
+        assert_eq!(option.name, "Allow");
+
+        let option = find_option(
+            &options[..1],
+            &[
+                PermissionOptionKind::AllowOnce,
+                PermissionOptionKind::AllowAlways,
+            ],
+        )
+        .unwrap();
+        assert_eq!(option.name, "Always Allow");
+
+        assert!(find_option(&options, &[PermissionOptionKind::RejectAlways]).is_none());
+    }
+
+    #[test]
+    fn test_tool_identity() {
+        use sacp::schema::{ToolCallUpdateFields, ToolKind};
+        use serde_json::json;
+
+        let call = |fields: ToolCallUpdateFields, meta: Option<serde_json::Value>| {
+            ToolCallUpdate::new("call-1", fields).meta(meta.and_then(|m| m.as_object().cloned()))
+        };
+
+        // The title alone identifies nothing
+        let fields = ToolCallUpdateFields::new()
+            .title("execute:Bash".to_string())
+            .kind(ToolKind::Execute);
+        assert_eq!(tool_identity(&call(fields.clone(), None)), None);
+
+        // A tool name in an agent's section of `_meta`, qualified by the kind
+        assert_eq!(
+            tool_identity(&call(
+                fields.clone(),
+                Some(json!({ "claudeCode": { "toolName": "Bash" } }))
+            )),
+            Some("execute:Bash".to_string())
+        );
+        assert_eq!(
+            tool_identity(&call(fields, Some(json!({ "toolName": "Bash" })))),
+            Some("execute:Bash".to_string())
+        );
+
+        // Or in the raw input, without a kind
+        let fields = ToolCallUpdateFields::new()
+            .title("Edit src/main.rs".to_string())
+            .raw_input(json!({ "toolName": "edit_file", "path": "src/main.rs" }));
+        assert_eq!(
+            tool_identity(&call(fields, None)),
+            Some("edit_file".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_allowed_tools_roundtrip() {
+        let root = tempfile::tempdir().unwrap();
+        let config_paths = ConfigPaths::with_root(root.path());
+        let workspace = PathBuf::from("/home/user/project");
+        let allowed_tools = AllowedTools::new(config_paths.clone(), workspace.clone());
+
+        // Nothing is remembered before the workspace is set up
+        assert!(!allowed_tools.insert("edit:Edit").await.unwrap());
+        assert!(!allowed_tools.contains("edit:Edit"));
+        assert!(
+            WorkspaceModsConfig::load(&config_paths, &workspace)
+                .unwrap()
+                .is_none()
+        );
+
+        WorkspaceModsConfig::new(vec![])
+            .save(&config_paths, &workspace)
+            .await
+            .unwrap();
+        assert!(allowed_tools.insert("edit:Edit").await.unwrap());
+        assert!(allowed_tools.insert("edit:Edit").await.unwrap());
+        assert!(allowed_tools.contains("edit:Edit"));
+        assert!(!allowed_tools.contains("execute:Bash"));
+
+        assert_eq!(allowed_tools.list().unwrap(), ["edit:Edit"]);
+
+        // Saving the workspace config keeps them
+        WorkspaceModsConfig::new(vec![])
+            .save(&config_paths, &workspace)
+            .await
+            .unwrap();
+        assert!(allowed_tools.contains("edit:Edit"));
+
+        assert!(allowed_tools.remove("edit:Edit").await.unwrap());
+        assert!(!allowed_tools.remove("edit:Edit").await.unwrap());
+        assert!(!allowed_tools.contains("edit:Edit"));
+        assert!(allowed_tools.insert("edit:Edit").await.unwrap());
+
+        // Other workspaces are unaffected
+        let other = AllowedTools::new(config_paths, PathBuf::from("/home/user/other"));
+        assert!(!other.contains("edit:Edit"));
+    }
+}
@@ -10,7 +10,8 @@ use futures::stream::Peekable;
 use futures_concurrency::future::Race;
 use sacp::JrConnectionCx;
 use sacp::schema::{
-    ToolCall, ToolCallContent, ToolCallId, ToolCallStatus, ToolCallUpdate, ToolCallUpdateFields,
+    ToolCall, ToolCallContent, ToolCallId, ToolCallLocation, ToolCallStatus, ToolCallUpdate,
+    ToolCallUpdateFields,
 };
 use sacp::{
     ClientToAgent, Component, MessageCx,
@@ -33,18 +34,22 @@ use super::history_actor::{HistoryActorHandle, SessionToHistoryMessage};
 use super::vscode_tools_mcp::{
     ToolInvocation, VscodeTool, VscodeToolsHandle, VscodeToolsMcpServer,
 };
-use super::{ContentPart, Message, ROLE_USER, SYMPOSIUM_AGENT_ACTION, ToolDefinition};
+use super::{
+    AgentUpdateKind, ClientCapabilities, ContentPart, Message, ROLE_USER, SYMPOSIUM_AGENT_ACTION,
+    ToolDefinition,
+};
 
 /// Helper to peek at the next item in a peekable stream.
 async fn peek<T>(stream: &mut Peekable<mpsc::UnboundedReceiver<T>>) -> Option<&T> {
     Pin::new(stream).peek().await
 }
 
-/// Tracks the state of tool calls and renders them to markdown.
+/// Tracks the state of tool calls and renders them as response parts.
 ///
 /// Tool calls arrive as an initial `ToolCall` followed by `ToolCallUpdate` messages.
-/// We accumulate the state and re-render the markdown on each update, streaming
-/// the result to VS Code as text parts.
+/// We accumulate the state and re-render it on each update, streaming the result
+/// to VS Code either as markdown text parts or, if the extension supports them,
+/// as structured tool progress, diff and file location parts.
 #[derive(Debug, Default)]
 struct ToolCallTracker {
     /// Current state of each tool call, keyed by tool_call_id
@@ -57,6 +62,10 @@ struct ToolCallState {
     title: String,
     status: ToolCallStatus,
     content: Vec<ToolCallContent>,
+    locations: Vec<ToolCallLocation>,
+    /// Diff and file location parts already sent, so that an update
+    /// only changing the status doesn't repeat them
+    sent: Vec<ContentPart>,
 }
 
 impl ToolCallTracker {
@@ -65,19 +74,27 @@ impl ToolCallTracker {
     }
 
     /// Process an initial tool call notification
-    fn handle_tool_call(&mut self, tool_call: ToolCall) -> String {
-        let state = ToolCallState {
-            title: tool_call.title,
-            status: tool_call.status,
-            content: tool_call.content,
-        };
-        self.tool_calls
-            .insert(tool_call.tool_call_id.clone(), state.clone());
-        self.render_tool_call(&state)
+    fn handle_tool_call(&mut self, tool_call: ToolCall, structured: bool) -> Vec<ContentPart> {
+        let state = self
+            .tool_calls
+            .entry(tool_call.tool_call_id.clone())
+            .insert_entry(ToolCallState {
+                title: tool_call.title,
+                status: tool_call.status,
+                content: tool_call.content,
+                locations: tool_call.locations,
+                sent: Vec::new(),
+            })
+            .into_mut();
+        Self::render(&tool_call.tool_call_id, state, structured)
     }
 
     /// Process a tool call update notification
-    fn handle_tool_call_update(&mut self, update: ToolCallUpdate) -> Option<String> {
+    fn handle_tool_call_update(
+        &mut self,
+        update: ToolCallUpdate,
+        structured: bool,
+    ) -> Option<Vec<ContentPart>> {
         let state = self.tool_calls.get_mut(&update.tool_call_id)?;
 
         // Apply updates
@@ -90,42 +107,25 @@ impl ToolCallTracker {
         if let Some(content) = update.fields.content {
             state.content = content;
         }
+        if let Some(locations) = update.fields.locations {
+            state.locations = locations;
+        }
 
-        // Clone to avoid borrow conflict
-        let state = state.clone();
-        Some(self.render_tool_call(&state))
+        Some(Self::render(&update.tool_call_id, state, structured))
     }
 
-    /// Render a tool call state to markdown
-    fn render_tool_call(&self, state: &ToolCallState) -> String {
-        let mut output = String::new();
-
-        // Status indicator
-        let status_icon = match state.status {
-            ToolCallStatus::Pending => "⏳",
-            ToolCallStatus::InProgress => "⚙️",
-            ToolCallStatus::Completed => "✅",
-            ToolCallStatus::Failed => "❌",
-            _ => "•",
-        };
-
-        // Header with title
-        output.push_str(&format!("{} **{}**\n", status_icon, state.title));
-
-        // Content - render in a long code fence to allow nested fences
-        if !state.content.is_empty() {
-            output.push_str("``````````\n");
-            for content in &state.content {
-                output.push_str(&tool_call_content_to_string(content));
-            }
-            // Ensure content ends with newline before closing fence
-            if !output.ends_with('\n') {
-                output.push('\n');
-            }
-            output.push_str("``````````\n");
+    fn render(
+        tool_call_id: &ToolCallId,
+        state: &mut ToolCallState,
+        structured: bool,
+    ) -> Vec<ContentPart> {
+        if structured {
+            render_structured(tool_call_id, state)
+        } else {
+            vec![ContentPart::Text {
+                value: render_tool_call(state),
+            }]
         }
-
-        output
     }
 
     /// Clear all tracked tool calls (call at end of turn)
@@ -134,6 +134,83 @@ impl ToolCallTracker {
     }
 }
 
+/// Render a tool call state to markdown
+fn render_tool_call(state: &ToolCallState) -> String {
+    let mut output = String::new();
+
+    // Status indicator
+    let status_icon = match state.status {
+        ToolCallStatus::Pending => "⏳",
+        ToolCallStatus::InProgress => "⚙️",
+        ToolCallStatus::Completed => "✅",
+        ToolCallStatus::Failed => "❌",
+        _ => "•",
+    };
+
+    // Header with title
+    output.push_str(&format!("{} **{}**\n", status_icon, state.title));
+
+    // Content - render in a long code fence to allow nested fences
+    if !state.content.is_empty() {
+        output.push_str("``````````\n");
+        for content in &state.content {
+            output.push_str(&tool_call_content_to_string(content));
+        }
+        // Ensure content ends with newline before closing fence
+        if !output.ends_with('\n') {
+            output.push('\n');
+        }
+        output.push_str("``````````\n");
+    }
+
+    output
+}
+
+/// Render a tool call state to a progress part, followed by its diffs and
+/// file locations that weren't sent before.
+fn render_structured(tool_call_id: &ToolCallId, state: &mut ToolCallState) -> Vec<ContentPart> {
+    let tool_call_id = tool_call_id.to_string();
+
+    let output = state
+        .content
+        .iter()
+        .filter(|content| !matches!(content, ToolCallContent::Diff(_)))
+        .map(tool_call_content_to_string)
+        .collect();
+    let mut parts = vec![ContentPart::ToolProgress {
+        tool_call_id: tool_call_id.clone(),
+        title: state.title.clone(),
+        status: state.status,
+        output,
+    }];
+
+    let diffs = state.content.iter().filter_map(|content| match content {
+        ToolCallContent::Diff(diff) => Some(ContentPart::Diff {
+            tool_call_id: tool_call_id.clone(),
+            path: diff.path.display().to_string(),
+            old_text: diff.old_text.clone(),
+            new_text: diff.new_text.clone(),
+        }),
+        _ => None,
+    });
+    let locations = state
+        .locations
+        .iter()
+        .map(|location| ContentPart::FileLocation {
+            tool_call_id: tool_call_id.clone(),
+            path: location.path.display().to_string(),
+            line: location.line,
+        });
+    for part in diffs.chain(locations).collect::<Vec<_>>() {
+        if !state.sent.contains(&part) {
+            state.sent.push(part.clone());
+            parts.push(part);
+        }
+    }
+
+    parts
+}
+
 /// Convert tool call content to a string representation
 fn tool_call_content_to_string(content: &ToolCallContent) -> String {
     match content {
@@ -156,6 +233,113 @@ fn tool_call_content_to_string(content: &ToolCallContent) -> String {
     }
 }
 
+/// Renders the agent's updates during a turn as response parts.
+#[derive(Debug)]
+struct TurnState {
+    tool_calls: ToolCallTracker,
+    /// Thought chunks received since the agent last did something else
+    thought: String,
+}
+
+impl TurnState {
+    fn new() -> Self {
+        Self {
+            tool_calls: ToolCallTracker::new(),
+            thought: String::new(),
+        }
+    }
+
+    /// Render an agent update, sending only the update kinds the extension asked for.
+    ///
+    /// Thought chunks are collected and sent as one part before the next update.
+    fn handle_update(
+        &mut self,
+        update: SessionUpdate,
+        capabilities: &ClientCapabilities,
+    ) -> Vec<ContentPart> {
+        let wants = |kind| capabilities.agent_updates.contains(&kind);
+
+        if let SessionUpdate::AgentThoughtChunk(chunk) = update {
+            if wants(AgentUpdateKind::Thoughts) {
+                self.thought
+                    .push_str(&content_block_to_string(&chunk.content));
+            }
+            return vec![];
+        }
+
+        let mut parts: Vec<ContentPart> = self.take_thought().into_iter().collect();
+        let structured = capabilities.structured_tool_calls;
+        match update {
+            SessionUpdate::AgentMessageChunk(chunk) => {
+                let text = content_block_to_string(&chunk.content);
+                if !text.is_empty() {
+                    parts.push(ContentPart::Text { value: text });
+                }
+            }
+            SessionUpdate::ToolCall(tool_call) => {
+                parts.extend(self.tool_calls.handle_tool_call(tool_call, structured));
+            }
+            SessionUpdate::ToolCallUpdate(update) => {
+                parts.extend(
+                    self.tool_calls
+                        .handle_tool_call_update(update, structured)
+                        .unwrap_or_default(),
+                );
+            }
+            SessionUpdate::Plan(plan) if wants(AgentUpdateKind::Plan) => {
+                parts.push(ContentPart::Plan {
+                    entries: plan.entries,
+                });
+            }
+            SessionUpdate::CurrentModeUpdate(update) if wants(AgentUpdateKind::Mode) => {
+                parts.push(ContentPart::ModeChange {
+                    mode_id: update.current_mode_id.to_string(),
+                });
+            }
+            SessionUpdate::AvailableCommandsUpdate(update) if wants(AgentUpdateKind::Commands) => {
+                parts.push(ContentPart::AvailableCommands {
+                    commands: update.available_commands,
+                });
+            }
+            _ => {
+                // Ignore other update types
+            }
+        }
+        parts
+    }
+
+    /// Take the thought collected so far, if any
+    fn take_thought(&mut self) -> Option<ContentPart> {
+        let value = std::mem::take(&mut self.thought);
+        let trimmed = value.trim();
+        if trimmed.is_empty() {
+            None
+        } else {
+            Some(ContentPart::Thinking {
+                value: trimmed.to_string(),
+            })
+        }
+    }
+
+    /// Clear the state of the turn (call at end of turn)
+    fn clear(&mut self) {
+        self.tool_calls.clear();
+        self.thought.clear();
+    }
+}
+
+/// Send parts to the history actor, which forwards them to VS Code.
+fn send_parts(
+    history_handle: &HistoryActorHandle,
+    session_id: Uuid,
+    parts: impl IntoIterator<Item = ContentPart>,
+) -> Result<(), sacp::Error> {
+    for part in parts {
+        history_handle.send_from_session(session_id, SessionToHistoryMessage::Part(part))?;
+    }
+    Ok(())
+}
+
 /// Defines which agent backend to use for a session.
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -191,8 +375,10 @@ pub struct RequestState {
     /// Cancelation channel for this request
     pub cancel_rx: oneshot::Receiver<()>,
     /// Whether the internal tool (symposium-agent-action) is available.
-    /// If false, all permission requests should be auto-denied.
+    /// If false, and the extension cannot prompt, permission requests are auto-denied.
     pub has_internal_tool: bool,
+    /// What the extension supports and which agent updates the user wants to see
+    pub capabilities: ClientCapabilities,
 }
 
 impl RequestState {
@@ -242,6 +428,7 @@ impl SessionActor {
         canceled: bool,
         cancel_rx: oneshot::Receiver<()>,
         has_internal_tool: bool,
+        capabilities: ClientCapabilities,
         vscode_tools: Vec<ToolDefinition>,
     ) {
         let _ = self.tx.unbounded_send(SessionRequest {
@@ -250,6 +437,7 @@ impl SessionActor {
             state: RequestState {
                 cancel_rx,
                 has_internal_tool,
+                capabilities,
             },
             vscode_tools,
         });
@@ -379,7 +567,7 @@ impl SessionActor {
 
         tracing::debug!(%session_id, "session created with VS Code tools MCP server, waiting for messages");
 
-        let mut tool_call_tracker = ToolCallTracker::new();
+        let mut turn = TurnState::new();
 
         while let Some(request) = request_rx.next().await {
             let new_message_count = request.messages.len();
@@ -453,7 +641,7 @@ impl SessionActor {
                                     &history_handle,
                                     &mut request_rx,
                                     request_state,
-                                    &mut tool_call_tracker,
+                                    &mut turn,
                                     &tools_handle,
                                     session_id,
                                 )
@@ -466,6 +654,7 @@ impl SessionActor {
                             }
                             sacp::SessionMessage::StopReason(stop_reason) => {
                                 tracing::debug!(%session_id, ?stop_reason, "agent turn complete");
+                                send_parts(&history_handle, session_id, turn.take_thought())?;
                                 break false;
                             }
                             other => {
@@ -487,6 +676,8 @@ impl SessionActor {
                             "received VS Code tool invocation from MCP server"
                         );
 
+                        send_parts(&history_handle, session_id, turn.take_thought())?;
+
                         // Handle the tool invocation (emit ToolCall to VS Code, wait for result)
                         match Self::handle_vscode_tool_invocation(
                             invocation,
@@ -517,8 +708,8 @@ impl SessionActor {
                 history_handle.send_from_session(session_id, SessionToHistoryMessage::Complete)?;
             }
 
-            // Clear tool call state for next turn
-            tool_call_tracker.clear();
+            // Clear tool call and thought state for next turn
+            turn.clear();
         }
 
         tracing::debug!(%session_id, "session actor shutting down");
@@ -534,51 +725,25 @@ impl SessionActor {
         history_handle: &HistoryActorHandle,
         request_rx: &mut Peekable<mpsc::UnboundedReceiver<SessionRequest>>,
         request_state: RequestState,
-        tool_call_tracker: &mut ToolCallTracker,
+        turn: &mut TurnState,
         tools_handle: &VscodeToolsHandle,
         session_id: Uuid,
     ) -> Result<Option<RequestState>, sacp::Error> {
         use sacp::util::MatchMessage;
 
         let has_internal_tool = request_state.has_internal_tool;
+        let capabilities = request_state.capabilities.clone();
         let mut return_value = Some(request_state);
 
         MatchMessage::new(message)
             .if_notification(async |notif: SessionNotification| {
-                match notif.update {
-                    SessionUpdate::AgentMessageChunk(chunk) => {
-                        let text = content_block_to_string(&chunk.content);
-                        if !text.is_empty() {
-                            history_handle.send_from_session(
-                                session_id,
-                                SessionToHistoryMessage::Part(ContentPart::Text { value: text }),
-                            )?;
-                        }
-                    }
-                    SessionUpdate::ToolCall(tool_call) => {
-                        let markdown = tool_call_tracker.handle_tool_call(tool_call);
-                        history_handle.send_from_session(
-                            session_id,
-                            SessionToHistoryMessage::Part(ContentPart::Text { value: markdown }),
-                        )?;
-                    }
-                    SessionUpdate::ToolCallUpdate(update) => {
-                        if let Some(markdown) = tool_call_tracker.handle_tool_call_update(update) {
-                            history_handle.send_from_session(
-                                session_id,
-                                SessionToHistoryMessage::Part(ContentPart::Text { value: markdown }),
-                            )?;
-                        }
-                    }
-                    _ => {
-                        // Ignore other update types
-                    }
-                }
-                Ok(())
+                let parts = turn.handle_update(notif.update, &capabilities);
+                send_parts(history_handle, session_id, parts)
             })
             .await
             .if_request(async |perm_request: RequestPermissionRequest, request_cx| {
                 tracing::debug!(%session_id, has_internal_tool, ?perm_request, "received permission request");
+                send_parts(history_handle, session_id, turn.take_thought())?;
 
                 // Check if this is a VS Code tool - if so, auto-approve
                 // VS Code tools are ones we injected via our vscode_tools MCP server
@@ -599,6 +764,39 @@ impl SessionActor {
                     return Ok(());
                 }
 
+                // If the extension can prompt the user, wait for their answer
+                // unless VS Code cancels the response first
+                if capabilities.permission_prompts {
+                    let (respond_to, outcome_rx) = oneshot::channel();
+                    history_handle.send_from_session(
+                        session_id,
+                        SessionToHistoryMessage::RequestPermission {
+                            request: Box::new(perm_request),
+                            respond_to,
+                        },
+                    )?;
+
+                    let outcome = Race::race((
+                        async { Some(outcome_rx.await.unwrap_or(RequestPermissionOutcome::Cancelled)) },
+                        return_value.as_mut().unwrap().on_cancel(None),
+                    ))
+                    .await;
+
+                    match outcome {
+                        Some(outcome) => {
+                            request_cx.respond(RequestPermissionResponse::new(outcome))?;
+                        }
+                        None => {
+                            tracing::debug!(%session_id, "canceled while waiting for permission");
+                            request_cx.respond(RequestPermissionResponse::new(
+                                RequestPermissionOutcome::Cancelled,
+                            ))?;
+                            return_value = None;
+                        }
+                    }
+                    return Ok(());
+                }
+
                 // If the internal tool is not available, auto-deny all permission requests
                 if !has_internal_tool {
                     tracing::info!(%session_id, "auto-denying permission request: internal tool not available");
@@ -889,7 +1087,18 @@ fn content_block_to_string(block: &sacp::schema::ContentBlock) -> String {
 mod tests {
     use super::*;
     use expect_test::expect;
-    use sacp::schema::{ContentBlock, TextContent, ToolKind};
+    use sacp::schema::{
+        ContentBlock, ContentChunk, CurrentModeUpdate, Diff, Plan, PlanEntry, PlanEntryPriority,
+        PlanEntryStatus, TextContent, ToolKind,
+    };
+
+    /// The markdown of parts rendered without structured tool calls
+    fn markdown(parts: Vec<ContentPart>) -> String {
+        match parts.as_slice() {
+            [ContentPart::Text { value }] => value.clone(),
+            _ => panic!("expected a single text part, got {parts:?}"),
+        }
+    }
 
     #[test]
     fn test_tool_call_tracker_initial_call() {
@@ -899,7 +1108,7 @@ mod tests {
             .kind(ToolKind::Read)
             .status(ToolCallStatus::InProgress);
 
-        let markdown = tracker.handle_tool_call(tool_call);
+        let markdown = markdown(tracker.handle_tool_call(tool_call, false));
 
         expect![[r#"
             ⚙️ **Read src/main.rs**
@@ -921,7 +1130,7 @@ mod tests {
                 .into(),
             ]);
 
-        let markdown = tracker.handle_tool_call(tool_call);
+        let markdown = markdown(tracker.handle_tool_call(tool_call, false));
 
         expect![[r#"
             ✅ **grep -n pattern file.rs**
@@ -941,7 +1150,7 @@ mod tests {
         let tool_call = ToolCall::new("test-789", "Running cargo build")
             .kind(ToolKind::Execute)
             .status(ToolCallStatus::InProgress);
-        tracker.handle_tool_call(tool_call);
+        tracker.handle_tool_call(tool_call, false);
 
         // Update with completion and content
         let update = ToolCallUpdate::new(
@@ -953,7 +1162,7 @@ mod tests {
                 ]),
         );
 
-        let markdown = tracker.handle_tool_call_update(update).unwrap();
+        let markdown = markdown(tracker.handle_tool_call_update(update, false).unwrap());
 
         expect![[r#"
             ✅ **Running cargo build**
@@ -973,6 +1182,155 @@ mod tests {
             ToolCallUpdateFields::new().status(ToolCallStatus::Completed),
         );
 
-        assert!(tracker.handle_tool_call_update(update).is_none());
+        assert!(tracker.handle_tool_call_update(update, false).is_none());
+    }
+
+    #[test]
+    fn test_tool_call_tracker_structured() {
+        let mut tracker = ToolCallTracker::new();
+
+        let tool_call = ToolCall::new("test-edit", "Edit src/lib.rs")
+            .kind(ToolKind::Edit)
+            .status(ToolCallStatus::InProgress)
+            .locations(vec![ToolCallLocation::new("src/lib.rs").line(3)]);
+        let parts = tracker.handle_tool_call(tool_call, true);
+
+        expect![[r#"
+            [
+                ToolProgress {
+                    tool_call_id: "test-edit",
+                    title: "Edit src/lib.rs",
+                    status: InProgress,
+                    output: "",
+                },
+                FileLocation {
+                    tool_call_id: "test-edit",
+                    path: "src/lib.rs",
+                    line: Some(
+                        3,
+                    ),
+                },
+            ]
+        "#]]
+        .assert_debug_eq(&parts);
+
+        // The location was already sent; only the diff is new
+        let update = ToolCallUpdate::new(
+            "test-edit",
+            ToolCallUpdateFields::new()
+                .status(ToolCallStatus::Completed)
+                .content(vec![
+                    Diff::new("src/lib.rs", "fn new() {}\n")
+                        .old_text("fn old() {}\n")
+                        .into(),
+                    ContentBlock::Text(TextContent::new("Edited 1 line")).into(),
+                ]),
+        );
+        let parts = tracker.handle_tool_call_update(update, true).unwrap();
+
+        expect![[r#"
+            [
+                ToolProgress {
+                    tool_call_id: "test-edit",
+                    title: "Edit src/lib.rs",
+                    status: Completed,
+                    output: "Edited 1 line",
+                },
+                Diff {
+                    tool_call_id: "test-edit",
+                    path: "src/lib.rs",
+                    old_text: Some(
+                        "fn old() {}\n",
+                    ),
+                    new_text: "fn new() {}\n",
+                },
+            ]
+        "#]]
+        .assert_debug_eq(&parts);
+    }
+
+    fn thought(text: &str) -> SessionUpdate {
+        SessionUpdate::AgentThoughtChunk(ContentChunk::new(text.into()))
+    }
+
+    fn message(text: &str) -> SessionUpdate {
+        SessionUpdate::AgentMessageChunk(ContentChunk::new(text.into()))
+    }
+
+    #[test]
+    fn test_turn_state_collects_thoughts() {
+        let mut turn = TurnState::new();
+        let capabilities = ClientCapabilities {
+            agent_updates: vec![AgentUpdateKind::Thoughts],
+            ..Default::default()
+        };
+
+        assert!(
+            turn.handle_update(thought("Let me "), &capabilities)
+                .is_empty()
+        );
+        assert!(
+            turn.handle_update(thought("check.\n"), &capabilities)
+                .is_empty()
+        );
+        let parts = turn.handle_update(message("Done."), &capabilities);
+
+        expect![[r#"
+            [
+                Thinking {
+                    value: "Let me check.",
+                },
+                Text {
+                    value: "Done.",
+                },
+            ]
+        "#]]
+        .assert_debug_eq(&parts);
+        assert!(turn.take_thought().is_none());
+    }
+
+    #[test]
+    fn test_turn_state_sends_only_chosen_updates() {
+        let plan = || {
+            SessionUpdate::Plan(Plan::new(vec![PlanEntry::new(
+                "Write tests",
+                PlanEntryPriority::High,
+                PlanEntryStatus::InProgress,
+            )]))
+        };
+        let mode = || SessionUpdate::CurrentModeUpdate(CurrentModeUpdate::new("architect"));
+
+        // Older extensions see none of them
+        let mut turn = TurnState::new();
+        let capabilities = ClientCapabilities::default();
+        assert!(turn.handle_update(thought("Hmm"), &capabilities).is_empty());
+        assert!(turn.handle_update(plan(), &capabilities).is_empty());
+        assert!(turn.handle_update(mode(), &capabilities).is_empty());
+
+        let capabilities = ClientCapabilities {
+            agent_updates: vec![AgentUpdateKind::Plan, AgentUpdateKind::Mode],
+            ..Default::default()
+        };
+        let mut parts = turn.handle_update(plan(), &capabilities);
+        parts.extend(turn.handle_update(mode(), &capabilities));
+
+        expect![[r#"
+            [
+                Plan {
+                    entries: [
+                        PlanEntry {
+                            content: "Write tests",
+                            priority: High,
+                            status: InProgress,
+                            meta: None,
+                        },
+                    ],
+                },
+                ModeChange {
+                    mode_id: "architect",
+                },
+            ]
+        "#]]
+        .assert_debug_eq(&parts);
     }
 }
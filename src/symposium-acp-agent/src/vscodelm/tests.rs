@@ -52,12 +52,14 @@ async fn test_provide_info() -> Result<(), sacp::Error> {
 #[tokio::test]
 async fn test_provide_token_count() -> Result<(), sacp::Error> {
     VsCodeToLmBackend::builder()
-        .connect_to(LmBackend::new())?
+        .connect_to(LmBackend::with_tokenizers(Tokenizers::new(
+            Default::default(),
+        )))?
         .run_until(async |cx| {
             let response = cx
                 .send_request(ProvideTokenCountRequest {
                     model_id: "symposium-eliza".to_string(),
-                    text: "Hello, world!".to_string(),
+                    text: TokenCountInput::Text("Hello, world!".to_string()),
                 })
                 .block_task()
                 .await?;
@@ -74,6 +76,43 @@ async fn test_provide_token_count() -> Result<(), sacp::Error> {
         .await
 }
 
+#[tokio::test]
+async fn test_provide_token_count_for_message() -> Result<(), sacp::Error> {
+    // VS Code may pass a whole message instead of a string
+    let request: ProvideTokenCountRequest = serde_json::from_value(serde_json::json!({
+        "modelId": "symposium-eliza",
+        "text": {
+            "role": "user",
+            "content": [
+                { "type": "text", "value": "Hello, world!" },
+                { "type": "tool_call", "toolCallId": "1", "toolName": "read", "parameters": {} },
+                { "type": "data", "mimeType": "image/webp", "data": "" }
+            ]
+        }
+    }))
+    .unwrap();
+    assert!(matches!(request.text, TokenCountInput::Message(_)));
+
+    VsCodeToLmBackend::builder()
+        .connect_to(LmBackend::with_tokenizers(Tokenizers::new(
+            Default::default(),
+        )))?
+        .run_until(async |cx| {
+            let response = cx.send_request(request).block_task().await?;
+
+            // The text, the tool name and a WebP image of unknown size
+            expect![[r#"
+                ProvideTokenCountResponse {
+                    count: 1604,
+                }
+            "#]]
+            .assert_debug_eq(&response);
+
+            Ok(())
+        })
+        .await
+}
+
 #[test]
 fn test_chat_request_options_deserialization() {
     // Test deserializing options from TypeScript format
@@ -103,6 +142,132 @@ fn test_chat_request_options_default() {
     assert_eq!(options.tool_mode, None);
 }
 
+#[test]
+fn test_client_capabilities_default() {
+    // Older extensions don't send capabilities: they get markdown tool calls,
+    // no other agent updates and no permission prompts
+    let json = r#"{
+        "modelId": "symposium-eliza",
+        "messages": [],
+        "agent": {"eliza": {}}
+    }"#;
+    let request: ProvideResponseRequest = serde_json::from_str(json).unwrap();
+    assert!(!request.capabilities.structured_tool_calls);
+    assert!(request.capabilities.agent_updates.is_empty());
+    assert!(!request.capabilities.permission_prompts);
+    assert_eq!(request.workspace, None);
+
+    let capabilities: ClientCapabilities = serde_json::from_str(
+        r#"{"structuredToolCalls": true, "agentUpdates": ["thoughts", "plan"]}"#,
+    )
+    .unwrap();
+    assert!(capabilities.structured_tool_calls);
+    assert_eq!(
+        capabilities.agent_updates,
+        vec![AgentUpdateKind::Thoughts, AgentUpdateKind::Plan]
+    );
+}
+
+#[test]
+fn test_tool_call_parts_serialization() {
+    let parts = vec![
+        ContentPart::ToolProgress {
+            tool_call_id: "call-1".to_string(),
+            title: "Edit src/lib.rs".to_string(),
+            status: sacp::schema::ToolCallStatus::InProgress,
+            output: String::new(),
+        },
+        ContentPart::Diff {
+            tool_call_id: "call-1".to_string(),
+            path: "src/lib.rs".to_string(),
+            old_text: None,
+            new_text: "fn main() {}\n".to_string(),
+        },
+        ContentPart::FileLocation {
+            tool_call_id: "call-1".to_string(),
+            path: "src/lib.rs".to_string(),
+            line: Some(3),
+        },
+    ];
+    let json = serde_json::to_string_pretty(&parts).unwrap();
+
+    expect![[r#"
+        [
+          {
+            "type": "tool_progress",
+            "toolCallId": "call-1",
+            "title": "Edit src/lib.rs",
+            "status": "in_progress"
+          },
+          {
+            "type": "diff",
+            "toolCallId": "call-1",
+            "path": "src/lib.rs",
+            "newText": "fn main() {}\n"
+          },
+          {
+            "type": "file_location",
+            "toolCallId": "call-1",
+            "path": "src/lib.rs",
+            "line": 3
+          }
+        ]"#]]
+    .assert_eq(&json);
+
+    let parsed: Vec<ContentPart> = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, parts);
+}
+
+#[test]
+fn test_permission_prompt_serialization() {
+    use sacp::schema::{PermissionOption, PermissionOptionKind, ToolKind};
+
+    let request = PermissionPromptRequest {
+        request_id: serde_json::json!(1),
+        tool_call_id: "toolu_1".to_string(),
+        title: "Edit src/lib.rs".to_string(),
+        kind: Some(ToolKind::Edit),
+        raw_input: None,
+        options: vec![
+            PermissionOption::new(
+                "allow_always",
+                "Always Allow",
+                PermissionOptionKind::AllowAlways,
+            ),
+            PermissionOption::new("reject", "Reject", PermissionOptionKind::RejectOnce),
+        ],
+    };
+    let json = serde_json::to_string_pretty(&request).unwrap();
+
+    expect![[r#"
+        {
+          "requestId": 1,
+          "toolCallId": "toolu_1",
+          "title": "Edit src/lib.rs",
+          "kind": "edit",
+          "options": [
+            {
+              "optionId": "allow_always",
+              "name": "Always Allow",
+              "kind": "allow_always"
+            },
+            {
+              "optionId": "reject",
+              "name": "Reject",
+              "kind": "reject_once"
+            }
+          ]
+        }"#]]
+    .assert_eq(&json);
+
+    // A dismissed prompt has no option
+    let response: PermissionPromptResponse = serde_json::from_str(r#"{"optionId": null}"#).unwrap();
+    assert!(response.option_id.is_none());
+    let response: PermissionPromptResponse =
+        serde_json::from_str(r#"{"optionId": "reject"}"#).unwrap();
+    assert_eq!(response.option_id.unwrap().to_string(), "reject");
+}
+
 #[test]
 fn test_agent_definition_eliza_serialization() {
     use super::session_actor::AgentDefinition;
@@ -210,6 +375,8 @@ async fn send_chat(
             tools,
             tool_mode: Some(ToolMode::Auto),
         },
+        capabilities: ClientCapabilities::default(),
+        workspace: None,
     })
     .block_task()
     .await?;
@@ -366,6 +533,8 @@ async fn test_multi_turn_conversation() -> Result<(), sacp::Error> {
                     deterministic: true,
                 },
                 options: ChatRequestOptions::default(),
+                capabilities: ClientCapabilities::default(),
+                workspace: None,
             })
             .block_task()
             .await?;
@@ -555,6 +724,8 @@ async fn test_mcp_invoke_tool() -> Result<(), sacp::Error> {
                     tools,
                     tool_mode: Some(ToolMode::Auto),
                 },
+                capabilities: ClientCapabilities::default(),
+                workspace: None,
             })
             .block_task()
             .await?;
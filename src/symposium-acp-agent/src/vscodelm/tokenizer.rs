@@ -0,0 +1,435 @@
+//! Token counting for `lm/provideTokenCount`.
+//!
+//! VS Code budgets a model's context window with these counts. Models listed
+//! in the `tokenizers` user setting are counted with a byte-pair encoding
+//! loaded from a local vocabulary file; other models (or ones whose vocabulary
+//! can't be loaded) fall back to [`HeuristicTokenizer`].
+
+use super::{ContentPart, Message};
+use crate::user_config::{ConfigPaths, UserSettings};
+use anyhow::{Context, Result, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use fxhash::FxHashMap;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Counts the tokens of a text for some model.
+pub trait Tokenizer: Send + Sync {
+    fn count(&self, text: &str) -> usize;
+}
+
+/// Estimates one token per four bytes of text.
+pub struct HeuristicTokenizer;
+
+impl Tokenizer for HeuristicTokenizer {
+    fn count(&self, text: &str) -> usize {
+        text.len() / 4
+    }
+}
+
+/// Pre-tokenization pattern of OpenAI's `cl100k_base`, which splits text into
+/// pieces that are encoded separately.
+///
+/// The original ends with `\s+(?!\S)|\s+`; the `regex` crate has no lookahead,
+/// so [`BpeTokenizer::pieces`] emulates it.
+const PIECE_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+";
+
+/// Pieces longer than this are encoded in chunks, as merging is quadratic in
+/// the length of a piece (e.g. a long base64 string). This may overcount by
+/// a token per chunk.
+const MAX_PIECE_LEN: usize = 512;
+
+/// A byte-pair encoding tokenizer, as used by GPT and Claude models.
+pub struct BpeTokenizer {
+    /// Rank of each token; lower ranks are merged first
+    ranks: FxHashMap<Vec<u8>, u32>,
+    pattern: Regex,
+}
+
+impl BpeTokenizer {
+    /// Load a vocabulary file in the tiktoken format: one token per line, as
+    /// its base64-encoded bytes followed by its rank.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read vocabulary from {}", path.display()))?;
+        Self::from_vocabulary(&content)
+            .with_context(|| format!("Failed to parse vocabulary from {}", path.display()))
+    }
+
+    pub fn from_vocabulary(content: &str) -> Result<Self> {
+        let mut ranks = FxHashMap::default();
+        for (line, line_number) in content.lines().zip(1..) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Some((token, rank)) = line.split_once(' ') else {
+                bail!("line {}: expected a token and its rank", line_number);
+            };
+            let token = BASE64
+                .decode(token)
+                .with_context(|| format!("line {}: invalid base64", line_number))?;
+            let rank = rank
+                .trim()
+                .parse()
+                .with_context(|| format!("line {}: invalid rank", line_number))?;
+            ranks.insert(token, rank);
+        }
+        if ranks.is_empty() {
+            bail!("the vocabulary is empty");
+        }
+
+        Ok(Self {
+            ranks,
+            pattern: Regex::new(PIECE_PATTERN).expect("valid pattern"),
+        })
+    }
+
+    /// Split text into the pieces that are encoded separately.
+    fn pieces<'t>(&self, text: &'t str) -> Vec<&'t str> {
+        let mut pieces = vec![];
+        let mut start = 0;
+        while let Some(m) = self.pattern.find_at(text, start) {
+            let mut end = m.end();
+
+            // `\s+(?!\S)`: whitespace before other text leaves its last
+            // character to the next piece (so ` world` stays one piece)
+            let piece = m.as_str();
+            if end < text.len()
+                && piece.chars().all(char::is_whitespace)
+                && !piece.ends_with(['\r', '\n'])
+                && let Some((last, _)) = piece.char_indices().last()
+                && last > 0
+            {
+                end = m.start() + last;
+            }
+
+            pieces.push(&text[m.start()..end]);
+            start = end;
+        }
+        pieces
+    }
+
+    /// Number of tokens a piece is encoded to.
+    fn count_piece(&self, piece: &[u8]) -> usize {
+        if piece.len() <= 1 || self.ranks.contains_key(piece) {
+            return piece.len().min(1);
+        }
+
+        // Start with one part per byte, and merge the adjacent parts whose
+        // concatenation has the lowest rank until none can be merged
+        let mut starts: Vec<usize> = (0..=piece.len()).collect();
+        loop {
+            let best = (0..starts.len() - 2)
+                .filter_map(|i| {
+                    self.ranks
+                        .get(&piece[starts[i]..starts[i + 2]])
+                        .map(|&rank| (rank, i))
+                })
+                .min();
+            let Some((_, i)) = best else {
+                break;
+            };
+            starts.remove(i + 1);
+        }
+        starts.len() - 1
+    }
+}
+
+impl Tokenizer for BpeTokenizer {
+    fn count(&self, text: &str) -> usize {
+        self.pieces(text)
+            .into_iter()
+            .flat_map(|piece| piece.as_bytes().chunks(MAX_PIECE_LEN))
+            .map(|chunk| self.count_piece(chunk))
+            .sum()
+    }
+}
+
+/// The tokenizer for each model, loaded when the model is first counted.
+pub struct Tokenizers {
+    /// Vocabulary file for each model ID
+    vocabularies: BTreeMap<String, PathBuf>,
+    /// Tokenizers loaded so far, by model ID
+    loaded: FxHashMap<String, Arc<dyn Tokenizer>>,
+}
+
+impl Tokenizers {
+    pub fn new(vocabularies: BTreeMap<String, PathBuf>) -> Self {
+        Self {
+            vocabularies,
+            loaded: FxHashMap::default(),
+        }
+    }
+
+    /// Tokenizers configured in the user settings.
+    ///
+    /// If the settings can't be loaded, every model uses the heuristic.
+    pub fn from_user_settings() -> Self {
+        let vocabularies = ConfigPaths::default_location().and_then(|config_paths| {
+            let settings = UserSettings::load(&config_paths)?;
+            Ok(settings.tokenizer_paths(&config_paths))
+        });
+        match vocabularies {
+            Ok(vocabularies) => Self::new(vocabularies),
+            Err(e) => {
+                tracing::warn!("Failed to load tokenizer settings: {:#}", e);
+                Self::new(BTreeMap::new())
+            }
+        }
+    }
+
+    /// The tokenizer for a model.
+    ///
+    /// Vocabularies are several megabytes, so they are parsed on a blocking thread.
+    pub async fn for_model(&mut self, model_id: &str) -> Arc<dyn Tokenizer> {
+        if let Some(tokenizer) = self.loaded.get(model_id) {
+            return tokenizer.clone();
+        }
+
+        let tokenizer: Arc<dyn Tokenizer> = match self.vocabularies.get(model_id) {
+            Some(path) => match load_blocking(path.clone()).await {
+                Ok(tokenizer) => Arc::new(tokenizer),
+                Err(e) => {
+                    tracing::warn!(model_id, "Counting tokens with a heuristic: {:#}", e);
+                    Arc::new(HeuristicTokenizer)
+                }
+            },
+            None => Arc::new(HeuristicTokenizer),
+        };
+        self.loaded.insert(model_id.to_string(), tokenizer.clone());
+        tokenizer
+    }
+}
+
+async fn load_blocking(path: PathBuf) -> Result<BpeTokenizer> {
+    tokio::task::spawn_blocking(move || BpeTokenizer::load(&path))
+        .await
+        .unwrap_or_else(|e| Err(e.into()))
+}
+
+/// Number of tokens of a message, including its tool calls, tool results and images.
+pub fn count_message(tokenizer: &dyn Tokenizer, message: &Message) -> usize {
+    message
+        .content
+        .iter()
+        .map(|part| count_part(tokenizer, part))
+        .sum()
+}
+
+fn count_part(tokenizer: &dyn Tokenizer, part: &ContentPart) -> usize {
+    match part {
+        ContentPart::Text { value } => tokenizer.count(value),
+        ContentPart::ToolCall {
+            tool_name,
+            parameters,
+            ..
+        } => tokenizer.count(tool_name) + tokenizer.count(&parameters.to_string()),
+        ContentPart::ToolResult { result, .. } => count_json(tokenizer, result),
+        ContentPart::Data { mime_type, data } => {
+            let bytes = BASE64.decode(data).unwrap_or_default();
+            if mime_type.starts_with("image/") {
+                image_tokens(&bytes)
+            } else {
+                tokenizer.count(&String::from_utf8_lossy(&bytes))
+            }
+        }
+        // Shown to the user but never sent to the agent
+        ContentPart::ToolProgress { .. }
+        | ContentPart::Diff { .. }
+        | ContentPart::FileLocation { .. }
+        | ContentPart::Thinking { .. }
+        | ContentPart::Plan { .. }
+        | ContentPart::ModeChange { .. }
+        | ContentPart::AvailableCommands { .. } => 0,
+    }
+}
+
+/// Tokens of a tool result, counting only the text of the parts VS Code
+/// sends (e.g. `[{"value": "..."}]`) rather than their JSON syntax.
+fn count_json(tokenizer: &dyn Tokenizer, value: &serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::String(text) => tokenizer.count(text),
+        serde_json::Value::Array(parts) => parts.iter().map(|p| count_json(tokenizer, p)).sum(),
+        serde_json::Value::Object(part) => match part.get("value") {
+            Some(value) => count_json(tokenizer, value),
+            None => tokenizer.count(&value.to_string()),
+        },
+        value => tokenizer.count(&value.to_string()),
+    }
+}
+
+/// Most tokens an image costs: larger images are scaled down first.
+const MAX_IMAGE_TOKENS: usize = 1600;
+
+/// Pixels per token of an image.
+const PIXELS_PER_IMAGE_TOKEN: usize = 750;
+
+/// Tokens of an image, as Claude counts them: one per 750 pixels, up to
+/// [`MAX_IMAGE_TOKENS`]. Images of an unknown format count as the maximum.
+fn image_tokens(bytes: &[u8]) -> usize {
+    match image_dimensions(bytes) {
+        Some((width, height)) => ((width as usize * height as usize)
+            .div_ceil(PIXELS_PER_IMAGE_TOKEN))
+        .min(MAX_IMAGE_TOKENS),
+        None => MAX_IMAGE_TOKENS,
+    }
+}
+
+/// Width and height of a PNG, GIF or JPEG image, read from its header.
+fn image_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let be_u16 = |at: usize| Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?));
+
+    // PNG: the signature, then the IHDR chunk starting with width and height
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let be_u32 = |at: usize| Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+        return Some((be_u32(16)?, be_u32(20)?));
+    }
+
+    // GIF: the logical screen size follows the signature
+    if bytes.starts_with(b"GIF8") {
+        let le_u16 = |at: usize| Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?));
+        return Some((le_u16(6)?.into(), le_u16(8)?.into()));
+    }
+
+    // JPEG: the size is in the first start-of-frame segment
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        let mut at = 2;
+        while *bytes.get(at)? == 0xFF {
+            let marker = *bytes.get(at + 1)?;
+            // SOF0 to SOF15, except DHT, JPG and DAC which share the range
+            if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+                return Some((be_u16(at + 7)?.into(), be_u16(at + 5)?.into()));
+            }
+            at += 2 + usize::from(be_u16(at + 2)?);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expect_test::expect;
+
+    /// A vocabulary of all single bytes, plus some merges.
+    fn vocabulary(merges: &[&str]) -> String {
+        let tokens = (0..=255u8)
+            .map(|byte| vec![byte])
+            .chain(merges.iter().map(|merge| merge.as_bytes().to_vec()));
+        tokens
+            .zip(0..)
+            .map(|(token, rank)| format!("{} {}\n", BASE64.encode(token), rank))
+            .collect()
+    }
+
+    #[test]
+    fn test_pieces() {
+        let tokenizer = BpeTokenizer::from_vocabulary(&vocabulary(&[])).unwrap();
+        expect![[r#"
+            [
+                "Hello",
+                " ",
+                " world",
+                "'s",
+                "\n\n",
+                "foo",
+                " ",
+                "123",
+                "456",
+                " {}",
+                "  ",
+            ]
+        "#]]
+        .assert_debug_eq(&tokenizer.pieces("Hello  world's\n\nfoo 123456 {}  "));
+    }
+
+    #[test]
+    fn test_bpe_count() {
+        let tokenizer =
+            BpeTokenizer::from_vocabulary(&vocabulary(&["he", "ll", "hell", "hello", " w"]))
+                .unwrap();
+        assert_eq!(tokenizer.count(""), 0);
+        assert_eq!(tokenizer.count("hello"), 1);
+        // ` world`: ` w`, then each of `orld`
+        assert_eq!(tokenizer.count("hello world"), 6);
+        // `he` and `ll` merge first, then `hell` and `hello`
+        assert_eq!(tokenizer.count("hellohello"), 2);
+        assert_eq!(tokenizer.count("helloo"), 2);
+    }
+
+    #[test]
+    fn test_vocabulary_errors() {
+        assert!(BpeTokenizer::from_vocabulary("").is_err());
+        assert!(BpeTokenizer::from_vocabulary("aGk=").is_err());
+        assert!(BpeTokenizer::from_vocabulary("not-base64! 0").is_err());
+        assert!(BpeTokenizer::from_vocabulary("aGk= first").is_err());
+    }
+
+    #[test]
+    fn test_count_message_parts() {
+        // A 100x150 PNG: signature, IHDR length and type, width and height
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(100u32.to_be_bytes());
+        png.extend(150u32.to_be_bytes());
+
+        let message = Message {
+            role: "user".to_string(),
+            content: vec![
+                ContentPart::Text {
+                    value: "12345678".to_string(),
+                },
+                ContentPart::ToolCall {
+                    tool_call_id: "call-1".to_string(),
+                    tool_name: "read".to_string(),
+                    parameters: serde_json::json!({"path": "a"}),
+                },
+                ContentPart::ToolResult {
+                    tool_call_id: "call-1".to_string(),
+                    result: serde_json::json!([{"value": "contents"}]),
+                },
+                ContentPart::Data {
+                    mime_type: "image/png".to_string(),
+                    data: BASE64.encode(&png),
+                },
+            ],
+        };
+
+        // 2 + (1 + 3) + 2 + 20
+        assert_eq!(count_message(&HeuristicTokenizer, &message), 28);
+    }
+
+    #[test]
+    fn test_image_dimensions() {
+        let gif = b"GIF89a\x40\x01\xf0\x00";
+        assert_eq!(image_dimensions(gif), Some((320, 240)));
+
+        // SOI, an APP0 segment, then SOF0 with precision, height and width
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x02,
+            0x00, 0x03, 0x00,
+        ];
+        assert_eq!(image_dimensions(&jpeg), Some((768, 512)));
+
+        assert_eq!(image_dimensions(b"not an image"), None);
+        assert_eq!(image_tokens(b"not an image"), MAX_IMAGE_TOKENS);
+    }
+
+    #[tokio::test]
+    async fn test_tokenizer_per_model() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("model.tiktoken");
+        std::fs::write(&path, vocabulary(&["hello"])).unwrap();
+
+        let mut tokenizers = Tokenizers::new(BTreeMap::from([
+            ("bpe-model".to_string(), path),
+            ("missing".to_string(), dir.path().join("missing.tiktoken")),
+        ]));
+        assert_eq!(tokenizers.for_model("bpe-model").await.count("hello"), 1);
+        assert_eq!(tokenizers.for_model("missing").await.count("hello"), 1);
+        assert_eq!(tokenizers.for_model("other").await.count("hello hello"), 2);
+    }
+}
@@ -89,6 +89,10 @@ async fn test_mcp_server_injected_and_used() -> Result<(), sacp::Error> {
         source: source.clone(),
         enabled: true,
         when: When::default(),
+        order: 0,
+        version: None,
+        policy: Default::default(),
+        overrides: Default::default(),
     });
 
     mods_config
@@ -1,6 +1,7 @@
 //! Cache management for extracted crates
 
 use crate::{FerrisError, Result};
+use semver::Version;
 use std::path::PathBuf;
 
 /// Manages access to cargo's cache and our extraction cache
@@ -56,11 +57,55 @@ impl CacheManager {
         }
 
         // 4. Download and extract
+        if super::is_offline() {
+            return Err(FerrisError::NotCachedOffline(format!(
+                "`{}` {}",
+                crate_name, version
+            )));
+        }
         extractor
             .download_and_extract_crate(crate_name, version, &extraction_path)
             .await
     }
 
+    /// Versions of a crate available in cargo's caches (extracted sources or .crate files)
+    pub fn cached_versions(&self, crate_name: &str) -> Result<Vec<Version>> {
+        let prefix = format!("{}-", crate_name);
+        let mut versions = Vec::new();
+
+        for (subdir, suffix) in [("src", ""), ("cache", ".crate")] {
+            let dir = self.cargo_cache_dir.join(subdir);
+            if !dir.exists() {
+                continue;
+            }
+
+            // Look for registry directories (e.g., index.crates.io-*)
+            for registry in std::fs::read_dir(dir)? {
+                let registry = registry?;
+                if !registry.file_type()?.is_dir()
+                    || !registry.file_name().to_string_lossy().starts_with("index.")
+                {
+                    continue;
+                }
+
+                for entry in std::fs::read_dir(registry.path())? {
+                    let name = entry?.file_name().to_string_lossy().to_string();
+                    let version = name
+                        .strip_prefix(&prefix)
+                        .and_then(|rest| rest.strip_suffix(suffix))
+                        .and_then(|v| Version::parse(v).ok());
+                    if let Some(version) = version
+                        && !versions.contains(&version)
+                    {
+                        versions.push(version);
+                    }
+                }
+            }
+        }
+
+        Ok(versions)
+    }
+
     /// Find extracted crate in cargo's src cache
     fn find_cargo_extracted_crate(
         &self,
@@ -13,6 +13,17 @@ pub use cache::CacheManager;
 pub use extraction::CrateExtractor;
 pub use version::VersionResolver;
 
+/// Check whether offline mode is enabled; if so, only cargo's caches are used.
+///
+/// Symposium sets `SYMPOSIUM_OFFLINE=1` when it runs offline. Any value other
+/// than empty, `0` or `false` enables it.
+pub(crate) fn is_offline() -> bool {
+    match std::env::var("SYMPOSIUM_OFFLINE") {
+        Ok(value) => !matches!(value.trim(), "" | "0" | "false"),
+        Err(_) => false,
+    }
+}
+
 /// Result of fetching a crate's sources
 #[derive(Debug, Clone)]
 pub struct FetchResult {
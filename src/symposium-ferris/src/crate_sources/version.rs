@@ -38,10 +38,14 @@ impl VersionResolver {
 
     /// Find crate version in current project's dependencies
     fn find_in_current_project(&self, crate_name: &str) -> Result<String> {
-        let metadata = MetadataCommand::new()
+        let mut command = MetadataCommand::new();
+        command
             .features(CargoOpt::AllFeatures)
-            .current_dir(&self.cwd)
-            .exec()?;
+            .current_dir(&self.cwd);
+        if super::is_offline() {
+            command.other_options(vec!["--offline".to_string()]);
+        }
+        let metadata = command.exec()?;
 
         // Look through all packages in the resolved dependency graph
         for package in metadata.packages {
@@ -79,8 +83,18 @@ impl VersionResolver {
             })
     }
 
-    /// Get latest version from crates.io
+    /// Get latest version from crates.io (offline: the latest cached version)
     async fn get_latest_version(&self, crate_name: &str) -> Result<String> {
+        if super::is_offline() {
+            return self
+                .get_available_versions(crate_name)
+                .await?
+                .into_iter()
+                .max()
+                .map(|v| v.to_string())
+                .ok_or_else(|| FerrisError::NotCachedOffline(format!("`{}`", crate_name)));
+        }
+
         let client = crates_io_api::AsyncClient::new(
             "symposium-ferris (https://github.com/symposium-dev/symposium)",
             std::time::Duration::from_millis(1000),
@@ -95,8 +109,12 @@ impl VersionResolver {
         Ok(crate_info.crate_data.max_version)
     }
 
-    /// Get all available versions from crates.io
+    /// Get all available versions from crates.io (offline: from cargo's caches)
     async fn get_available_versions(&self, crate_name: &str) -> Result<Vec<Version>> {
+        if super::is_offline() {
+            return super::CacheManager::new()?.cached_versions(crate_name);
+        }
+
         let client = crates_io_api::AsyncClient::new(
             "symposium-ferris (https://github.com/symposium-dev/symposium)",
             std::time::Duration::from_millis(1000),
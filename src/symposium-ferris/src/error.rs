@@ -35,6 +35,11 @@ pub enum FerrisError {
         crate_name: String,
         constraint: String,
     },
+    /// Offline mode is enabled and the crate is not available locally
+    #[error(
+        "Offline mode is enabled (SYMPOSIUM_OFFLINE) and {0} is not in the cargo registry cache"
+    )]
+    NotCachedOffline(String),
     /// Other error
     #[error("Error: {0}")]
     Other(String),
@@ -5,6 +5,7 @@
 //! - [`Recommendation`] - A recommended mod with source and conditions
 //! - [`ComponentSource`] - How to obtain and run a component (cargo, npx, etc.)
 //! - [`When`] - Conditions for when a recommendation applies
+//! - [`offline`] - How mods learn that Symposium runs in offline mode
 //!
 //! # Example
 //!
@@ -24,11 +25,12 @@
 //! assert_eq!(recs.mods.len(), 2);
 //! ```
 
+pub mod offline;
 mod source;
 mod when;
 
 pub use source::*;
-pub use when::When;
+pub use when::{CrateRequirement, FileContains, When};
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
@@ -214,20 +216,40 @@ fn serialize_when(when: &When) -> Result<Vec<String>> {
         lines.push(format!("when.files-exist = [{}]", arr));
     }
 
-    if let Some(crate_name) = &when.using_crate {
+    if let Some(pattern) = &when.glob {
+        lines.push(format!("when.glob = \"{}\"", escape_toml_string(pattern)));
+    }
+
+    if let Some(file_contains) = &when.file_contains {
         lines.push(format!(
-            "when.using-crate = \"{}\"",
-            escape_toml_string(crate_name)
+            "when.file-contains = {}",
+            json_to_toml_inline(&serde_json::to_value(file_contains)?)
         ));
     }
 
-    if let Some(crate_names) = &when.using_crates {
-        let arr = crate_names
-            .iter()
-            .map(|c| format!("\"{}\"", escape_toml_string(c)))
-            .collect::<Vec<_>>()
-            .join(", ");
-        lines.push(format!("when.using-crates = [{}]", arr));
+    if let Some(krate) = &when.using_crate {
+        lines.push(format!(
+            "when.using-crate = {}",
+            json_to_toml_inline(&serde_json::to_value(krate)?)
+        ));
+    }
+
+    if let Some(krates) = &when.using_crates {
+        lines.push(format!(
+            "when.using-crates = {}",
+            json_to_toml_inline(&serde_json::to_value(krates)?)
+        ));
+    }
+
+    if let Some(name) = &when.env_var_set {
+        lines.push(format!(
+            "when.env-var-set = \"{}\"",
+            escape_toml_string(name)
+        ));
+    }
+
+    if let Some(condition) = &when.not {
+        lines.push(format!("when.not = {}", serialize_when_inline(condition)?));
     }
 
     if let Some(conditions) = &when.any {
@@ -390,4 +412,40 @@ when.file-exists = "Cargo.toml"
         assert_eq!(recs.mods[1].display_name(), "symposium-cargo");
         assert_eq!(recs.mods[2].display_name(), "symposium-rust-analyzer");
     }
+
+    #[test]
+    fn test_to_toml_compact_format_rich_conditions() {
+        use expect_test::expect;
+
+        let file = r#"
+[recommendation]
+source.cargo = { crate = "symposium-proto" }
+when.glob = "**/*.proto"
+when.file-contains = { path = "Cargo.toml", regex = 'edition\s*=\s*"2024"' }
+when.using-crate = { name = "tokio", version = ">=1.30" }
+when.using-crates = ["serde", { name = "prost", version = "0.13" }]
+when.env-var-set = "CI"
+when.not = { file-exists = "package.json" }
+"#;
+
+        let combined = Recommendations::concatenate_files(&[file]).unwrap();
+
+        expect![[r#"
+            [[recommendation]]
+            kind = "proxy"
+            source.cargo = { crate = "symposium-proto" }
+            when.glob = "**/*.proto"
+            when.file-contains = { path = "Cargo.toml", regex = "edition\\s*=\\s*\"2024\"" }
+            when.using-crate = { name = "tokio", version = ">=1.30" }
+            when.using-crates = ["serde", { name = "prost", version = "0.13" }]
+            when.env-var-set = "CI"
+            when.not = { file-exists = "package.json" }
+        "#]]
+        .assert_eq(&combined);
+
+        // Verify it round-trips
+        let original = Recommendations::parse_single(file).unwrap();
+        let recs = Recommendations::from_toml(&combined).unwrap();
+        assert_eq!(recs.mods[0].when, original.when);
+    }
 }
@@ -0,0 +1,17 @@
+//! Offline mode as seen by mods
+//!
+//! `symposium-acp-agent --offline` sets `SYMPOSIUM_OFFLINE=1` in the
+//! environment of the components it spawns. Mods that download things should
+//! check [`offline_from_env`] and only use what is already cached.
+
+/// Environment variable that enables offline mode.
+/// Any value other than empty, `0` or `false` enables it.
+pub const SYMPOSIUM_OFFLINE_ENV: &str = "SYMPOSIUM_OFFLINE";
+
+/// Whether offline mode is enabled through [`SYMPOSIUM_OFFLINE_ENV`].
+pub fn offline_from_env() -> bool {
+    match std::env::var(SYMPOSIUM_OFFLINE_ENV) {
+        Ok(value) => !matches!(value.trim(), "" | "0" | "false"),
+        Err(_) => false,
+    }
+}
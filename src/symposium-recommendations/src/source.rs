@@ -141,6 +141,9 @@ pub struct BinaryDistribution {
     pub cmd: String,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub args: Vec<String>,
+    /// Expected SHA-256 of the archive (hex). When set, downloads are verified against it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
 }
 
 /// An HTTP header to set when making requests.
@@ -27,6 +27,15 @@ use serde::{Deserialize, Serialize};
 ///     { file-exists = "package.json" },
 /// ]
 /// ```
+///
+/// Crate version ranges, globs, file contents and environment variables:
+/// ```toml
+/// when.using-crate = { name = "tokio", version = ">=1.30" }
+/// when.glob = "**/*.proto"
+/// when.file-contains = { path = "Cargo.toml", regex = "edition\\s*=\\s*\"2024\"" }
+/// when.env-var-set = "CI"
+/// when.not = { file-exists = "package.json" }
+/// ```
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(default, rename_all = "kebab-case")]
 pub struct When {
@@ -38,13 +47,29 @@ pub struct When {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub files_exist: Option<Vec<String>>,
 
+    /// At least one file in the workspace must match this glob (e.g. `**/*.proto`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub glob: Option<String>,
+
+    /// A file in the workspace must exist and its contents match a regex
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_contains: Option<FileContains>,
+
     /// Single crate must be a dependency
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub using_crate: Option<String>,
+    pub using_crate: Option<CrateRequirement>,
 
     /// All crates must be dependencies (AND)
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub using_crates: Option<Vec<String>>,
+    pub using_crates: Option<Vec<CrateRequirement>>,
+
+    /// Environment variable must be set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env_var_set: Option<String>,
+
+    /// This condition must NOT match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub not: Option<Box<When>>,
 
     /// Any of these conditions must match (OR)
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -55,6 +80,68 @@ pub struct When {
     pub all: Option<Vec<When>>,
 }
 
+/// A file whose contents must match a regular expression
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileContains {
+    /// Path relative to the workspace root
+    pub path: String,
+    /// Regular expression searched for in the file contents
+    pub regex: String,
+}
+
+/// A crate dependency, optionally restricted to a semver range.
+///
+/// Written either as a plain name or as a table with a version requirement:
+/// ```toml
+/// when.using-crate = "tokio"
+/// when.using-crate = { name = "tokio", version = ">=1.30" }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CrateRequirement {
+    /// Any version of the crate
+    Name(String),
+    /// A crate whose resolved version matches `version` (a semver requirement)
+    Versioned {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version: Option<String>,
+    },
+}
+
+impl CrateRequirement {
+    /// The crate name
+    pub fn name(&self) -> &str {
+        match self {
+            CrateRequirement::Name(name) => name,
+            CrateRequirement::Versioned { name, .. } => name,
+        }
+    }
+
+    /// The semver requirement, if any
+    pub fn version(&self) -> Option<&str> {
+        match self {
+            CrateRequirement::Name(_) => None,
+            CrateRequirement::Versioned { version, .. } => version.as_deref(),
+        }
+    }
+}
+
+impl From<&str> for CrateRequirement {
+    fn from(name: &str) -> Self {
+        CrateRequirement::Name(name.to_string())
+    }
+}
+
+impl std::fmt::Display for CrateRequirement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.version() {
+            Some(version) => write!(f, "`{}` ({})", self.name(), version),
+            None => write!(f, "`{}`", self.name()),
+        }
+    }
+}
+
 impl When {
     /// Explain why this condition causes a recommendation to be added
     pub fn explain_why_added(&self) -> Vec<String> {
@@ -70,16 +157,33 @@ impl When {
             }
         }
 
-        if let Some(crate_name) = &self.using_crate {
-            reasons.push(format!("because using crate `{crate_name}`"));
+        if let Some(pattern) = &self.glob {
+            reasons.push(format!("because files matching `{pattern}` exist"));
+        }
+
+        if let Some(FileContains { path, regex }) = &self.file_contains {
+            reasons.push(format!("because `{path}` matches `{regex}`"));
+        }
+
+        if let Some(krate) = &self.using_crate {
+            reasons.push(format!("because using crate {krate}"));
         }
 
-        if let Some(crate_names) = &self.using_crates {
-            for name in crate_names {
-                reasons.push(format!("because using crate `{name}`"));
+        if let Some(krates) = &self.using_crates {
+            for krate in krates {
+                reasons.push(format!("because using crate {krate}"));
             }
         }
 
+        if let Some(name) = &self.env_var_set {
+            reasons.push(format!("because environment variable `{name}` is set"));
+        }
+
+        if let Some(condition) = &self.not {
+            // The negated condition is added when the inner one is stale
+            reasons.extend(condition.explain_why_stale());
+        }
+
         if let Some(conditions) = &self.any {
             // For 'any', just list one that matches
             for c in conditions {
@@ -114,16 +218,35 @@ impl When {
             }
         }
 
-        if let Some(crate_name) = &self.using_crate {
-            reasons.push(format!("because no longer using crate `{crate_name}`"));
+        if let Some(pattern) = &self.glob {
+            reasons.push(format!("because no files match `{pattern}` anymore"));
+        }
+
+        if let Some(FileContains { path, regex }) = &self.file_contains {
+            reasons.push(format!("because `{path}` no longer matches `{regex}`"));
         }
 
-        if let Some(crate_names) = &self.using_crates {
-            for name in crate_names {
-                reasons.push(format!("because no longer using crate `{name}`"));
+        if let Some(krate) = &self.using_crate {
+            reasons.push(format!("because no longer using crate {krate}"));
+        }
+
+        if let Some(krates) = &self.using_crates {
+            for krate in krates {
+                reasons.push(format!("because no longer using crate {krate}"));
             }
         }
 
+        if let Some(name) = &self.env_var_set {
+            reasons.push(format!(
+                "because environment variable `{name}` is no longer set"
+            ));
+        }
+
+        if let Some(condition) = &self.not {
+            // The negated condition is stale when the inner one applies
+            reasons.extend(condition.explain_why_added());
+        }
+
         if let Some(conditions) = &self.any {
             // For 'any', all must fail for it to be stale
             for c in conditions {
@@ -168,7 +291,7 @@ mod tests {
     fn test_explain_why_added() {
         let when = When {
             file_exists: Some("Cargo.toml".to_string()),
-            using_crate: Some("serde".to_string()),
+            using_crate: Some("serde".into()),
             ..Default::default()
         };
 
@@ -199,4 +322,49 @@ mod tests {
         let parsed: When = toml::from_str(&toml).unwrap();
         assert_eq!(when, parsed);
     }
+
+    #[test]
+    fn test_using_crate_with_version() {
+        let when: When = toml::from_str(
+            r#"
+using-crate = { name = "tokio", version = ">=1.30" }
+using-crates = ["serde", { name = "regex" }]
+"#,
+        )
+        .unwrap();
+
+        let krate = when.using_crate.as_ref().unwrap();
+        assert_eq!(krate.name(), "tokio");
+        assert_eq!(krate.version(), Some(">=1.30"));
+
+        let krates = when.using_crates.as_ref().unwrap();
+        assert_eq!(krates[0].name(), "serde");
+        assert_eq!(krates[1].name(), "regex");
+        assert_eq!(krates[1].version(), None);
+
+        assert_eq!(
+            when.explain_why_added()[0],
+            "because using crate `tokio` (>=1.30)"
+        );
+    }
+
+    #[test]
+    fn test_not_inverts_explanations() {
+        let when = When {
+            not: Some(Box::new(When {
+                file_exists: Some("package.json".to_string()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            when.explain_why_added(),
+            vec!["because `package.json` no longer exists".to_string()]
+        );
+        assert_eq!(
+            when.explain_why_stale(),
+            vec!["because `package.json` exists".to_string()]
+        );
+    }
 }
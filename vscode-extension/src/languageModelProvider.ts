@@ -60,8 +60,105 @@ type ContentPart =
       type: "tool_result";
       toolCallId: string;
       result: unknown;
+    }
+  | {
+      type: "data";
+      mimeType: string;
+      /** Base64-encoded */
+      data: string;
+    }
+  | DisplayPart;
+
+/**
+ * Structured parts for an agent's tool calls, streamed in responses when we
+ * announce the `structuredToolCalls` capability.
+ */
+type ToolPart =
+  | {
+      type: "tool_progress";
+      toolCallId: string;
+      title: string;
+      status: "pending" | "in_progress" | "completed" | "failed";
+      output?: string;
+    }
+  | {
+      type: "diff";
+      toolCallId: string;
+      path: string;
+      oldText?: string;
+      newText: string;
+    }
+  | {
+      type: "file_location";
+      toolCallId: string;
+      path: string;
+      line?: number;
     };
 
+/**
+ * Agent updates the user chose to see (`symposium.lmAgentUpdates`), streamed
+ * in responses when we list them in the `agentUpdates` capability.
+ */
+type AgentUpdatePart =
+  | { type: "thinking"; value: string }
+  | {
+      type: "plan";
+      entries: Array<{
+        content: string;
+        priority: "high" | "medium" | "low";
+        status: "pending" | "in_progress" | "completed";
+      }>;
+    }
+  | { type: "mode_change"; modeId: string }
+  | {
+      type: "available_commands";
+      commands: Array<{ name: string; description: string }>;
+    };
+
+/**
+ * Response parts that are shown to the user but not sent to the agent.
+ */
+type DisplayPart = ToolPart | AgentUpdatePart;
+
+/**
+ * An agent's request to run a tool (`lm/requestPermission`), sent when we
+ * announce the `permissionPrompts` capability.
+ */
+interface PermissionPromptParams {
+  requestId: number;
+  toolCallId: string;
+  title: string;
+  kind?: string;
+  rawInput?: unknown;
+  options: Array<{
+    optionId: string;
+    name: string;
+    kind: "allow_once" | "allow_always" | "reject_once" | "reject_always";
+  }>;
+}
+
+/**
+ * Mime type of the data parts that carry a `DisplayPart` through VS Code's history.
+ */
+const DISPLAY_PART_MIMETYPE = "application/vnd.symposium.display-part+json";
+
+/**
+ * A long code fence, so that tool output can contain fences of its own.
+ */
+const LONG_FENCE = "`".repeat(10);
+
+function isDisplayPart(part: ContentPart): part is DisplayPart {
+  return (
+    part.type === "tool_progress" ||
+    part.type === "diff" ||
+    part.type === "file_location" ||
+    part.type === "thinking" ||
+    part.type === "plan" ||
+    part.type === "mode_change" ||
+    part.type === "available_commands"
+  );
+}
+
 interface JsonRpcMessage {
   jsonrpc: "2.0";
   id?: number | string;
@@ -218,6 +315,18 @@ export class SymposiumLanguageModelProvider
               part.parameters,
             ),
           );
+        } else if (isDisplayPart(part)) {
+          // Show the part as markdown, followed by the part itself so that
+          // `contentToArray` can restore it when VS Code sends the history back
+          pending.progress.report(
+            new vscode.LanguageModelTextPart(this.renderDisplayPart(part)),
+          );
+          pending.progress.report(
+            new vscode.LanguageModelDataPart(
+              Buffer.from(JSON.stringify(part)),
+              DISPLAY_PART_MIMETYPE,
+            ),
+          );
         }
       }
       return;
@@ -228,6 +337,23 @@ export class SymposiumLanguageModelProvider
       return;
     }
 
+    // Handle requests from the backend
+    if (msg.method !== undefined && msg.id !== undefined) {
+      if (msg.method === "lm/requestPermission") {
+        void this.promptForPermission(
+          msg.id,
+          msg.params as PermissionPromptParams,
+        );
+      } else {
+        logger.warn("lm-provider", `unknown request method: ${msg.method}`);
+        this.sendResponse(msg.id, undefined, {
+          code: -32601,
+          message: `Method not found: ${msg.method}`,
+        });
+      }
+      return;
+    }
+
     // Handle responses
     if (msg.id !== undefined) {
       const id = typeof msg.id === "string" ? parseInt(msg.id, 10) : msg.id;
@@ -260,6 +386,53 @@ export class SymposiumLanguageModelProvider
     proc.stdin?.write(json + "\n");
   }
 
+  /**
+   * Send a JSON-RPC response to a request from the backend
+   */
+  private sendResponse(
+    id: number | string,
+    result: unknown,
+    error?: { code: number; message: string },
+  ): void {
+    const proc = this.ensureProcess();
+
+    const response: JsonRpcMessage = error
+      ? { jsonrpc: "2.0", id, error }
+      : { jsonrpc: "2.0", id, result };
+
+    const json = JSON.stringify(response);
+    logger.debug("lm-provider", `sending response: ${json}`);
+    proc.stdin?.write(json + "\n");
+  }
+
+  /**
+   * Ask the user whether the agent may run a tool, offering every option
+   * of the agent. Dismissing the dialog answers with no option.
+   */
+  private async promptForPermission(
+    id: number | string,
+    params: PermissionPromptParams,
+  ): Promise<void> {
+    let optionId: string | null = null;
+    try {
+      const input =
+        params.rawInput === undefined
+          ? undefined
+          : JSON.stringify(params.rawInput, null, 2);
+      const detail =
+        input && input.length > 1000 ? `${input.slice(0, 1000)}…` : input;
+      const choice = await vscode.window.showWarningMessage(
+        `The agent wants to run: ${params.title}`,
+        { modal: true, detail },
+        ...params.options.map((option) => ({ title: option.name, option })),
+      );
+      optionId = choice?.option.optionId ?? null;
+    } catch (err) {
+      logger.error("lm-provider", `Permission prompt failed: ${err}`);
+    }
+    this.sendResponse(id, { optionId });
+  }
+
   /**
    * Send a JSON-RPC request and wait for response
    *
@@ -375,7 +548,8 @@ export class SymposiumLanguageModelProvider
     // Convert VS Code messages to our format
     const convertedMessages = messages.map((msg) => ({
       role: this.roleToString(msg.role),
-      content: this.contentToArray(msg.content),
+      // The backend doesn't use data parts in histories, so images aren't sent
+      content: this.contentToArray(msg.content, false),
     }));
 
     // Convert options to our format
@@ -401,6 +575,14 @@ export class SymposiumLanguageModelProvider
           messages: convertedMessages,
           agent: agentDef,
           options: convertedOptions,
+          capabilities: {
+            structuredToolCalls: true,
+            agentUpdates: vscode.workspace
+              .getConfiguration("symposium")
+              .get<string[]>("lmAgentUpdates", ["thoughts", "plan", "mode"]),
+            permissionPrompts: true,
+          },
+          workspace: vscode.workspace.workspaceFolders?.[0]?.uri.fsPath,
         },
         progress,
         token,
@@ -444,13 +626,18 @@ export class SymposiumLanguageModelProvider
     text: string | vscode.LanguageModelChatRequestMessage,
     _token: vscode.CancellationToken,
   ): Promise<number> {
-    const textStr =
-      typeof text === "string" ? text : this.messageToString(text);
+    const input =
+      typeof text === "string"
+        ? text
+        : {
+            role: this.roleToString(text.role),
+            content: this.contentToArray(text.content, true),
+          };
     const result = (await this.sendRequest("lm/provideTokenCount", {
       modelId: model.id,
-      text: textStr,
-    })) as number;
-    return result;
+      text: input,
+    })) as { count: number };
+    return result.count;
   }
 
   /**
@@ -468,43 +655,209 @@ export class SymposiumLanguageModelProvider
   }
 
   /**
-   * Convert message content to array format
+   * Convert message content to array format.
+   *
+   * Data parts (e.g. images) are only included if `includeData` is set.
    */
-  private contentToArray(content: ReadonlyArray<unknown>): ContentPart[] {
-    return content.flatMap((part): ContentPart[] => {
-      if (part instanceof vscode.LanguageModelTextPart) {
-        return [{ type: "text", value: part.value }];
-      }
-      if (part instanceof vscode.LanguageModelToolCallPart) {
-        return [
-          {
-            type: "tool_call",
-            toolCallId: part.callId,
-            toolName: part.name,
-            parameters: part.input,
-          },
-        ];
-      }
-      if (part instanceof vscode.LanguageModelToolResultPart) {
-        return [
-          {
-            type: "tool_result",
-            toolCallId: part.callId,
-            result: part.content,
-          },
-        ];
+  private contentToArray(
+    content: ReadonlyArray<unknown>,
+    includeData: boolean,
+  ): ContentPart[] {
+    const result: ContentPart[] = [];
+    for (const part of content) {
+      if (
+        part instanceof vscode.LanguageModelDataPart &&
+        part.mimeType === DISPLAY_PART_MIMETYPE
+      ) {
+        this.restoreDisplayPart(result, part);
+      } else {
+        result.push(...this.partToArray(part, includeData));
       }
-      // Handle known-but-unsupported VS Code/Copilot internal types
-      if (this.isKnownUnsupportedPart(part)) {
+    }
+    return result;
+  }
+
+  /**
+   * Convert a single message part to our format
+   */
+  private partToArray(part: unknown, includeData: boolean): ContentPart[] {
+    if (part instanceof vscode.LanguageModelTextPart) {
+      return [{ type: "text", value: part.value }];
+    }
+    if (part instanceof vscode.LanguageModelToolCallPart) {
+      return [
+        {
+          type: "tool_call",
+          toolCallId: part.callId,
+          toolName: part.name,
+          parameters: part.input,
+        },
+      ];
+    }
+    if (part instanceof vscode.LanguageModelToolResultPart) {
+      return [
+        {
+          type: "tool_result",
+          toolCallId: part.callId,
+          result: part.content,
+        },
+      ];
+    }
+    // Handle known-but-unsupported VS Code/Copilot internal types
+    if (this.isKnownUnsupportedPart(part)) {
+      return [];
+    }
+    if (part instanceof vscode.LanguageModelDataPart) {
+      if (!includeData) {
         return [];
       }
-      // Log truly unknown parts as errors
-      logger.error("lm", "Skipping unknown message part type", {
-        type: part?.constructor?.name ?? typeof part,
-        json: JSON.stringify(part, null, 2),
-      });
-      return [];
+      return [
+        {
+          type: "data",
+          mimeType: part.mimeType,
+          data: Buffer.from(part.data).toString("base64"),
+        },
+      ];
+    }
+    // Log truly unknown parts as errors
+    logger.error("lm", "Skipping unknown message part type", {
+      type: part?.constructor?.name ?? typeof part,
+      json: JSON.stringify(part, null, 2),
     });
+    return [];
+  }
+
+  /**
+   * Restore a display part we reported in a response, replacing the markdown
+   * it was shown as, so the backend gets back exactly what it sent.
+   */
+  private restoreDisplayPart(
+    result: ContentPart[],
+    dataPart: vscode.LanguageModelDataPart,
+  ): void {
+    let displayPart: DisplayPart;
+    try {
+      displayPart = JSON.parse(Buffer.from(dataPart.data).toString("utf8"));
+    } catch (e) {
+      logger.error("lm", `Failed to parse display part: ${e}`);
+      return;
+    }
+
+    const rendered = this.renderDisplayPart(displayPart);
+    const last = result[result.length - 1];
+    if (last?.type === "text" && last.value.endsWith(rendered)) {
+      const value = last.value.slice(0, last.value.length - rendered.length);
+      if (value) {
+        last.value = value;
+      } else {
+        result.pop();
+      }
+    }
+    result.push(displayPart);
+  }
+
+  /**
+   * Render a display part as markdown for the chat
+   */
+  private renderDisplayPart(part: DisplayPart): string {
+    switch (part.type) {
+      case "tool_progress": {
+        const icon = {
+          pending: "⏳",
+          in_progress: "⚙️",
+          completed: "✅",
+          failed: "❌",
+        }[part.status];
+        let markdown = `${icon ?? "•"} **${part.title}**\n`;
+        if (part.output) {
+          const output = part.output.endsWith("\n")
+            ? part.output
+            : `${part.output}\n`;
+          markdown += `${LONG_FENCE}\n${output}${LONG_FENCE}\n`;
+        }
+        return markdown;
+      }
+      case "diff":
+        return (
+          `${this.fileLink(part.path)}\n${LONG_FENCE}diff\n` +
+          `${this.diffLines(part.oldText ?? "", part.newText)}${LONG_FENCE}\n`
+        );
+      case "file_location":
+        return `📄 ${this.fileLink(part.path, part.line)}\n`;
+      case "thinking": {
+        // A blank line ends the quote before the text that follows
+        const lines = part.value.split("\n").map((line) => `> ${line}\n`);
+        return `> 💭 *Thinking*\n>\n${lines.join("")}\n`;
+      }
+      case "plan": {
+        const entries = part.entries.map((entry) => {
+          const icon = {
+            pending: "⬜",
+            in_progress: "⏳",
+            completed: "✅",
+          }[entry.status];
+          return `- ${icon ?? "•"} ${entry.content}\n`;
+        });
+        return `**Plan**\n${entries.join("")}\n`;
+      }
+      case "mode_change":
+        return `*Switched to mode \`${part.modeId}\`*\n\n`;
+      case "available_commands": {
+        const commands = part.commands.map(
+          (command) => `- \`/${command.name}\`: ${command.description}\n`,
+        );
+        return `**Available commands**\n${commands.join("")}\n`;
+      }
+    }
+  }
+
+  /**
+   * Markdown link opening a file, at a line if given
+   */
+  private fileLink(path: string, line?: number): string {
+    const folder = vscode.workspace.workspaceFolders?.[0]?.uri;
+    let uri =
+      folder && !path.startsWith("/") && !/^[a-zA-Z]:[\\/]/.test(path)
+        ? vscode.Uri.joinPath(folder, path)
+        : vscode.Uri.file(path);
+    let label = vscode.workspace.asRelativePath(uri);
+    if (line !== undefined) {
+      uri = uri.with({ fragment: `L${line}` });
+      label += `:${line}`;
+    }
+    return `[${label}](${uri.toString()})`;
+  }
+
+  /**
+   * The changed lines between two texts, leaving out the lines they start
+   * and end with in common
+   */
+  private diffLines(oldText: string, newText: string): string {
+    const oldLines = oldText ? oldText.replace(/\n$/, "").split("\n") : [];
+    const newLines = newText ? newText.replace(/\n$/, "").split("\n") : [];
+    let start = 0;
+    while (
+      start < oldLines.length &&
+      start < newLines.length &&
+      oldLines[start] === newLines[start]
+    ) {
+      start++;
+    }
+    let end = 0;
+    while (
+      end < oldLines.length - start &&
+      end < newLines.length - start &&
+      oldLines[oldLines.length - 1 - end] === newLines[newLines.length - 1 - end]
+    ) {
+      end++;
+    }
+    const removed = oldLines
+      .slice(start, oldLines.length - end)
+      .map((l) => `-${l}\n`);
+    const added = newLines
+      .slice(start, newLines.length - end)
+      .map((l) => `+${l}\n`);
+    return removed.join("") + added.join("");
   }
 
   /**
@@ -536,27 +889,6 @@ export class SymposiumLanguageModelProvider
     return false;
   }
 
-  /**
-   * Convert a message to string for token counting
-   */
-  private messageToString(msg: vscode.LanguageModelChatRequestMessage): string {
-    return msg.content
-      .map((part) => {
-        if (part instanceof vscode.LanguageModelTextPart) {
-          return part.value;
-        }
-        if (part instanceof vscode.LanguageModelToolCallPart) {
-          return `[tool:${part.name}]`;
-        }
-        if (part instanceof vscode.LanguageModelToolResultPart) {
-          return "[tool_result]";
-        }
-        // Skip unknown parts for token counting
-        return "";
-      })
-      .join("");
-  }
-
   /**
    * Install an agent from the registry by ID.
    * Fetches the registry, finds the agent, and adds it to settings.